@@ -1,8 +1,11 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{self, TokenInterface};
 use sha2::{Sha256, Digest};
 
-declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+declare_id!("B1cMe9xsGaZrcikvGi73R4cL8vraxQvKwxuiSN8XXVP2");
 
 #[program]
 pub mod minimal {
@@ -13,146 +16,2006 @@ pub mod minimal {
         let user_profile = &mut ctx.accounts.user_profile;
         user_profile.owner = ctx.accounts.owner.key();
         user_profile.created_at = Clock::get()?.unix_timestamp;
-        user_profile.active = true
+        user_profile.active = true;
+        user_profile.proof_count = 0;
+        user_profile.latest_proof = Pubkey::default();
+        user_profile.verified_height = 0;
+        user_profile.total_work = 0;
+        user_profile.username = String::new();
+        user_profile.last_proof_at = 0;
+        user_profile.total_transferred = 0;
+        user_profile.total_fees_paid = 0;
+        user_profile.lamports_transferred = 0;
+        user_profile.lamports_fees_paid = 0;
+        user_profile.metadata_uri = None;
+        user_profile.pending_owner = None;
+        user_profile.migrated_from = Pubkey::default();
+        user_profile.open_escrows = 0;
+        user_profile.open_allowances = 0;
         Ok(())
     }
 
     // Update user profile status
     pub fn update_status(ctx: Context<UpdateStatus>, active: bool) -> Result<()> {
         let user_profile = &mut ctx.accounts.user_profile;
-        require!(user_profile.owner == ctx.accounts.owner.key(), ErrorCode::Unauthorized);
-        
         user_profile.active = active;
         user_profile.updated_at = Clock::get()?.unix_timestamp;
+        emit!(StatusUpdated {
+            owner: user_profile.owner,
+            user_profile: user_profile.key(),
+            active,
+            timestamp: user_profile.updated_at,
+        });
         Ok(())
     }
 
-    // Process token interaction
+    // Process token interaction: the recipient gets `amount` net of the
+    // interaction fee, which goes to the treasury. Works with SPL Token and
+    // Token-2022 mints; a mint's own transfer fee is withheld on top, so the
+    // profile records what actually arrived.
     pub fn process_interaction(ctx: Context<ProcessInteraction>, amount: u64) -> Result<()> {
+        let fee = bps_fee(amount, ctx.accounts.config.interaction_fee_bps as u64)?;
+        let accounts = &mut *ctx.accounts;
+
         // Transfer tokens
+        let delivered = transfer_checked_received(
+            &accounts.token_program,
+            &accounts.from,
+            &accounts.mint,
+            &mut accounts.to,
+            &accounts.owner,
+            amount - fee,
+        )?;
+        let fee_received = if fee > 0 {
+            transfer_checked_received(
+                &accounts.token_program,
+                &accounts.from,
+                &accounts.mint,
+                &mut accounts.treasury_token,
+                &accounts.owner,
+                fee,
+            )?
+        } else {
+            0
+        };
+
+        let user_profile = &mut accounts.user_profile;
+        user_profile.total_transferred = user_profile.total_transferred.saturating_add(delivered);
+        user_profile.total_fees_paid = user_profile.total_fees_paid.saturating_add(fee_received);
+        emit!(InteractionProcessed {
+            owner: accounts.owner.key(),
+            from: accounts.from.key(),
+            to: accounts.to.key(),
+            mint: accounts.mint.key(),
+            amount: delivered,
+            fee: fee_received,
+        });
+        Ok(())
+    }
+
+    // Submit a proof of hash
+    pub fn submit_proof(ctx: Context<SubmitProof>, data_hash: [u8; 32], nonce: u64) -> Result<()> {
+        require!(!ctx.accounts.config.commit_reveal_required, ErrorCode::CommitRequired);
+        accept_proof(ctx.accounts, ctx.bumps.config, data_hash, nonce)
+    }
+
+    // Verify chain of proofs
+    pub fn verify_chain(ctx: Context<VerifyChain>, previous_proof: Pubkey) -> Result<()> {
+        let previous = &ctx.accounts.previous_proof;
+        let current = &ctx.accounts.current_proof;
+        require!(previous.key() == previous_proof, ErrorCode::InvalidChain);
+        check_proof_link(previous, current, &ctx.accounts.config.chain_target)?;
+        emit!(ChainVerified {
+            owner: ctx.accounts.owner.key(),
+            first_proof: previous_proof,
+            last_proof: current.key(),
+            first_height: previous.height,
+            last_height: current.height,
+        });
+        Ok(())
+    }
+
+    // Verify a run of the owner's proofs, passed in height order as
+    // remaining_accounts, in one pass. The run must start at or below the
+    // profile's verified_height, which then advances to its last proof so
+    // every proof up to verified_height has a checked link.
+    pub fn verify_chain_segment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VerifyChainSegment<'info>>,
+    ) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let chain_target = ctx.accounts.config.chain_target;
+        let user_profile = &mut ctx.accounts.user_profile;
+        let migrated_from = user_profile.migrated_from;
+        require!(ctx.remaining_accounts.len() >= 2, ErrorCode::InvalidChain);
+
+        let mut first: Option<(Pubkey, u64)> = None;
+        let mut previous: Option<Account<ProofData>> = None;
+        for info in ctx.remaining_accounts.iter() {
+            let proof = Account::<ProofData>::try_from(info)?;
+            // The address pins the proof to this owner's chain at its height,
+            // including proofs made under the key the profile migrated from
+            require!(
+                proof.key() == find_proof_address(&owner, proof.height).0
+                    || proof.key() == find_proof_address(&migrated_from, proof.height).0,
+                ErrorCode::InvalidChain
+            );
+            match &previous {
+                Some(previous) => check_proof_link(previous, &proof, &chain_target)?,
+                None => {
+                    require!(
+                        proof.height <= user_profile.verified_height,
+                        ErrorCode::InvalidChain
+                    );
+                    first = Some((proof.key(), proof.height));
+                }
+            }
+            previous = Some(proof);
+        }
+
+        // At least two proofs were walked, so both ends are set
+        let (first_proof, first_height) = first.unwrap();
+        let last = previous.unwrap();
+        user_profile.verified_height = user_profile.verified_height.max(last.height);
+        emit!(ChainVerified {
+            owner,
+            first_proof,
+            last_proof: last.key(),
+            first_height,
+            last_height: last.height,
+        });
+        Ok(())
+    }
+
+    // View: whether the owner's profile is active (returned via return data for CPI callers)
+    pub fn is_user_active(ctx: Context<IsUserActive>, _owner: Pubkey) -> Result<bool> {
+        Ok(ctx.accounts.user_profile.active)
+    }
+
+    // View: whether the proof account is a verified proof of `data_hash` by `owner`
+    pub fn has_verified_proof(
+        ctx: Context<HasVerifiedProof>,
+        owner: Pubkey,
+        data_hash: [u8; 32],
+    ) -> Result<bool> {
+        let proof = &ctx.accounts.proof;
+        let now = Clock::get()?.unix_timestamp;
+        Ok(proof.is_live(now) && proof.owner == owner && proof.data_hash == data_hash)
+    }
+
+    // List a verified proof for sale at `price` fee units, paid in
+    // `payment_mint` at its fee table rate when bought
+    pub fn list_proof_for_sale(ctx: Context<ListProofForSale>, price: u64) -> Result<()> {
+        let proof = &ctx.accounts.proof;
+        require!(proof.verified && !proof.consumed, ErrorCode::InvalidProof);
+        require!(proof.is_live(Clock::get()?.unix_timestamp), ErrorCode::ProofExpired);
+        require!(price > 0, ErrorCode::InvalidPrice);
+        require!(
+            ctx.accounts.fee_tokens.rate_for(&ctx.accounts.payment_mint.key()).is_some(),
+            ErrorCode::UnsupportedFeeToken
+        );
+
+        let listing = &mut ctx.accounts.listing;
+        listing.proof = proof.key();
+        listing.seller = ctx.accounts.seller.key();
+        listing.payment_mint = ctx.accounts.payment_mint.key();
+        listing.price = price;
+        Ok(())
+    }
+
+    // Buy a listed proof: converts the price into the payment mint at the
+    // current rate, pays the seller net of the protocol fee, pays the fee to
+    // the treasury, and hands proof ownership to the buyer
+    pub fn buy_proof(ctx: Context<BuyProof>) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+        let proof = &mut ctx.accounts.proof;
+
+        // A listing is void once the proof has changed hands or been spent
+        require!(proof.owner == listing.seller, ErrorCode::ListingMismatch);
+        require!(proof.verified && !proof.consumed, ErrorCode::InvalidProof);
+        require!(proof.is_live(Clock::get()?.unix_timestamp), ErrorCode::ProofExpired);
+        // Mints can be delisted or repriced while a sale is open
+        let amount = ctx.accounts.fee_tokens.convert(listing.price, &listing.payment_mint)?;
+        require!(amount > 0, ErrorCode::InvalidPrice);
+
+        let fee = protocol_fee(amount)?;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.buyer_token.to_account_info(),
+                    to: ctx.accounts.seller_token.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            ),
+            amount - fee,
+        )?;
+        if fee > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.buyer_token.to_account_info(),
+                        to: ctx.accounts.treasury_token.to_account_info(),
+                        authority: ctx.accounts.buyer.to_account_info(),
+                    },
+                ),
+                fee,
+            )?;
+        }
+
+        proof.owner = ctx.accounts.buyer.key();
+        Ok(())
+    }
+
+    // Withdraw a listing; its rent goes back to the seller
+    pub fn cancel_listing(_ctx: Context<CancelListing>) -> Result<()> {
+        Ok(())
+    }
+
+    // Create the fee token table; the signer becomes its admin
+    pub fn initialize_fee_tokens(ctx: Context<InitializeFeeTokens>) -> Result<()> {
+        let fee_tokens = &mut ctx.accounts.fee_tokens;
+        fee_tokens.admin = ctx.accounts.admin.key();
+        fee_tokens.entries = Vec::new();
+        Ok(())
+    }
+
+    // Accept `mint` for fee payment at `rate` (see FeeTokenTable::convert), or update its rate
+    pub fn set_fee_token(ctx: Context<UpdateFeeTokens>, mint: Pubkey, rate: u64) -> Result<()> {
+        require!(rate > 0, ErrorCode::InvalidPrice);
+        let fee_tokens = &mut ctx.accounts.fee_tokens;
+        match fee_tokens.entries.iter_mut().find(|entry| entry.mint == mint) {
+            Some(entry) => entry.rate = rate,
+            None => {
+                require!(
+                    fee_tokens.entries.len() < FeeTokenTable::MAX_ENTRIES,
+                    ErrorCode::FeeTokenTableFull
+                );
+                fee_tokens.entries.push(FeeToken { mint, rate });
+            }
+        }
+        Ok(())
+    }
+
+    // Stop accepting `mint` for fee payment
+    pub fn remove_fee_token(ctx: Context<UpdateFeeTokens>, mint: Pubkey) -> Result<()> {
+        let fee_tokens = &mut ctx.accounts.fee_tokens;
+        let before = fee_tokens.entries.len();
+        fee_tokens.entries.retain(|entry| entry.mint != mint);
+        require!(fee_tokens.entries.len() < before, ErrorCode::UnsupportedFeeToken);
+        Ok(())
+    }
+
+    // Create the program config; the signer becomes its admin. Targets are
+    // big-endian 256-bit values (see meets_target).
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        proof_target: [u8; 32],
+        chain_target: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            proof_target != [0; 32] && chain_target != [0; 32],
+            ErrorCode::InvalidDifficulty
+        );
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.proof_target = proof_target;
+        config.chain_target = chain_target;
+        config.target_proofs_per_epoch = 0;
+        config.epoch_seconds = 0;
+        config.epoch_start = 0;
+        config.epoch_proofs = 0;
+        config.total_work = 0;
+        config.reward_mint = Pubkey::default();
+        config.initial_reward = 0;
+        config.halving_interval = 0;
+        config.epoch_reward_cap = 0;
+        config.epoch_rewards = 0;
+        config.rewarded_proofs = 0;
+        config.min_proof_age = 0;
+        config.proof_lifetime = 0;
+        config.interaction_fee_bps = 0;
+        config.stake_mint = Pubkey::default();
+        config.stake_unit = 0;
+        config.commit_reveal_required = false;
+        Ok(())
+    }
+
+    // Retune the targets submit_proof and verify_chain hashes must not exceed
+    pub fn set_difficulty(
+        ctx: Context<UpdateConfig>,
+        proof_target: [u8; 32],
+        chain_target: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            proof_target != [0; 32] && chain_target != [0; 32],
+            ErrorCode::InvalidDifficulty
+        );
+        let config = &mut ctx.accounts.config;
+        config.proof_target = proof_target;
+        config.chain_target = chain_target;
+        Ok(())
+    }
+
+    // Retarget proof_target every `epoch_seconds` toward
+    // `target_proofs_per_epoch` accepted proofs. A zero target turns
+    // retargeting off; epochs still roll over for the reward cap, which
+    // needs them to.
+    pub fn set_retarget(
+        ctx: Context<UpdateConfig>,
+        target_proofs_per_epoch: u64,
+        epoch_seconds: i64,
+    ) -> Result<()> {
+        require!(
+            target_proofs_per_epoch == 0 || epoch_seconds > 0,
+            ErrorCode::InvalidDifficulty
+        );
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.epoch_reward_cap == 0 || epoch_seconds > 0,
+            ErrorCode::InvalidRewardSchedule
+        );
+        config.target_proofs_per_epoch = target_proofs_per_epoch;
+        config.epoch_seconds = epoch_seconds;
+        config.epoch_start = Clock::get()?.unix_timestamp;
+        config.epoch_proofs = 0;
+        config.epoch_rewards = 0;
+        Ok(())
+    }
+
+    // Spend a verified proof. Other programs gate actions on a proof by
+    // consuming it via CPI with the owner's signature.
+    pub fn consume_proof(ctx: Context<ConsumeProof>) -> Result<()> {
+        let proof = &mut ctx.accounts.proof;
+        require!(proof.verified && !proof.consumed, ErrorCode::InvalidProof);
+        require!(proof.is_live(Clock::get()?.unix_timestamp), ErrorCode::ProofExpired);
+        proof.consumed = true;
+        Ok(())
+    }
+
+    // Commit a Merkle root over `count` proofs (see batch_leaf_hash) in one
+    // account. The leaves aren't checked here, so the claimed count adds
+    // nothing to epoch_proofs or total_work; verify_batch_leaf spot-checks
+    // them and challenge_batch_leaf marks fraudulent and slashes a batch
+    // with a leaf that misses its target.
+    pub fn submit_proof_batch(ctx: Context<SubmitProofBatch>, root: [u8; 32], count: u32) -> Result<()> {
+        require!(count > 0 && count <= MAX_BATCH_PROOFS, ErrorCode::InvalidBatch);
+        let clock = Clock::get()?;
+        let config = &mut ctx.accounts.config;
+        config.retarget(clock.unix_timestamp);
+
+        let batch = &mut ctx.accounts.batch;
+        batch.owner = ctx.accounts.owner.key();
+        batch.root = root;
+        batch.count = count;
+        batch.proof_target = config.proof_target;
+        batch.timestamp = clock.unix_timestamp;
+        batch.fraudulent = false;
+        batch.salt = ctx.accounts.challenge.salt;
+        Ok(())
+    }
+
+    // View: check `leaf` is in the batch via its Merkle `path` (leaf to
+    // root) and that its work hash meets the batch's target. Returns whether
+    // it does; a leaf that is in the tree but misses the target can be
+    // taken to challenge_batch_leaf.
+    pub fn verify_batch_leaf(
+        ctx: Context<VerifyBatchLeaf>,
+        leaf: BatchLeaf,
+        path: Vec<[u8; 32]>,
+    ) -> Result<bool> {
+        ctx.accounts.batch.check_leaf(&leaf, &path)
+    }
+
+    // Create the reward mint. Its mint authority is the config PDA, so only
+    // submit_proof can issue rewards.
+    pub fn initialize_reward_mint(ctx: Context<InitializeRewardMint>, _decimals: u8) -> Result<()> {
+        ctx.accounts.config.reward_mint = ctx.accounts.reward_mint.key();
+        Ok(())
+    }
+
+    // Set the emission schedule: each accepted proof mints `initial_reward`,
+    // halved every `halving_interval` rewarded proofs (0 never halves), with
+    // at most `epoch_reward_cap` minted per epoch (0 is uncapped). A cap
+    // needs epochs that roll over, so set_retarget's epoch_seconds first.
+    pub fn set_reward_schedule(
+        ctx: Context<UpdateConfig>,
+        initial_reward: u64,
+        halving_interval: u64,
+        epoch_reward_cap: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            epoch_reward_cap == 0 || config.epoch_seconds > 0,
+            ErrorCode::InvalidRewardSchedule
+        );
+        config.initial_reward = initial_reward;
+        config.halving_interval = halving_interval;
+        config.epoch_reward_cap = epoch_reward_cap;
+        Ok(())
+    }
+
+    // Minimum age, in seconds, before an unconsumed proof may be closed
+    pub fn set_min_proof_age(ctx: Context<UpdateConfig>, min_proof_age: i64) -> Result<()> {
+        require!(min_proof_age >= 0, ErrorCode::InvalidDifficulty);
+        ctx.accounts.config.min_proof_age = min_proof_age;
+        Ok(())
+    }
+
+    // Close a proof and return its rent to the owner, once it's consumed,
+    // expired, revoked or at least min_proof_age old. Its chain links can no
+    // longer be verified.
+    pub fn close_proof(ctx: Context<CloseProof>) -> Result<()> {
+        let proof = &ctx.accounts.proof;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            proof.consumed
+                || !proof.is_live(now)
+                || now >= proof.timestamp + ctx.accounts.config.min_proof_age,
+            ErrorCode::ProofTooYoung
+        );
+        Ok(())
+    }
+
+    // Seconds a new proof stays usable; 0 never expires. Proofs keep the
+    // expiry they were submitted with.
+    pub fn set_proof_lifetime(ctx: Context<UpdateConfig>, proof_lifetime: i64) -> Result<()> {
+        require!(proof_lifetime >= 0, ErrorCode::InvalidDifficulty);
+        ctx.accounts.config.proof_lifetime = proof_lifetime;
+        Ok(())
+    }
+
+    // Admin: withdraw a proof so it no longer gates, sells or counts
+    pub fn revoke_proof(ctx: Context<RevokeProof>) -> Result<()> {
+        ctx.accounts.proof.revoked = true;
+        Ok(())
+    }
+
+    // Create the top-K leaderboard submit_proof maintains
+    pub fn initialize_leaderboard(ctx: Context<InitializeLeaderboard>) -> Result<()> {
+        ctx.accounts.leaderboard.entries = Vec::new();
+        Ok(())
+    }
+
+    // Reserve `name` for the signer and show it on their profile. Names are
+    // normalized (see normalize_username) and unique; a previous name must be
+    // passed as `previous_record` and is released.
+    pub fn set_username(ctx: Context<SetUsername>, name: String) -> Result<()> {
+        let name = normalize_username(&name)?;
+        let user_profile = &mut ctx.accounts.user_profile;
+        if !user_profile.username.is_empty() {
+            let previous = ctx
+                .accounts
+                .previous_record
+                .as_ref()
+                .ok_or(ErrorCode::InvalidUsername)?;
+            require!(previous.name == user_profile.username, ErrorCode::InvalidUsername);
+            previous.close(ctx.accounts.owner.to_account_info())?;
+        }
+
+        let record = &mut ctx.accounts.username_record;
+        record.owner = ctx.accounts.owner.key();
+        record.name = name.clone();
+        user_profile.username = name;
+        user_profile.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Point the profile at off-chain metadata (avatar, bio, links), or clear it
+    pub fn set_profile_metadata(ctx: Context<SetProfileMetadata>, metadata_uri: Option<String>) -> Result<()> {
+        if let Some(uri) = &metadata_uri {
+            require!(
+                !uri.is_empty() && uri.len() <= MAX_METADATA_URI_LEN,
+                ErrorCode::InvalidMetadataUri
+            );
+        }
+        let user_profile = &mut ctx.accounts.user_profile;
+        user_profile.metadata_uri = metadata_uri;
+        user_profile.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Close the signer's profile and refund its rent. Open escrows and
+    // allowances and a non-empty stake block it; a reserved username's
+    // record must be passed and is released too. A profile that has
+    // submitted proofs stays open: a new one would number its proofs from 0
+    // again, onto addresses the old proofs still hold.
+    pub fn close_user_profile(ctx: Context<CloseUserProfile>) -> Result<()> {
+        let user_profile = &ctx.accounts.user_profile;
+        require!(
+            user_profile.open_escrows == 0
+                && user_profile.open_allowances == 0
+                && user_profile.proof_count == 0,
+            ErrorCode::ProfileHasObligations
+        );
+        let stake = &ctx.accounts.stake;
+        require!(
+            stake.data_is_empty() || Stake::try_deserialize(&mut &stake.try_borrow_data()?[..])?.amount == 0,
+            ErrorCode::ProfileHasObligations
+        );
+        if !user_profile.username.is_empty() {
+            let record = ctx
+                .accounts
+                .username_record
+                .as_ref()
+                .ok_or(ErrorCode::ProfileHasObligations)?;
+            require!(record.name == user_profile.username, ErrorCode::ProfileHasObligations);
+            record.close(ctx.accounts.owner.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    // Offer the profile to another wallet, or withdraw the offer with None
+    pub fn propose_owner(ctx: Context<ProposeOwner>, new_owner: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.user_profile.pending_owner = new_owner;
+        Ok(())
+    }
+
+    // Take over a profile offered by propose_owner. The profile moves to the
+    // new owner's address with its proof history and username. Proofs already
+    // submitted stay at addresses derived from the old key, which the new
+    // profile records as migrated_from. Escrows and allowances are settled
+    // against the old owner's profile, so they must be closed first.
+    pub fn accept_owner(ctx: Context<AcceptOwner>) -> Result<()> {
+        let old_profile = &ctx.accounts.old_profile;
+        let new_owner = ctx.accounts.new_owner.key();
+        require!(old_profile.pending_owner == Some(new_owner), ErrorCode::Unauthorized);
+        require!(
+            old_profile.open_escrows == 0 && old_profile.open_allowances == 0,
+            ErrorCode::ProfileHasObligations
+        );
+
+        if !old_profile.username.is_empty() {
+            let record = ctx
+                .accounts
+                .username_record
+                .as_mut()
+                .ok_or(ErrorCode::InvalidUsername)?;
+            require!(record.name == old_profile.username, ErrorCode::InvalidUsername);
+            record.owner = new_owner;
+        }
+
+        let new_profile = &mut ctx.accounts.new_profile;
+        new_profile.set_inner(UserProfile {
+            owner: new_owner,
+            updated_at: Clock::get()?.unix_timestamp,
+            pending_owner: None,
+            migrated_from: old_profile.owner,
+            ..(**old_profile).clone()
+        });
+        Ok(())
+    }
+
+    // Lock `amount` of the owner's tokens in a vault owned by the escrow PDA
+    // until the owner releases them to `recipient`, or takes them back once
+    // `deadline` has passed
+    pub fn create_escrow(
+        ctx: Context<CreateEscrow>,
+        escrow_id: u64,
+        recipient: Pubkey,
+        amount: u64,
+        deadline: i64,
+    ) -> Result<()> {
+        require!(
+            amount > 0 && deadline > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidEscrow
+        );
         token::transfer(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
                 token::Transfer {
                     from: ctx.accounts.from.to_account_info(),
-                    to: ctx.accounts.to.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
                     authority: ctx.accounts.owner.to_account_info(),
                 },
             ),
             amount,
         )?;
 
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.owner = ctx.accounts.owner.key();
+        escrow.recipient = recipient;
+        escrow.mint = ctx.accounts.mint.key();
+        escrow.amount = amount;
+        escrow.deadline = deadline;
+        escrow.escrow_id = escrow_id;
+        ctx.accounts.user_profile.open_escrows += 1;
         Ok(())
     }
 
-    // Submit a proof of hash
-    pub fn submit_proof(ctx: Context<SubmitProof>, data_hash: [u8; 32], nonce: u64) -> Result<()> {
-        let proof = &mut ctx.accounts.proof;
-        let clock = Clock::get()?;
+    // Owner: pay the escrowed tokens to the recipient
+    pub fn release_escrow(ctx: Context<SettleEscrow>) -> Result<()> {
+        require!(
+            ctx.accounts.to.owner == ctx.accounts.escrow.recipient,
+            ErrorCode::InvalidEscrow
+        );
+        ctx.accounts.user_profile.open_escrows -= 1;
+        settle_escrow(&ctx)
+    }
 
-        // Verify the hash meets difficulty requirement
+    // Owner: take the escrowed tokens back after the deadline
+    pub fn refund_escrow(ctx: Context<SettleEscrow>) -> Result<()> {
         require!(
-            verify_hash_difficulty(&data_hash, 3), // Require 3 leading zeros
-            ErrorCode::InvalidProof
+            Clock::get()?.unix_timestamp >= ctx.accounts.escrow.deadline,
+            ErrorCode::EscrowNotExpired
         );
+        require!(
+            ctx.accounts.to.owner == ctx.accounts.escrow.owner,
+            ErrorCode::InvalidEscrow
+        );
+        ctx.accounts.user_profile.open_escrows -= 1;
+        settle_escrow(&ctx)
+    }
+
+    // Fee on process_interaction, in basis points
+    pub fn set_interaction_fee(ctx: Context<UpdateConfig>, interaction_fee_bps: u16) -> Result<()> {
+        require!(interaction_fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidPrice);
+        ctx.accounts.config.interaction_fee_bps = interaction_fee_bps;
+        Ok(())
+    }
 
-        proof.owner = ctx.accounts.owner.key();
-        proof.data_hash = data_hash;
-        proof.nonce = nonce;
-        proof.timestamp = clock.unix_timestamp;
-        proof.verified = true;
+    // Let `delegate` move up to `limit` tokens out of `token_account` via
+    // process_delegated_interaction until `expiry`. The token account
+    // approves this allowance's own spend authority PDA for `limit`, so one
+    // delegate can't spend through another's approval. SPL accounts hold one
+    // approval, so this replaces any earlier one on the same account.
+    pub fn approve_delegate(
+        ctx: Context<ApproveDelegate>,
+        delegate: Pubkey,
+        limit: u64,
+        expiry: i64,
+    ) -> Result<()> {
+        require!(
+            limit > 0 && expiry > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidAllowance
+        );
+        token::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Approve {
+                    to: ctx.accounts.token_account.to_account_info(),
+                    delegate: ctx.accounts.spend_authority.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            limit,
+        )?;
 
+        let allowance = &mut ctx.accounts.allowance;
+        if allowance.owner == Pubkey::default() {
+            ctx.accounts.user_profile.open_allowances += 1;
+        }
+        allowance.owner = ctx.accounts.owner.key();
+        allowance.delegate = delegate;
+        allowance.token_account = ctx.accounts.token_account.key();
+        allowance.limit = limit;
+        allowance.spent = 0;
+        allowance.expiry = expiry;
         Ok(())
     }
 
-    // Verify chain of proofs
-    pub fn verify_chain(ctx: Context<VerifyChain>, previous_proof: Pubkey) -> Result<()> {
-        let current_proof = &ctx.accounts.current_proof;
-        let previous = &ctx.accounts.previous_proof;
+    // Withdraw a delegate's allowance, and the token account's SPL approval
+    // if it is still this allowance's rather than a later one's
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        ctx.accounts.user_profile.open_allowances -= 1;
+        if ctx.accounts.token_account.delegate != COption::Some(ctx.accounts.spend_authority.key()) {
+            return Ok(());
+        }
+        token::revoke(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Revoke {
+                source: ctx.accounts.token_account.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ))
+    }
 
-        // Verify chronological order
+    // process_interaction signed by a delegate within its allowance
+    pub fn process_delegated_interaction(
+        ctx: Context<ProcessDelegatedInteraction>,
+        amount: u64,
+    ) -> Result<()> {
+        let allowance = &mut ctx.accounts.allowance;
         require!(
-            previous.timestamp < current_proof.timestamp,
-            ErrorCode::InvalidChain
+            Clock::get()?.unix_timestamp < allowance.expiry
+                && allowance.spent.saturating_add(amount) <= allowance.limit,
+            ErrorCode::InvalidAllowance
         );
+        allowance.spent += amount;
 
-        // Verify hash chain
-        let mut hasher = Sha256::new();
-        hasher.update(previous.data_hash);
-        hasher.update(current_proof.data_hash);
-        let chain_hash = hasher.finalize();
+        let fee = bps_fee(amount, ctx.accounts.config.interaction_fee_bps as u64)?;
+        let (owner, delegate) = (allowance.owner, allowance.delegate);
+        let seeds: &[&[u8]] = &[
+            b"spend-authority",
+            owner.as_ref(),
+            delegate.as_ref(),
+            &[ctx.bumps.spend_authority],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.from.to_account_info(),
+                    to: ctx.accounts.to.to_account_info(),
+                    authority: ctx.accounts.spend_authority.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount - fee,
+        )?;
+        if fee > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.from.to_account_info(),
+                        to: ctx.accounts.treasury_token.to_account_info(),
+                        authority: ctx.accounts.spend_authority.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                fee,
+            )?;
+        }
+
+        let user_profile = &mut ctx.accounts.user_profile;
+        user_profile.total_transferred = user_profile.total_transferred.saturating_add(amount - fee);
+        user_profile.total_fees_paid = user_profile.total_fees_paid.saturating_add(fee);
+        emit!(InteractionProcessed {
+            owner,
+            from: ctx.accounts.from.key(),
+            to: ctx.accounts.to.key(),
+            mint: ctx.accounts.from.mint,
+            amount: amount - fee,
+            fee,
+        });
+        Ok(())
+    }
+
+    // process_interaction in native SOL: the recipient gets `amount` lamports
+    // net of the interaction fee, which goes to the treasury PDA
+    pub fn process_sol_interaction(ctx: Context<ProcessSolInteraction>, amount: u64) -> Result<()> {
+        let fee = bps_fee(amount, ctx.accounts.config.interaction_fee_bps as u64)?;
+        let system = ctx.accounts.system_program.to_account_info();
+        system_program::transfer(
+            CpiContext::new(
+                system.clone(),
+                system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.to.to_account_info(),
+                },
+            ),
+            amount - fee,
+        )?;
+        if fee > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system,
+                    system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: ctx.accounts.treasury.to_account_info(),
+                    },
+                ),
+                fee,
+            )?;
+        }
+
+        let user_profile = &mut ctx.accounts.user_profile;
+        user_profile.lamports_transferred = user_profile.lamports_transferred.saturating_add(amount - fee);
+        user_profile.lamports_fees_paid = user_profile.lamports_fees_paid.saturating_add(fee);
+        emit!(InteractionProcessed {
+            owner: ctx.accounts.owner.key(),
+            from: ctx.accounts.owner.key(),
+            to: ctx.accounts.to.key(),
+            mint: Pubkey::default(),
+            amount: amount - fee,
+            fee,
+        });
+        Ok(())
+    }
+
+    // Pay `amount` of collected interaction fees out of a treasury token
+    // account. Admin only.
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let seeds: &[&[u8]] = &[b"treasury", &[ctx.bumps.treasury]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::TransferChecked {
+                    from: ctx.accounts.treasury_token.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.to.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )
+    }
 
-        // Verify chain hash meets difficulty
+    // Pay `amount` lamports of SOL interaction fees out of the treasury PDA.
+    // Admin only; the treasury must stay rent exempt or be emptied.
+    pub fn withdraw_treasury_sol(ctx: Context<WithdrawTreasurySol>, amount: u64) -> Result<()> {
+        let seeds: &[&[u8]] = &[b"treasury", &[ctx.bumps.treasury]];
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: ctx.accounts.to.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )
+    }
+
+    // Pay several recipients at once: `amounts[i]` goes to the i-th
+    // remaining account (a token account of `mint`). Recipients get their
+    // amounts in full; one interaction fee on the total is charged on top.
+    // All transfers land or none do.
+    pub fn process_interactions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessInteractions<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
         require!(
-            verify_hash_difficulty(&chain_hash.into(), 2), // Chain requires 2 leading zeros
-            ErrorCode::InvalidChain
+            !amounts.is_empty()
+                && amounts.len() <= MAX_BATCH_RECIPIENTS
+                && amounts.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidRecipients
         );
+        let total = amounts
+            .iter()
+            .try_fold(0u64, |total, amount| total.checked_add(*amount))
+            .ok_or(ErrorCode::Overflow)?;
+        let fee = bps_fee(total, ctx.accounts.config.interaction_fee_bps as u64)?;
+        let accounts = &mut *ctx.accounts;
+
+        let mut delivered = 0u64;
+        let mut deliveries = Vec::with_capacity(amounts.len());
+        for (info, amount) in ctx.remaining_accounts.iter().zip(amounts) {
+            let mut to = InterfaceAccount::<token_interface::TokenAccount>::try_from(info)?;
+            require!(to.mint == accounts.mint.key(), ErrorCode::InvalidRecipients);
+            let received = transfer_checked_received(
+                &accounts.token_program,
+                &accounts.from,
+                &accounts.mint,
+                &mut to,
+                &accounts.owner,
+                amount,
+            )?;
+            delivered += received;
+            deliveries.push((to.key(), received));
+        }
+        let fee_received = if fee > 0 {
+            transfer_checked_received(
+                &accounts.token_program,
+                &accounts.from,
+                &accounts.mint,
+                &mut accounts.treasury_token,
+                &accounts.owner,
+                fee,
+            )?
+        } else {
+            0
+        };
 
+        let user_profile = &mut accounts.user_profile;
+        user_profile.total_transferred = user_profile.total_transferred.saturating_add(delivered);
+        user_profile.total_fees_paid = user_profile.total_fees_paid.saturating_add(fee_received);
+        // One event per recipient; the batch's single fee is on the last
+        let last = deliveries.len() - 1;
+        for (i, (to, amount)) in deliveries.into_iter().enumerate() {
+            emit!(InteractionProcessed {
+                owner: accounts.owner.key(),
+                from: accounts.from.key(),
+                to,
+                mint: accounts.mint.key(),
+                amount,
+                fee: if i == last { fee_received } else { 0 },
+            });
+        }
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct InitializeUser<'info> {
-    #[account(
-        init,
-        payer = owner,
-        space = UserProfile::LEN,
-        seeds = [b"user-profile", owner.key().as_ref()],
-        bump
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    // Lock `total` tokens for `beneficiary`, vesting linearly over `duration`
+    // seconds from now with nothing claimable before `cliff` seconds
+    pub fn create_vesting(
+        ctx: Context<CreateVesting>,
+        beneficiary: Pubkey,
+        total: u64,
+        cliff: i64,
+        duration: i64,
+    ) -> Result<()> {
+        require!(
+            total > 0 && duration > 0 && (0..=duration).contains(&cliff),
+            ErrorCode::InvalidVesting
+        );
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.from.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            ),
+            total,
+        )?;
 
-#[derive(Accounts)]
-pub struct UpdateStatus<'info> {
-    #[account(mut)]
-    pub user_profile: Account<'info, UserProfile>,
-    pub owner: Signer<'info>,
+        let vesting = &mut ctx.accounts.vesting;
+        vesting.funder = ctx.accounts.funder.key();
+        vesting.beneficiary = beneficiary;
+        vesting.mint = ctx.accounts.mint.key();
+        vesting.total = total;
+        vesting.claimed = 0;
+        vesting.start = Clock::get()?.unix_timestamp;
+        vesting.cliff = cliff;
+        vesting.duration = duration;
+        Ok(())
+    }
+
+    // Beneficiary: withdraw everything vested so far
+    pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+        let vesting = &ctx.accounts.vesting;
+        let claimable = vesting.vested(Clock::get()?.unix_timestamp) - vesting.claimed;
+        require!(claimable > 0, ErrorCode::NothingVested);
+
+        let funder = vesting.funder;
+        let beneficiary = vesting.beneficiary;
+        let seeds: &[&[u8]] = &[
+            b"vesting",
+            funder.as_ref(),
+            beneficiary.as_ref(),
+            &[ctx.bumps.vesting],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.to.to_account_info(),
+                    authority: vesting.to_account_info(),
+                },
+                &[seeds],
+            ),
+            claimable,
+        )?;
+        ctx.accounts.vesting.claimed += claimable;
+        Ok(())
+    }
+
+    // Choose the token staked for proof bonuses and how much makes a unit
+    pub fn set_staking(ctx: Context<UpdateConfig>, stake_mint: Pubkey, stake_unit: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.stake_mint = stake_mint;
+        config.stake_unit = stake_unit;
+        Ok(())
+    }
+
+    // Lock `amount` more of the stake mint in the signer's stake vault. Each
+    // stake restarts the STAKE_LOCK_SECONDS lock on the whole stake.
+    pub fn stake(ctx: Context<StakeTokens>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidStake);
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.from.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let stake = &mut ctx.accounts.stake;
+        stake.owner = ctx.accounts.owner.key();
+        stake.mint = ctx.accounts.mint.key();
+        stake.amount += amount;
+        stake.staked_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Withdraw `amount` of an unlocked stake
+    pub fn unstake(ctx: Context<UnstakeTokens>, amount: u64) -> Result<()> {
+        let stake = &ctx.accounts.stake;
+        require!(amount > 0 && amount <= stake.amount, ErrorCode::InvalidStake);
+        require!(
+            Clock::get()?.unix_timestamp >= stake.staked_at + STAKE_LOCK_SECONDS,
+            ErrorCode::StakeLocked
+        );
+
+        let owner = stake.owner;
+        let seeds: &[&[u8]] = &[b"stake", owner.as_ref(), &[ctx.bumps.stake]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.to.to_account_info(),
+                    authority: stake.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+        ctx.accounts.stake.amount -= amount;
+        Ok(())
+    }
+
+    // Challenge a proof that repeats the data_hash of a lower-height proof by
+    // the same submitter. The duplicate is revoked and SLASH_BPS of its
+    // submitter's stake goes to the challenger, who can't be the submitter.
+    pub fn challenge_proof(ctx: Context<ChallengeProof>) -> Result<()> {
+        let proof = &ctx.accounts.proof;
+        let original = &ctx.accounts.original;
+        // The proofs' addresses pin both to the stake owner as submitter
+        let submitter = ctx.accounts.stake.owner;
+        require_keys_neq!(ctx.accounts.challenger.key(), submitter, ErrorCode::InvalidChallenge);
+        require!(
+            proof.key() == find_proof_address(&submitter, proof.height).0
+                && original.key() == find_proof_address(&submitter, original.height).0,
+            ErrorCode::InvalidChallenge
+        );
+        require!(
+            !proof.revoked
+                && original.data_hash == proof.data_hash
+                && original.height < proof.height,
+            ErrorCode::InvalidChallenge
+        );
+        ctx.accounts.proof.revoked = true;
+        slash_stake(
+            &mut ctx.accounts.stake,
+            &ctx.accounts.vault,
+            &ctx.accounts.challenger_token,
+            &ctx.accounts.token_program,
+            ctx.bumps.stake,
+        )
+    }
+
+    // Challenge a batch with a leaf that misses its target (see
+    // verify_batch_leaf). The batch is marked fraudulent and SLASH_BPS of
+    // its submitter's stake goes to another challenger; each batch is
+    // slashed once.
+    pub fn challenge_batch_leaf(
+        ctx: Context<ChallengeBatchLeaf>,
+        leaf: BatchLeaf,
+        path: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let batch = &mut ctx.accounts.batch;
+        require_keys_neq!(ctx.accounts.challenger.key(), batch.owner, ErrorCode::InvalidChallenge);
+        require!(
+            !batch.fraudulent && !batch.check_leaf(&leaf, &path)?,
+            ErrorCode::InvalidChallenge
+        );
+        batch.fraudulent = true;
+        slash_stake(
+            &mut ctx.accounts.stake,
+            &ctx.accounts.vault,
+            &ctx.accounts.challenger_token,
+            &ctx.accounts.token_program,
+            ctx.bumps.stake,
+        )
+    }
+
+    // Permissionless: start a new challenge epoch once CHALLENGE_PERIOD_SLOTS
+    // have passed, salting it with the most recent SlotHashes entry. Proofs
+    // may use the new salt or the one it replaces.
+    pub fn refresh_challenge(ctx: Context<RefreshChallenge>) -> Result<()> {
+        let challenge = &mut ctx.accounts.challenge;
+        let slot = Clock::get()?.slot;
+        require!(
+            challenge.epoch == 0 || slot >= challenge.refreshed_slot + CHALLENGE_PERIOD_SLOTS,
+            ErrorCode::ChallengeNotDue
+        );
+
+        let slot_hashes = ctx.accounts.slot_hashes.try_borrow_data()?;
+        // SlotHashes: u64 entry count, then (slot, hash) pairs, newest first
+        require!(slot_hashes.len() >= 8 + 8 + 32, ErrorCode::ChallengeNotDue);
+        let recent_hash = &slot_hashes[16..48];
+
+        let mut hasher = Sha256::new();
+        hasher.update(challenge.salt);
+        hasher.update(recent_hash);
+        hasher.update(slot.to_le_bytes());
+        challenge.previous_salt = challenge.salt;
+        challenge.salt = hasher.finalize().into();
+        challenge.epoch += 1;
+        challenge.refreshed_slot = slot;
+        Ok(())
+    }
+
+    // Require proofs to go through commit_proof / reveal_proof
+    pub fn set_commit_reveal(ctx: Context<UpdateConfig>, required: bool) -> Result<()> {
+        ctx.accounts.config.commit_reveal_required = required;
+        Ok(())
+    }
+
+    // First half of commit-reveal submission: record
+    // `commitment` = proof_commitment(data_hash, nonce, salt, owner) without
+    // exposing the proof
+    pub fn commit_proof(ctx: Context<CommitProof>, commitment: [u8; 32]) -> Result<()> {
+        let proof_commit = &mut ctx.accounts.proof_commit;
+        proof_commit.owner = ctx.accounts.owner.key();
+        proof_commit.commitment = commitment;
+        proof_commit.slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    // Second half: at least MIN_REVEAL_SLOTS after commit_proof, open the
+    // commitment and submit the proof. The commitment account is closed.
+    pub fn reveal_proof(
+        ctx: Context<RevealProof>,
+        data_hash: [u8; 32],
+        nonce: u64,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let proof_commit = &ctx.accounts.proof_commit;
+        require!(
+            Clock::get()?.slot >= proof_commit.slot + MIN_REVEAL_SLOTS,
+            ErrorCode::RevealTooEarly
+        );
+        require!(
+            proof_commit.commitment
+                == proof_commitment(&data_hash, nonce, &salt, &ctx.accounts.submit.owner.key()),
+            ErrorCode::CommitmentMismatch
+        );
+        accept_proof(&mut ctx.accounts.submit, ctx.bumps.submit.config, data_hash, nonce)?;
+        ctx.accounts
+            .proof_commit
+            .close(ctx.accounts.submit.owner.to_account_info())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeUser<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = UserProfile::LEN,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateStatus<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump,
+        has_one = owner @ ErrorCode::ProfileOwnerMismatch
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessInteraction<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub from: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub to: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+        constraint = treasury_token.owner == treasury_authority() @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitProof<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = ProofData::LEN,
+        seeds = [b"proof", owner.key().as_ref(), &user_profile.proof_count.to_le_bytes()],
+        bump
+    )]
+    pub proof: Account<'info, ProofData>,
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [b"challenge"], bump)]
+    pub challenge: Account<'info, Challenge>,
+    // Required once the reward mint exists
+    #[account(mut)]
+    pub reward_mint: Option<Account<'info, Mint>>,
+    #[account(mut)]
+    pub reward_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    #[account(mut, seeds = [b"leaderboard"], bump)]
+    pub leaderboard: Option<Account<'info, Leaderboard>>,
+    #[account(seeds = [b"stake", owner.key().as_ref()], bump)]
+    pub stake: Option<Account<'info, Stake>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyChain<'info> {
+    #[account(mut, has_one = owner @ ErrorCode::ProofOwnerMismatch)]
+    pub current_proof: Account<'info, ProofData>,
+    #[account(address = current_proof.previous_proof @ ErrorCode::InvalidChain)]
+    pub previous_proof: Account<'info, ProofData>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyChainSegment<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct IsUserActive<'info> {
+    #[account(
+        seeds = [b"user-profile", owner.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+}
+
+#[derive(Accounts)]
+pub struct HasVerifiedProof<'info> {
+    pub proof: Account<'info, ProofData>,
+}
+
+#[derive(Accounts)]
+pub struct ListProofForSale<'info> {
+    #[account(constraint = proof.owner == seller.key() @ ErrorCode::ProofOwnerMismatch)]
+    pub proof: Account<'info, ProofData>,
+    #[account(
+        init,
+        payer = seller,
+        space = ProofListing::LEN,
+        seeds = [b"listing", proof.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, ProofListing>,
+    pub payment_mint: Account<'info, Mint>,
+    #[account(seeds = [b"fee-tokens"], bump)]
+    pub fee_tokens: Account<'info, FeeTokenTable>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyProof<'info> {
+    #[account(mut)]
+    pub proof: Account<'info, ProofData>,
+    #[account(
+        mut,
+        close = seller,
+        seeds = [b"listing", proof.key().as_ref()],
+        bump,
+        has_one = seller
+    )]
+    pub listing: Account<'info, ProofListing>,
+    #[account(mut, constraint = buyer_token.mint == listing.payment_mint @ ErrorCode::ListingMismatch)]
+    pub buyer_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = seller_token.mint == listing.payment_mint @ ErrorCode::ListingMismatch,
+        constraint = seller_token.owner == listing.seller @ ErrorCode::ListingMismatch
+    )]
+    pub seller_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = treasury_token.mint == listing.payment_mint @ ErrorCode::ListingMismatch,
+        constraint = treasury_token.owner == treasury_authority() @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+    #[account(seeds = [b"fee-tokens"], bump)]
+    pub fee_tokens: Account<'info, FeeTokenTable>,
+    /// CHECK: receives the listing rent; must match `listing.seller`
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+    pub buyer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+    #[account(
+        mut,
+        close = seller,
+        seeds = [b"listing", listing.proof.as_ref()],
+        bump,
+        has_one = seller
+    )]
+    pub listing: Account<'info, ProofListing>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeTokens<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = FeeTokenTable::LEN,
+        seeds = [b"fee-tokens"],
+        bump
+    )]
+    pub fee_tokens: Account<'info, FeeTokenTable>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeTokens<'info> {
+    #[account(mut, seeds = [b"fee-tokens"], bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub fee_tokens: Account<'info, FeeTokenTable>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = Config::LEN,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [b"config"], bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConsumeProof<'info> {
+    #[account(mut, has_one = owner @ ErrorCode::Unauthorized)]
+    pub proof: Account<'info, ProofData>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(root: [u8; 32])]
+pub struct SubmitProofBatch<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = ProofBatch::LEN,
+        seeds = [b"proof-batch", owner.key().as_ref(), root.as_ref()],
+        bump
+    )]
+    pub batch: Account<'info, ProofBatch>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [b"challenge"], bump)]
+    pub challenge: Account<'info, Challenge>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyBatchLeaf<'info> {
+    pub batch: Account<'info, ProofBatch>,
+}
+
+#[derive(Accounts)]
+#[instruction(decimals: u8)]
+pub struct InitializeRewardMint<'info> {
+    #[account(mut, seeds = [b"config"], bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"reward-mint"],
+        bump,
+        mint::decimals = decimals,
+        mint::authority = config
+    )]
+    pub reward_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProof<'info> {
+    #[account(mut, has_one = owner @ ErrorCode::Unauthorized, close = owner)]
+    pub proof: Account<'info, ProofData>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeProof<'info> {
+    #[account(mut)]
+    pub proof: Account<'info, ProofData>,
+    #[account(seeds = [b"config"], bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLeaderboard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = Leaderboard::LEN,
+        seeds = [b"leaderboard"],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetUsername<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(
+        init,
+        payer = owner,
+        space = UsernameRecord::LEN,
+        seeds = [b"username", username_seed(&name).as_ref()],
+        bump
+    )]
+    pub username_record: Account<'info, UsernameRecord>,
+    #[account(mut, has_one = owner @ ErrorCode::Unauthorized)]
+    pub previous_record: Option<Account<'info, UsernameRecord>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetProfileMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUserProfile<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mut, has_one = owner @ ErrorCode::Unauthorized)]
+    pub username_record: Option<Account<'info, UsernameRecord>>,
+    /// CHECK: the owner's stake, which must be empty or never created
+    #[account(seeds = [b"stake", owner.key().as_ref()], bump)]
+    pub stake: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeOwner<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwner<'info> {
+    #[account(
+        mut,
+        close = previous_owner,
+        seeds = [b"user-profile", previous_owner.key().as_ref()],
+        bump
+    )]
+    pub old_profile: Account<'info, UserProfile>,
+    #[account(
+        init,
+        payer = new_owner,
+        space = UserProfile::LEN,
+        seeds = [b"user-profile", new_owner.key().as_ref()],
+        bump
+    )]
+    pub new_profile: Account<'info, UserProfile>,
+    #[account(
+        mut,
+        constraint = username_record.owner == previous_owner.key() @ ErrorCode::InvalidUsername
+    )]
+    pub username_record: Option<Account<'info, UsernameRecord>>,
+    /// CHECK: the old profile's owner; receives its rent
+    #[account(mut)]
+    pub previous_owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct CreateEscrow<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = Escrow::LEN,
+        seeds = [b"escrow", owner.key().as_ref(), escrow_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"escrow-vault", escrow.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = escrow
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    pub mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub from: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Shared by release_escrow and refund_escrow; each checks who `to` pays
+#[derive(Accounts)]
+pub struct SettleEscrow<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"escrow", owner.key().as_ref(), escrow.escrow_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(mut, seeds = [b"escrow-vault", escrow.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mut, token::mint = escrow.mint)]
+    pub to: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct ApproveDelegate<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Allowance::LEN,
+        seeds = [b"allowance", owner.key().as_ref(), delegate.as_ref()],
+        bump
+    )]
+    pub allowance: Account<'info, Allowance>,
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mut, token::authority = owner)]
+    pub token_account: Account<'info, TokenAccount>,
+    /// CHECK: PDA approved on `token_account`; signs this delegate's transfers
+    #[account(seeds = [b"spend-authority", owner.key().as_ref(), delegate.as_ref()], bump)]
+    pub spend_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"allowance", owner.key().as_ref(), allowance.delegate.as_ref()],
+        bump,
+        has_one = owner @ ErrorCode::Unauthorized,
+        has_one = token_account @ ErrorCode::InvalidAllowance
+    )]
+    pub allowance: Account<'info, Allowance>,
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+    /// CHECK: the allowance's spend authority PDA, compared with the token
+    /// account's delegate
+    #[account(seeds = [b"spend-authority", owner.key().as_ref(), allowance.delegate.as_ref()], bump)]
+    pub spend_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessDelegatedInteraction<'info> {
+    #[account(
+        mut,
+        seeds = [b"allowance", allowance.owner.as_ref(), delegate.key().as_ref()],
+        bump,
+        has_one = delegate @ ErrorCode::Unauthorized
+    )]
+    pub allowance: Account<'info, Allowance>,
+    #[account(
+        mut,
+        seeds = [b"user-profile", allowance.owner.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mut, address = allowance.token_account @ ErrorCode::InvalidAllowance)]
+    pub from: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub to: Account<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        constraint = treasury_token.mint == from.mint @ ErrorCode::InvalidTreasury,
+        constraint = treasury_token.owner == treasury_authority() @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+    /// CHECK: the allowance's spend authority PDA, approved on `from`
+    #[account(seeds = [b"spend-authority", allowance.owner.as_ref(), delegate.key().as_ref()], bump)]
+    pub spend_authority: UncheckedAccount<'info>,
+    pub delegate: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessSolInteraction<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    /// CHECK: any system account may receive lamports
+    #[account(mut)]
+    pub to: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: the treasury PDA, which holds SOL fees directly
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(seeds = [b"config"], bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+    /// CHECK: the treasury PDA, which owns the treasury token accounts
+    #[account(seeds = [b"treasury"], bump)]
+    pub treasury: UncheckedAccount<'info>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = treasury,
+        token::token_program = token_program
+    )]
+    pub treasury_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub to: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub admin: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasurySol<'info> {
+    #[account(seeds = [b"config"], bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+    /// CHECK: the treasury PDA, which holds SOL fees directly
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: UncheckedAccount<'info>,
+    /// CHECK: any system account may receive lamports
+    #[account(mut)]
+    pub to: UncheckedAccount<'info>,
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// process_interaction without `to`; recipients are remaining accounts
+#[derive(Accounts)]
+pub struct ProcessInteractions<'info> {
+    #[account(
+        mut,
+        seeds = [b"user-profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub from: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+        constraint = treasury_token.owner == treasury_authority() @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(beneficiary: Pubkey)]
+pub struct CreateVesting<'info> {
+    #[account(
+        init,
+        payer = funder,
+        space = Vesting::LEN,
+        seeds = [b"vesting", funder.key().as_ref(), beneficiary.as_ref()],
+        bump
+    )]
+    pub vesting: Account<'info, Vesting>,
+    #[account(
+        init,
+        payer = funder,
+        seeds = [b"vesting-vault", vesting.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vesting
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub from: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    #[account(
+        mut,
+        seeds = [b"vesting", vesting.funder.as_ref(), beneficiary.key().as_ref()],
+        bump,
+        has_one = beneficiary @ ErrorCode::Unauthorized
+    )]
+    pub vesting: Account<'info, Vesting>,
+    #[account(mut, seeds = [b"vesting-vault", vesting.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = vesting.mint)]
+    pub to: Account<'info, TokenAccount>,
+    pub beneficiary: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct StakeTokens<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Stake::LEN,
+        seeds = [b"stake", owner.key().as_ref()],
+        bump,
+        constraint = stake.amount == 0 || stake.mint == mint.key() @ ErrorCode::InvalidStake
+    )]
+    pub stake: Account<'info, Stake>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"stake-vault", stake.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = stake
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(address = config.stake_mint @ ErrorCode::InvalidStake)]
+    pub mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub from: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"stake", owner.key().as_ref()],
+        bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub stake: Account<'info, Stake>,
+    #[account(mut, seeds = [b"stake-vault", stake.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = stake.mint)]
+    pub to: Account<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeProof<'info> {
+    #[account(mut)]
+    pub proof: Account<'info, ProofData>,
+    pub original: Account<'info, ProofData>,
+    #[account(mut, seeds = [b"stake", stake.owner.as_ref()], bump)]
+    pub stake: Account<'info, Stake>,
+    #[account(mut, seeds = [b"stake-vault", stake.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = stake.mint)]
+    pub challenger_token: Account<'info, TokenAccount>,
+    pub challenger: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeBatchLeaf<'info> {
+    #[account(mut)]
+    pub batch: Account<'info, ProofBatch>,
+    #[account(mut, seeds = [b"stake", batch.owner.as_ref()], bump)]
+    pub stake: Account<'info, Stake>,
+    #[account(mut, seeds = [b"stake-vault", stake.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = stake.mint)]
+    pub challenger_token: Account<'info, TokenAccount>,
+    pub challenger: Signer<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct ProcessInteraction<'info> {
-    #[account(mut)]
-    pub from: Account<'info, TokenAccount>,
+pub struct RefreshChallenge<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Challenge::LEN,
+        seeds = [b"challenge"],
+        bump
+    )]
+    pub challenge: Account<'info, Challenge>,
+    /// CHECK: the SlotHashes sysvar, read raw since it's too large to deserialize
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
     #[account(mut)]
-    pub to: Account<'info, TokenAccount>,
-    pub owner: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SubmitProof<'info> {
+#[instruction(commitment: [u8; 32])]
+pub struct CommitProof<'info> {
     #[account(
         init,
         payer = owner,
-        space = ProofData::LEN,
-        seeds = [b"proof", owner.key().as_ref(), &Clock::get()?.unix_timestamp.to_le_bytes()],
+        space = ProofCommit::LEN,
+        seeds = [b"proof-commit", owner.key().as_ref(), commitment.as_ref()],
         bump
     )]
-    pub proof: Account<'info, ProofData>,
+    pub proof_commit: Account<'info, ProofCommit>,
     #[account(mut)]
     pub owner: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct VerifyChain<'info> {
-    #[account(mut)]
-    pub current_proof: Account<'info, ProofData>,
-    pub previous_proof: Account<'info, ProofData>,
-    pub owner: Signer<'info>,
+pub struct RevealProof<'info> {
+    pub submit: SubmitProof<'info>,
+    #[account(
+        mut,
+        seeds = [b"proof-commit", submit.owner.key().as_ref(), proof_commit.commitment.as_ref()],
+        bump,
+        constraint = proof_commit.owner == submit.owner.key() @ ErrorCode::Unauthorized
+    )]
+    pub proof_commit: Account<'info, ProofCommit>,
 }
 
 #[account]
+#[derive(Default)]
 pub struct UserProfile {
     pub owner: Pubkey,
     pub active: bool,
     pub created_at: i64,
     pub updated_at: i64,
+    pub proof_count: u64, // seeds the owner's next proof
+    pub latest_proof: Pubkey, // head of the owner's proof chain
+    pub verified_height: u64, // proofs up to this height have checked links
+    pub total_work: u128, // summed target_work of the owner's proofs
+    pub username: String, // normalized, empty until set_username
+    pub last_proof_at: i64,
+    pub total_transferred: u64, // delivered through process_interaction, net of fees
+    pub total_fees_paid: u64, // interaction fees sent to the treasury
+    pub lamports_transferred: u64, // delivered through process_sol_interaction, net of fees
+    pub lamports_fees_paid: u64,
+    pub metadata_uri: Option<String>,
+    pub pending_owner: Option<Pubkey>, // offered by propose_owner
+    pub migrated_from: Pubkey, // owner before accept_owner, whose key seeds earlier proofs
+    pub open_escrows: u32, // blocks close_user_profile
+    pub open_allowances: u32, // blocks close_user_profile
 }
 
 impl UserProfile {
@@ -160,16 +2023,55 @@ impl UserProfile {
         32 + // owner pubkey
         1 +  // active bool
         8 +  // created_at
-        8;   // updated_at
+        8 +  // updated_at
+        8 +  // proof_count
+        32 + // latest_proof
+        8 +  // verified_height
+        16 + // total_work
+        4 + MAX_USERNAME_LEN + // username
+        8 +  // last_proof_at
+        8 +  // total_transferred
+        8 +  // total_fees_paid
+        8 +  // lamports_transferred
+        8 +  // lamports_fees_paid
+        1 + 4 + MAX_METADATA_URI_LEN + // metadata_uri
+        1 + 32 + // pending_owner
+        32 + // migrated_from
+        4 +  // open_escrows
+        4;   // open_allowances
+}
+
+pub const MAX_METADATA_URI_LEN: usize = 200;
+
+// Reserves a username for its owner
+#[account]
+pub struct UsernameRecord {
+    pub owner: Pubkey,
+    pub name: String,
+}
+
+impl UsernameRecord {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + MAX_USERNAME_LEN; // name
 }
 
+pub const MIN_USERNAME_LEN: usize = 3;
+pub const MAX_USERNAME_LEN: usize = 32;
+
 #[account]
+#[derive(Default)]
 pub struct ProofData {
     pub owner: Pubkey,
     pub data_hash: [u8; 32],
     pub nonce: u64,
     pub timestamp: i64,
     pub verified: bool,
+    pub consumed: bool,
+    pub previous_proof: Pubkey, // the submitter's prior proof, default for their first
+    pub height: u64,            // proofs the submitter made before this one
+    pub expires_at: i64,        // 0 never expires
+    pub revoked: bool,
 }
 
 impl ProofData {
@@ -178,7 +2080,453 @@ impl ProofData {
         32 + // data_hash
         8 +  // nonce
         8 +  // timestamp
-        1;   // verified
+        1 +  // verified
+        1 +  // consumed
+        32 + // previous_proof
+        8 +  // height
+        8 +  // expires_at
+        1;   // revoked
+
+    // Whether the proof still counts: verified, not revoked and unexpired
+    pub fn is_live(&self, now: i64) -> bool {
+        self.verified && !self.revoked && (self.expires_at == 0 || now < self.expires_at)
+    }
+}
+
+#[account]
+pub struct ProofListing {
+    pub proof: Pubkey,
+    pub seller: Pubkey,
+    pub payment_mint: Pubkey,
+    pub price: u64, // fee units; see FeeToken::rate
+}
+
+impl ProofListing {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // proof
+        32 + // seller
+        32 + // payment_mint
+        8;   // price
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeToken {
+    pub mint: Pubkey,
+    pub rate: u64, // mint base units per RATE_SCALE fee units
+}
+
+// Mints accepted for protocol fees and their conversion rates
+#[account]
+pub struct FeeTokenTable {
+    pub admin: Pubkey,
+    pub entries: Vec<FeeToken>,
+}
+
+impl FeeTokenTable {
+    pub const MAX_ENTRIES: usize = 16;
+    pub const RATE_SCALE: u64 = 1_000_000;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // admin
+        4 + (32 + 8) * Self::MAX_ENTRIES; // entries
+
+    pub fn rate_for(&self, mint: &Pubkey) -> Option<u64> {
+        self.entries.iter().find(|entry| entry.mint == *mint).map(|entry| entry.rate)
+    }
+
+    // Convert an amount in fee units into base units of `mint`
+    pub fn convert(&self, fee_units: u64, mint: &Pubkey) -> Result<u64> {
+        let rate = self.rate_for(mint).ok_or(ErrorCode::UnsupportedFeeToken)?;
+        let amount = (fee_units as u128)
+            .checked_mul(rate as u128)
+            .ok_or(ErrorCode::Overflow)?
+            / Self::RATE_SCALE as u128;
+        u64::try_from(amount).map_err(|_| error!(ErrorCode::Overflow))
+    }
+}
+
+// Top miners by total work, highest first
+#[account]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    pub const MAX_ENTRIES: usize = 20;
+
+    pub const LEN: usize = 8 + // discriminator
+        4 + (32 + 8 + 16) * Self::MAX_ENTRIES; // entries
+
+    // Move `owner` to its place for `total_work`, dropping whoever falls off
+    // the end
+    pub fn record(&mut self, owner: Pubkey, proof_count: u64, total_work: u128) {
+        self.entries.retain(|entry| entry.owner != owner);
+        let position = self.entries.partition_point(|entry| entry.total_work >= total_work);
+        if position < Self::MAX_ENTRIES {
+            self.entries.insert(position, LeaderboardEntry { owner, proof_count, total_work });
+            self.entries.truncate(Self::MAX_ENTRIES);
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct LeaderboardEntry {
+    pub owner: Pubkey,
+    pub proof_count: u64,
+    pub total_work: u128,
+}
+
+// Program-wide settings
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub proof_target: [u8; 32], // submit_proof work hashes must be at most this
+    pub chain_target: [u8; 32], // verify_chain hashes must be at most this
+    pub target_proofs_per_epoch: u64, // 0 when retargeting is off
+    pub epoch_seconds: i64,
+    pub epoch_start: i64,
+    pub epoch_proofs: u64, // proofs accepted since epoch_start
+    pub total_work: u128, // expected hashes behind every accepted proof, see target_work
+    pub reward_mint: Pubkey, // default until initialize_reward_mint
+    pub initial_reward: u64,
+    pub halving_interval: u64, // rewarded proofs per halving, 0 never halves
+    pub epoch_reward_cap: u64, // 0 is uncapped
+    pub epoch_rewards: u64, // minted since epoch_start
+    pub rewarded_proofs: u64, // proofs accepted since rewards began
+    pub min_proof_age: i64, // seconds before close_proof takes an unconsumed proof
+    pub proof_lifetime: i64, // seconds a new proof stays live, 0 forever
+    pub interaction_fee_bps: u16,
+    pub stake_mint: Pubkey, // default until set_staking
+    pub stake_unit: u64, // stake per bonus bit doubling, 0 turns staking bonuses off
+    pub commit_reveal_required: bool, // submit_proof is disabled in favour of reveal_proof
+}
+
+impl Config {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // admin
+        32 + // proof_target
+        32 + // chain_target
+        8 +  // target_proofs_per_epoch
+        8 +  // epoch_seconds
+        8 +  // epoch_start
+        8 +  // epoch_proofs
+        16 + // total_work
+        32 + // reward_mint
+        8 +  // initial_reward
+        8 +  // halving_interval
+        8 +  // epoch_reward_cap
+        8 +  // epoch_rewards
+        8 +  // rewarded_proofs
+        8 +  // min_proof_age
+        8 +  // proof_lifetime
+        2 +  // interaction_fee_bps
+        32 + // stake_mint
+        8 +  // stake_unit
+        1;   // commit_reveal_required
+
+    // Close out the epoch once it has run, scaling proof_target by at most
+    // 2^MAX_RETARGET_STEP toward the target rate. Rates within a factor of
+    // RETARGET_TOLERANCE of the target leave it unchanged.
+    pub fn retarget(&mut self, now: i64) {
+        if self.epoch_seconds == 0 || now < self.epoch_start + self.epoch_seconds {
+            return;
+        }
+        let proofs = self.epoch_proofs;
+        let wanted = self.target_proofs_per_epoch;
+        // A zero target (retargeting off) matches neither branch
+        if wanted > 0 && proofs > wanted.saturating_mul(RETARGET_TOLERANCE) {
+            // A zero target could never be met; stop at the hardest non-zero one
+            let harder = shift_target_right(&self.proof_target, MAX_RETARGET_STEP);
+            if harder != [0; 32] {
+                self.proof_target = harder;
+            }
+        } else if proofs.saturating_mul(RETARGET_TOLERANCE) < wanted {
+            self.proof_target = shift_target_left(&self.proof_target, MAX_RETARGET_STEP);
+        }
+        self.epoch_start = now;
+        self.epoch_proofs = 0;
+        self.epoch_rewards = 0;
+    }
+
+    // Reward for the proof being accepted: initial_reward halved once per
+    // halving_interval rewarded proofs, times 1 + the prover's stake bonus,
+    // clipped to what's left of the epoch's cap. Counts the proof and its
+    // reward.
+    pub fn take_reward(&mut self, bonus_bits: u32) -> u64 {
+        if self.reward_mint == Pubkey::default() {
+            return 0;
+        }
+        let halvings = self.rewarded_proofs.checked_div(self.halving_interval).unwrap_or(0);
+        let mut reward = self
+            .initial_reward
+            .checked_shr(halvings.min(64) as u32)
+            .unwrap_or(0)
+            .saturating_mul(1 + bonus_bits as u64);
+        if self.epoch_reward_cap > 0 {
+            reward = reward.min(self.epoch_reward_cap.saturating_sub(self.epoch_rewards));
+        }
+        self.rewarded_proofs += 1;
+        self.epoch_rewards += reward;
+        reward
+    }
+
+    // Bits a stake of `staked` tokens eases the proof target by: one per
+    // doubling of whole stake_units held, starting at one unit, up to
+    // MAX_STAKE_BONUS_BITS
+    pub fn stake_bonus_bits(&self, staked: u64) -> u32 {
+        let units = staked.checked_div(self.stake_unit).unwrap_or(0);
+        (u64::BITS - units.leading_zeros()).min(MAX_STAKE_BONUS_BITS)
+    }
+}
+
+// Most a stake can ease the proof target, in bits (2x work each)
+pub const MAX_STAKE_BONUS_BITS: u32 = 4;
+
+// Share of a stake paid to the challenger of a bad proof, in basis points
+pub const SLASH_BPS: u64 = 1_000;
+
+// Seconds staked tokens stay locked after the last stake
+pub const STAKE_LOCK_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+// Largest change to proof_target per retarget, in bits (2x work)
+pub const MAX_RETARGET_STEP: u32 = 1;
+
+// Factor the proof rate may be off target before retarget adjusts difficulty
+pub const RETARGET_TOLERANCE: u64 = 2;
+
+// Protocol fee taken on proof sales, in basis points
+pub const PROTOCOL_FEE_BPS: u64 = 250;
+
+// Most recipients one process_interactions call may pay
+pub const MAX_BATCH_RECIPIENTS: usize = 20;
+
+// Highest configurable fee, in basis points
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+// A Merkle root committing to many proofs at once
+#[account]
+pub struct ProofBatch {
+    pub owner: Pubkey,
+    pub root: [u8; 32],
+    pub count: u32,
+    pub proof_target: [u8; 32], // target in force at submission
+    pub timestamp: i64,
+    pub fraudulent: bool, // a challenged leaf missed proof_target and the stake was slashed
+    pub salt: [u8; 32], // challenge salt leaves are mined over
+}
+
+impl ProofBatch {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // root
+        4 +  // count
+        32 + // proof_target
+        8 +  // timestamp
+        1 +  // fraudulent
+        32;  // salt
+
+    // Whether `leaf`, shown to be in the batch by its Merkle `path` (leaf to
+    // root), meets the batch's target. Errors if the path doesn't reach root.
+    pub fn check_leaf(&self, leaf: &BatchLeaf, path: &[[u8; 32]]) -> Result<bool> {
+        require!(path.len() <= MAX_BATCH_DEPTH, ErrorCode::InvalidBatch);
+        let root = path
+            .iter()
+            .fold(batch_leaf_hash(leaf), |node, sibling| batch_node_hash(&node, sibling));
+        require!(root == self.root, ErrorCode::InvalidBatch);
+
+        let work_hash = proof_work_hash(&self.salt, &leaf.data_hash, leaf.nonce, &self.owner);
+        Ok(meets_target(&work_hash, &self.proof_target))
+    }
+}
+
+// One proof committed to by a ProofBatch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct BatchLeaf {
+    pub data_hash: [u8; 32],
+    pub nonce: u64,
+}
+
+// Most proofs one batch may commit to
+pub const MAX_BATCH_PROOFS: u32 = 1 << 20;
+
+// Longest Merkle path verify_batch_leaf accepts; the tree needn't be balanced
+pub const MAX_BATCH_DEPTH: usize = 32;
+
+// Tokens held for `recipient` until released or refunded
+#[account]
+pub struct Escrow {
+    pub owner: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub deadline: i64, // refundable from here on
+    pub escrow_id: u64,
+}
+
+impl Escrow {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // recipient
+        32 + // mint
+        8 +  // amount
+        8 +  // deadline
+        8;   // escrow_id
+}
+
+// What a delegate may spend from one of the owner's token accounts
+#[account]
+pub struct Allowance {
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub token_account: Pubkey,
+    pub limit: u64,
+    pub spent: u64,
+    pub expiry: i64,
+}
+
+impl Allowance {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // delegate
+        32 + // token_account
+        8 +  // limit
+        8 +  // spent
+        8;   // expiry
+}
+
+// Tokens vesting linearly to `beneficiary`
+#[account]
+pub struct Vesting {
+    pub funder: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub total: u64,
+    pub claimed: u64,
+    pub start: i64,
+    pub cliff: i64,    // seconds after start before anything vests
+    pub duration: i64, // seconds after start when everything has vested
+}
+
+impl Vesting {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // funder
+        32 + // beneficiary
+        32 + // mint
+        8 +  // total
+        8 +  // claimed
+        8 +  // start
+        8 +  // cliff
+        8;   // duration
+
+    // Amount vested by `now`: none before the cliff, then total * elapsed / duration
+    pub fn vested(&self, now: i64) -> u64 {
+        let elapsed = now - self.start;
+        if elapsed < self.cliff {
+            0
+        } else if elapsed >= self.duration {
+            self.total
+        } else {
+            (self.total as u128 * elapsed as u128 / self.duration as u128) as u64
+        }
+    }
+}
+
+// Tokens an owner has locked to weight their proofs
+#[account]
+pub struct Stake {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub staked_at: i64, // unstake opens STAKE_LOCK_SECONDS after this
+}
+
+impl Stake {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // mint
+        8 +  // amount
+        8;   // staked_at
+}
+
+// Salt proofs must be mined over, rotated from recent slot hashes
+#[account]
+pub struct Challenge {
+    pub salt: [u8; 32],
+    pub previous_salt: [u8; 32], // still accepted until the next refresh
+    pub epoch: u64,
+    pub refreshed_slot: u64,
+}
+
+impl Challenge {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // salt
+        32 + // previous_salt
+        8 +  // epoch
+        8;   // refreshed_slot
+}
+
+// Slots between challenge refreshes (about ten minutes)
+pub const CHALLENGE_PERIOD_SLOTS: u64 = 1_500;
+
+// A hidden proof awaiting reveal_proof
+#[account]
+pub struct ProofCommit {
+    pub owner: Pubkey,
+    pub commitment: [u8; 32],
+    pub slot: u64, // reveal opens MIN_REVEAL_SLOTS after this
+}
+
+impl ProofCommit {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // commitment
+        8;   // slot
+}
+
+// Slots between commit_proof and reveal_proof
+pub const MIN_REVEAL_SLOTS: u64 = 2;
+
+#[event]
+pub struct ProofSubmitted {
+    pub owner: Pubkey,
+    pub proof: Pubkey,
+    pub data_hash: [u8; 32],
+    pub nonce: u64,
+    pub height: u64,
+    pub work: u128,
+    pub reward: u64,
+    pub timestamp: i64,
+}
+
+// Links from first_proof through last_proof checked by verify_chain or
+// verify_chain_segment
+#[event]
+pub struct ChainVerified {
+    pub owner: Pubkey,
+    pub first_proof: Pubkey,
+    pub last_proof: Pubkey,
+    pub first_height: u64,
+    pub last_height: u64,
+}
+
+// A payment through one of the process_*interaction instructions. `amount`
+// is what `to` received; `mint` is default for native SOL.
+#[event]
+pub struct InteractionProcessed {
+    pub owner: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct StatusUpdated {
+    pub owner: Pubkey,
+    pub user_profile: Pubkey,
+    pub active: bool,
+    pub timestamp: i64,
 }
 
 #[error_code]
@@ -189,14 +2537,498 @@ pub enum ErrorCode {
     InvalidProof,
     #[msg("Invalid chain - proofs are not properly linked")]
     InvalidChain,
+    #[msg("Price must be greater than zero")]
+    InvalidPrice,
+    #[msg("Listing does not match the proof or payment accounts")]
+    ListingMismatch,
+    #[msg("Fee account is not owned by the program treasury")]
+    InvalidTreasury,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Mint is not accepted for fee payment")]
+    UnsupportedFeeToken,
+    #[msg("Fee token table is full")]
+    FeeTokenTableFull,
+    #[msg("Difficulty is out of range")]
+    InvalidDifficulty,
+    #[msg("Proof batch is empty, too large, or the leaf is not in it")]
+    InvalidBatch,
+    #[msg("Reward mint and a token account of it are required")]
+    MissingRewardAccounts,
+    #[msg("Proof is unconsumed and younger than the minimum age")]
+    ProofTooYoung,
+    #[msg("Proof has expired or been revoked")]
+    ProofExpired,
+    #[msg("Username is taken, malformed, or the previous record is missing")]
+    InvalidUsername,
+    #[msg("Metadata URI is empty or too long")]
+    InvalidMetadataUri,
+    #[msg("Profile still holds a username or other obligations")]
+    ProfileHasObligations,
+    #[msg("Profile does not belong to the signer")]
+    ProfileOwnerMismatch,
+    #[msg("Proof does not belong to the signer")]
+    ProofOwnerMismatch,
+    #[msg("Escrow amount, deadline or payee is invalid")]
+    InvalidEscrow,
+    #[msg("Escrow deadline has not passed")]
+    EscrowNotExpired,
+    #[msg("Allowance is expired, exhausted or for another account")]
+    InvalidAllowance,
+    #[msg("Recipients and amounts don't match, or there are too many")]
+    InvalidRecipients,
+    #[msg("Vesting amount, cliff or duration is invalid")]
+    InvalidVesting,
+    #[msg("Nothing has vested since the last claim")]
+    NothingVested,
+    #[msg("Stake amount or mint is invalid")]
+    InvalidStake,
+    #[msg("Stake is still locked")]
+    StakeLocked,
+    #[msg("Challenged proof is valid or was already slashed")]
+    InvalidChallenge,
+    #[msg("Challenge can't be refreshed yet")]
+    ChallengeNotDue,
+    #[msg("Proofs must be submitted with commit_proof and reveal_proof")]
+    CommitRequired,
+    #[msg("Reveal is too soon after the commitment")]
+    RevealTooEarly,
+    #[msg("Revealed proof does not match the commitment")]
+    CommitmentMismatch,
+    #[msg("An epoch reward cap needs a non-zero epoch length")]
+    InvalidRewardSchedule,
+}
+
+// Hash submit_proof checks against the difficulty:
+// sha256(challenge salt || data_hash || nonce as little-endian u64 || owner)
+pub fn proof_work_hash(salt: &[u8; 32], data_hash: &[u8; 32], nonce: u64, owner: &Pubkey) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(data_hash);
+    hasher.update(nonce.to_le_bytes());
+    hasher.update(owner.as_ref());
+    hasher.finalize().into()
+}
+
+// Check that `current` was submitted directly on top of `previous`, no
+// earlier than it, and that sha256(previous.data_hash || current.data_hash)
+// meets the chain target
+fn check_proof_link(
+    previous: &Account<ProofData>,
+    current: &Account<ProofData>,
+    chain_target: &[u8; 32],
+) -> Result<()> {
+    require!(
+        current.previous_proof == previous.key()
+            && current.height == previous.height + 1
+            && previous.timestamp <= current.timestamp,
+        ErrorCode::InvalidChain
+    );
+    let mut hasher = Sha256::new();
+    hasher.update(previous.data_hash);
+    hasher.update(current.data_hash);
+    require!(meets_target(&hasher.finalize().into(), chain_target), ErrorCode::InvalidChain);
+    Ok(())
+}
+
+// Commitment commit_proof records:
+// sha256(data_hash || nonce as little-endian u64 || salt || owner)
+pub fn proof_commitment(data_hash: &[u8; 32], nonce: u64, salt: &[u8; 32], owner: &Pubkey) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data_hash);
+    hasher.update(nonce.to_le_bytes());
+    hasher.update(salt);
+    hasher.update(owner.as_ref());
+    hasher.finalize().into()
+}
+
+// Whether `hash`, read as a big-endian 256-bit integer, is at most `target`.
+// Byte arrays compare lexicographically, which is big-endian order.
+pub fn meets_target(hash: &[u8; 32], target: &[u8; 32]) -> bool {
+    hash <= target
+}
+
+// Target met by hashes with at least `bits` leading zero bits:
+// 2^(256 - bits) - 1
+pub fn target_from_leading_zero_bits(bits: u32) -> [u8; 32] {
+    shift_target_right(&[0xff; 32], bits)
+}
+
+// Expected hashes to find one at most `target`, 2^256 / (target + 1),
+// approximated from the target's top 128 bits
+pub fn target_work(target: &[u8; 32]) -> u128 {
+    let high = u128::from_be_bytes(target[..16].try_into().unwrap());
+    u128::MAX / high.saturating_add(1)
+}
+
+// target >> bits: a harder target
+pub fn shift_target_right(target: &[u8; 32], bits: u32) -> [u8; 32] {
+    let value = (
+        u128::from_be_bytes(target[..16].try_into().unwrap()),
+        u128::from_be_bytes(target[16..].try_into().unwrap()),
+    );
+    let (high, low) = match bits {
+        0 => value,
+        1..=127 => (value.0 >> bits, (value.1 >> bits) | (value.0 << (128 - bits))),
+        128..=255 => (0, value.0 >> (bits - 128)),
+        _ => (0, 0),
+    };
+    let mut shifted = [0; 32];
+    shifted[..16].copy_from_slice(&high.to_be_bytes());
+    shifted[16..].copy_from_slice(&low.to_be_bytes());
+    shifted
+}
+
+// target << bits, saturating at the easiest target: filling the vacated low
+// bits with ones keeps all-ones targets fixed
+pub fn shift_target_left(target: &[u8; 32], bits: u32) -> [u8; 32] {
+    if bits == 0 {
+        return *target;
+    }
+    let high = u128::from_be_bytes(target[..16].try_into().unwrap());
+    let low = u128::from_be_bytes(target[16..].try_into().unwrap());
+    if bits >= 128 || high.leading_zeros() < bits {
+        return [0xff; 32];
+    }
+    let fill = (1u128 << bits) - 1;
+    let high = (high << bits) | (low >> (128 - bits));
+    let low = (low << bits) | fill;
+    let mut shifted = [0; 32];
+    shifted[..16].copy_from_slice(&high.to_be_bytes());
+    shifted[16..].copy_from_slice(&low.to_be_bytes());
+    shifted
+}
+
+// PDA that owns the program's treasury token accounts
+pub fn treasury_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"treasury"], &ID).0
+}
+
+// PDA derivation for clients and CPI callers; mirrors the account constraints
+
+pub fn find_user_profile_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"user-profile", owner.as_ref()], &ID)
+}
+
+// `index` counts the owner's earlier proofs: UserProfile::proof_count at
+// submit time
+pub fn find_proof_address(owner: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"proof", owner.as_ref(), &index.to_le_bytes()], &ID)
 }
 
-// Helper function to verify hash meets difficulty requirement
-fn verify_hash_difficulty(hash: &[u8; 32], leading_zeros: u8) -> bool {
-    for i in 0..leading_zeros {
-        if hash[i as usize] != 0 {
-            return false;
+pub fn find_proof_batch_address(owner: &Pubkey, root: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"proof-batch", owner.as_ref(), root.as_ref()], &ID)
+}
+
+pub fn find_reward_mint_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"reward-mint"], &ID)
+}
+
+pub fn find_leaderboard_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"leaderboard"], &ID)
+}
+
+pub fn find_username_address(name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"username", username_seed(name).as_ref()], &ID)
+}
+
+pub fn find_escrow_address(owner: &Pubkey, escrow_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"escrow", owner.as_ref(), &escrow_id.to_le_bytes()], &ID)
+}
+
+pub fn find_escrow_vault_address(escrow: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"escrow-vault", escrow.as_ref()], &ID)
+}
+
+pub fn find_allowance_address(owner: &Pubkey, delegate: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"allowance", owner.as_ref(), delegate.as_ref()], &ID)
+}
+
+pub fn find_spend_authority_address(owner: &Pubkey, delegate: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"spend-authority", owner.as_ref(), delegate.as_ref()], &ID)
+}
+
+pub fn find_vesting_address(funder: &Pubkey, beneficiary: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vesting", funder.as_ref(), beneficiary.as_ref()], &ID)
+}
+
+pub fn find_vesting_vault_address(vesting: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vesting-vault", vesting.as_ref()], &ID)
+}
+
+pub fn find_stake_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake", owner.as_ref()], &ID)
+}
+
+pub fn find_stake_vault_address(stake: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake-vault", stake.as_ref()], &ID)
+}
+
+pub fn find_challenge_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"challenge"], &ID)
+}
+
+pub fn find_proof_commit_address(owner: &Pubkey, commitment: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"proof-commit", owner.as_ref(), commitment.as_ref()], &ID)
+}
+
+pub fn find_listing_address(proof: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"listing", proof.as_ref()], &ID)
+}
+
+pub fn find_fee_tokens_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"fee-tokens"], &ID)
+}
+
+pub fn find_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &ID)
+}
+
+// Batch leaf: sha256(0x00 || data_hash || nonce as little-endian u64)
+pub fn batch_leaf_hash(leaf: &BatchLeaf) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(leaf.data_hash);
+    hasher.update(leaf.nonce.to_le_bytes());
+    hasher.finalize().into()
+}
+
+// Batch interior node: sha256(0x01 || lower child || higher child). Sorting
+// the children means paths need no left/right flags.
+pub fn batch_node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(low);
+    hasher.update(high);
+    hasher.finalize().into()
+}
+
+// Canonical form of a username: trimmed, lowercased, MIN_USERNAME_LEN to
+// MAX_USERNAME_LEN of [a-z0-9_]
+pub fn normalize_username(name: &str) -> Result<String> {
+    let name = name.trim().to_lowercase();
+    require!(
+        (MIN_USERNAME_LEN..=MAX_USERNAME_LEN).contains(&name.len())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        ErrorCode::InvalidUsername
+    );
+    Ok(name)
+}
+
+// Seed component for a username. Invalid names map to a hash no valid name
+// produces, so the handler reports InvalidUsername rather than a seeds mismatch.
+pub fn username_seed(name: &str) -> [u8; 32] {
+    match normalize_username(name) {
+        Ok(name) => Sha256::digest(name.as_bytes()).into(),
+        Err(_) => [0; 32],
+    }
+}
+
+// Pay out an escrow's vault to `to` and close the vault; the escrow account
+// itself is closed by its `close` constraint
+fn settle_escrow(ctx: &Context<SettleEscrow>) -> Result<()> {
+    let escrow = &ctx.accounts.escrow;
+    let owner = escrow.owner;
+    let escrow_id = escrow.escrow_id.to_le_bytes();
+    let seeds: &[&[u8]] = &[b"escrow", owner.as_ref(), &escrow_id, &[ctx.bumps.escrow]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.vault.to_account_info(),
+                to: ctx.accounts.to.to_account_info(),
+                authority: escrow.to_account_info(),
+            },
+            &[seeds],
+        ),
+        escrow.amount,
+    )?;
+    token::close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        token::CloseAccount {
+            account: ctx.accounts.vault.to_account_info(),
+            destination: ctx.accounts.owner.to_account_info(),
+            authority: escrow.to_account_info(),
+        },
+        &[seeds],
+    ))
+}
+
+// transfer_checked `amount` from `from` to `to`, returning how much `to`
+// received: less than `amount` when the mint has a Token-2022 transfer fee
+fn transfer_checked_received<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    mint: &InterfaceAccount<'info, token_interface::Mint>,
+    to: &mut InterfaceAccount<'info, token_interface::TokenAccount>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let before = to.amount;
+    token_interface::transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: authority.to_account_info(),
+            },
+        ),
+        amount,
+        mint.decimals,
+    )?;
+    to.reload()?;
+    Ok(to.amount.saturating_sub(before))
+}
+
+// Check and record a proof for submit_proof and reveal_proof
+fn accept_proof(
+    accounts: &mut SubmitProof,
+    config_bump: u8,
+    data_hash: [u8; 32],
+    nonce: u64,
+) -> Result<()> {
+    let proof = &mut accounts.proof;
+    let clock = Clock::get()?;
+    let config = &mut accounts.config;
+    config.retarget(clock.unix_timestamp);
+
+    // Stake eases the target and raises the reward (see stake_bonus_bits)
+    let staked = accounts
+        .stake
+        .as_ref()
+        .filter(|stake| stake.mint == config.stake_mint)
+        .map_or(0, |stake| stake.amount);
+    let bonus_bits = config.stake_bonus_bits(staked);
+    let target = shift_target_left(&config.proof_target, bonus_bits);
+    // The work is over the hash bound to this nonce and owner, so a
+    // lucky hash can't be replayed by anyone else or with another nonce,
+    // and to a recent challenge salt so it can't be mined in advance
+    let owner = accounts.owner.key();
+    let challenge = &accounts.challenge;
+    let meets = |salt: &[u8; 32]| {
+        meets_target(&proof_work_hash(salt, &data_hash, nonce, &owner), &target)
+    };
+    require!(
+        meets(&challenge.salt)
+            || (challenge.previous_salt != [0; 32] && meets(&challenge.previous_salt)),
+        ErrorCode::InvalidProof
+    );
+    config.epoch_proofs += 1;
+    let work = target_work(&target);
+    config.total_work = config.total_work.saturating_add(work);
+    let reward = config.take_reward(bonus_bits);
+    let reward_on = config.reward_mint != Pubkey::default();
+
+    proof.owner = accounts.owner.key();
+    proof.data_hash = data_hash;
+    proof.nonce = nonce;
+    proof.timestamp = clock.unix_timestamp;
+    proof.verified = true;
+    proof.expires_at = match config.proof_lifetime {
+        0 => 0,
+        lifetime => clock.unix_timestamp + lifetime,
+    };
+    proof.revoked = false;
+
+    // Link onto the owner's previous proof
+    let user_profile = &mut accounts.user_profile;
+    proof.previous_proof = user_profile.latest_proof;
+    proof.height = user_profile.proof_count;
+    user_profile.latest_proof = proof.key();
+    user_profile.proof_count += 1;
+    user_profile.last_proof_at = clock.unix_timestamp;
+    user_profile.total_work = user_profile.total_work.saturating_add(work);
+
+    if let Some(leaderboard) = accounts.leaderboard.as_mut() {
+        leaderboard.record(user_profile.owner, user_profile.proof_count, user_profile.total_work);
+    }
+    let submitted = ProofSubmitted {
+        owner: proof.owner,
+        proof: proof.key(),
+        data_hash,
+        nonce,
+        height: proof.height,
+        work,
+        reward,
+        timestamp: proof.timestamp,
+    };
+
+    if reward_on {
+        let (Some(reward_mint), Some(reward_account), Some(token_program)) = (
+            &accounts.reward_mint,
+            &accounts.reward_account,
+            &accounts.token_program,
+        ) else {
+            return err!(ErrorCode::MissingRewardAccounts);
+        };
+        require!(
+            reward_mint.key() == accounts.config.reward_mint
+                && reward_account.mint == reward_mint.key(),
+            ErrorCode::MissingRewardAccounts
+        );
+        if reward > 0 {
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    token::MintTo {
+                        mint: reward_mint.to_account_info(),
+                        to: reward_account.to_account_info(),
+                        authority: accounts.config.to_account_info(),
+                    },
+                    &[&[b"config", &[config_bump]]],
+                ),
+                reward,
+            )?;
         }
     }
-    true
-} 
\ No newline at end of file
+
+    emit!(submitted);
+    Ok(())
+}
+
+// Pay SLASH_BPS of `stake` from its vault to `to`
+fn slash_stake<'info>(
+    stake: &mut Account<'info, Stake>,
+    vault: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    bump: u8,
+) -> Result<()> {
+    let amount = bps_fee(stake.amount, SLASH_BPS)?;
+    if amount == 0 {
+        return Ok(());
+    }
+    let owner = stake.owner;
+    let seeds: &[&[u8]] = &[b"stake", owner.as_ref(), &[bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: stake.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )?;
+    stake.amount -= amount;
+    Ok(())
+}
+
+// Helper to compute the protocol fee on `amount`
+fn protocol_fee(amount: u64) -> Result<u64> {
+    bps_fee(amount, PROTOCOL_FEE_BPS)
+}
+
+// Helper to compute a fee of `bps` basis points on `amount`
+fn bps_fee(amount: u64, bps: u64) -> Result<u64> {
+    let fee = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / 10_000;
+    Ok(fee as u64)
+}
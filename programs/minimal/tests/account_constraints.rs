@@ -0,0 +1,173 @@
+// Account validation of the profile and proof instructions, run through
+// Anchor's try_accounts against in-memory accounts
+
+use std::collections::BTreeSet;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::Discriminator;
+use minimal::{ProofData, UpdateStatus, UpdateStatusBumps, UserProfile, VerifyChain, VerifyChainBumps};
+
+// Backing storage for one AccountInfo
+struct TestAccount {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    is_signer: bool,
+}
+
+impl TestAccount {
+    fn program<T: AccountSerialize>(key: Pubkey, account: &T) -> Self {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        Self {
+            key,
+            owner: minimal::ID,
+            lamports: 1_000_000,
+            data,
+            is_signer: false,
+        }
+    }
+
+    // Program account of type T with every field zeroed
+    fn zeroed<T: Discriminator>(key: Pubkey, len: usize) -> Self {
+        let mut data = vec![0; len];
+        data[..8].copy_from_slice(&T::discriminator());
+        Self {
+            key,
+            owner: minimal::ID,
+            lamports: 1_000_000,
+            data,
+            is_signer: false,
+        }
+    }
+
+    fn signer(key: Pubkey) -> Self {
+        Self {
+            key,
+            owner: system_program::ID,
+            lamports: 1_000_000,
+            data: Vec::new(),
+            is_signer: true,
+        }
+    }
+
+    fn info(&mut self) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            self.is_signer,
+            true,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            false,
+            0,
+        )
+    }
+}
+
+fn profile_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"user-profile", owner.as_ref()], &minimal::ID).0
+}
+
+fn profile(owner: Pubkey) -> UserProfile {
+    UserProfile {
+        owner,
+        active: true,
+        ..Default::default()
+    }
+}
+
+fn error_code(result: Result<impl Sized>) -> u32 {
+    match result {
+        Err(Error::AnchorError(error)) => error.error_code_number,
+        Err(Error::ProgramError(error)) => panic!("unexpected program error: {error}"),
+        Ok(_) => panic!("accounts were accepted"),
+    }
+}
+
+fn update_status(profile: &mut TestAccount, owner: &mut TestAccount) -> Result<()> {
+    let infos = [profile.info(), owner.info()];
+    let mut accounts: &[AccountInfo] = &infos;
+    UpdateStatus::try_accounts(
+        &minimal::ID,
+        &mut accounts,
+        &[],
+        &mut UpdateStatusBumps::default(),
+        &mut BTreeSet::new(),
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn update_status_accepts_the_signers_profile() {
+    let owner = Pubkey::new_unique();
+    let mut profile = TestAccount::program(profile_address(&owner), &profile(owner));
+    let mut signer = TestAccount::signer(owner);
+    update_status(&mut profile, &mut signer).unwrap();
+}
+
+// Another user's profile isn't at the signer's PDA
+#[test]
+fn update_status_rejects_another_users_profile() {
+    let victim = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let mut profile = TestAccount::program(profile_address(&victim), &profile(victim));
+    let mut signer = TestAccount::signer(attacker);
+    assert_eq!(
+        error_code(update_status(&mut profile, &mut signer)),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+}
+
+// A profile at the signer's PDA must also name the signer as its owner
+#[test]
+fn update_status_rejects_a_profile_owned_by_someone_else() {
+    let owner = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let mut profile = TestAccount::program(profile_address(&owner), &profile(other));
+    let mut signer = TestAccount::signer(owner);
+    assert_eq!(
+        error_code(update_status(&mut profile, &mut signer)),
+        u32::from(minimal::ErrorCode::ProfileOwnerMismatch)
+    );
+}
+
+// verify_chain only extends the signer's own proofs
+#[test]
+fn verify_chain_rejects_another_owners_proof() {
+    let owner = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let previous_key = Pubkey::new_unique();
+    let mut current = TestAccount::program(
+        Pubkey::new_unique(),
+        &ProofData {
+            owner,
+            previous_proof: previous_key,
+            height: 1,
+            ..Default::default()
+        },
+    );
+    let mut previous = TestAccount::program(
+        previous_key,
+        &ProofData {
+            owner,
+            ..Default::default()
+        },
+    );
+    let config_key = Pubkey::find_program_address(&[b"config"], &minimal::ID).0;
+    let mut config = TestAccount::zeroed::<minimal::Config>(config_key, minimal::Config::LEN);
+    let mut signer = TestAccount::signer(attacker);
+
+    let infos = [current.info(), previous.info(), config.info(), signer.info()];
+    let mut accounts: &[AccountInfo] = &infos;
+    let result = VerifyChain::try_accounts(
+        &minimal::ID,
+        &mut accounts,
+        &[],
+        &mut VerifyChainBumps::default(),
+        &mut BTreeSet::new(),
+    );
+    assert_eq!(error_code(result), u32::from(minimal::ErrorCode::ProofOwnerMismatch));
+}
@@ -0,0 +1,114 @@
+// Slashing: a proof repeating an earlier proof's data_hash costs its
+// submitter SLASH_BPS of their stake, paid to someone else
+
+mod common;
+
+use anchor_lang::prelude::*;
+use common::*;
+use minimal::{accounts, instruction, ErrorCode, Stake, SLASH_BPS};
+use span_test::{program_error, Ledger, ProgramError};
+
+const STAKED: u64 = 10_000;
+
+struct Staked {
+    owner: Pubkey,
+    mint: Pubkey,
+    stake: Pubkey,
+    vault: Pubkey,
+}
+
+fn staked(ledger: &mut Ledger) -> Staked {
+    let owner = user(ledger);
+    let mint = mint(ledger);
+    let stake = minimal::find_stake_address(&owner).0;
+    let vault = minimal::find_stake_vault_address(&stake).0;
+    let state = Stake {
+        owner,
+        mint,
+        amount: STAKED,
+        staked_at: ledger.clock().unix_timestamp,
+    };
+    ledger.set_program_account(stake, &state, Stake::LEN);
+    ledger.create_token_account(vault, mint, stake, STAKED);
+    Staked { owner, mint, stake, vault }
+}
+
+fn challenge(
+    ledger: &mut Ledger,
+    staked: &Staked,
+    proof: Pubkey,
+    original: Pubkey,
+    challenger: Pubkey,
+) -> std::result::Result<Pubkey, ProgramError> {
+    let challenger_token = token_account(ledger, staked.mint, challenger, 0);
+    run(
+        ledger,
+        accounts::ChallengeProof {
+            proof,
+            original,
+            stake: staked.stake,
+            vault: staked.vault,
+            challenger_token,
+            challenger,
+            token_program: token_program(),
+        },
+        instruction::ChallengeProof {},
+    )?;
+    Ok(challenger_token)
+}
+
+#[test]
+fn challenge_proof_slashes_a_duplicate() {
+    let mut ledger = Ledger::new();
+    let staked = staked(&mut ledger);
+    let original = proof(&mut ledger, staked.owner, 0, [1; 32]);
+    let duplicate = proof(&mut ledger, staked.owner, 1, [1; 32]);
+
+    let paid = challenge(&mut ledger, &staked, duplicate, original, Pubkey::new_unique()).unwrap();
+    let slashed = STAKED * SLASH_BPS / 10_000;
+    assert_eq!(ledger.token_balance(&paid), slashed);
+    assert_eq!(ledger.program_account::<Stake>(&staked.stake).amount, STAKED - slashed);
+    assert!(ledger.program_account::<minimal::ProofData>(&duplicate).revoked);
+}
+
+#[test]
+fn challenge_proof_only_revokes_the_later_proof() {
+    let mut ledger = Ledger::new();
+    let staked = staked(&mut ledger);
+    let original = proof(&mut ledger, staked.owner, 0, [1; 32]);
+    let duplicate = proof(&mut ledger, staked.owner, 1, [1; 32]);
+
+    // Both land in the same second; only the heights tell them apart
+    assert_eq!(
+        challenge(&mut ledger, &staked, original, duplicate, Pubkey::new_unique()),
+        Err(program_error(ErrorCode::InvalidChallenge))
+    );
+    assert!(!ledger.program_account::<minimal::ProofData>(&original).revoked);
+}
+
+#[test]
+fn challenge_proof_rejects_the_submitter() {
+    let mut ledger = Ledger::new();
+    let staked = staked(&mut ledger);
+    let original = proof(&mut ledger, staked.owner, 0, [1; 32]);
+    let duplicate = proof(&mut ledger, staked.owner, 1, [1; 32]);
+
+    assert_eq!(
+        challenge(&mut ledger, &staked, duplicate, original, staked.owner),
+        Err(program_error(ErrorCode::InvalidChallenge))
+    );
+    assert_eq!(ledger.token_balance(&staked.vault), STAKED);
+}
+
+#[test]
+fn challenge_proof_needs_a_matching_hash() {
+    let mut ledger = Ledger::new();
+    let staked = staked(&mut ledger);
+    let original = proof(&mut ledger, staked.owner, 0, [1; 32]);
+    let distinct = proof(&mut ledger, staked.owner, 1, [2; 32]);
+
+    assert_eq!(
+        challenge(&mut ledger, &staked, distinct, original, Pubkey::new_unique()),
+        Err(program_error(ErrorCode::InvalidChallenge))
+    );
+}
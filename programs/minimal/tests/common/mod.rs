@@ -0,0 +1,96 @@
+// Shared setup for the tests that run minimal's instructions on a span-test
+// Ledger
+
+#![allow(dead_code)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use span_test::{ix, Ledger, ProgramError};
+
+pub const SOL: u64 = 1_000_000_000;
+
+pub fn run(ledger: &mut Ledger, accounts: impl ToAccountMetas, args: impl InstructionData) -> std::result::Result<(), ProgramError> {
+    ledger.process(&ix(minimal::ID, accounts, args))
+}
+
+// A funded wallet with an initialized profile
+pub fn user(ledger: &mut Ledger) -> Pubkey {
+    let owner = Pubkey::new_unique();
+    ledger.fund(owner, 10 * SOL);
+    run(
+        ledger,
+        minimal::accounts::InitializeUser {
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            owner,
+            system_program: system_program::ID,
+        },
+        minimal::instruction::InitializeUser {},
+    )
+    .unwrap();
+    owner
+}
+
+pub fn profile(ledger: &Ledger, owner: &Pubkey) -> minimal::UserProfile {
+    ledger.program_account(&minimal::find_user_profile_address(owner).0)
+}
+
+pub fn mint(ledger: &mut Ledger) -> Pubkey {
+    let mint = Pubkey::new_unique();
+    ledger.create_mint(mint, Pubkey::new_unique(), 6);
+    mint
+}
+
+pub fn token_account(ledger: &mut Ledger, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+    let account = Pubkey::new_unique();
+    ledger.create_token_account(account, mint, owner, amount);
+    account
+}
+
+// The program config, created by `admin` at the easiest targets
+pub fn config(ledger: &mut Ledger) -> Pubkey {
+    let admin = Pubkey::new_unique();
+    ledger.fund(admin, 10 * SOL);
+    run(
+        ledger,
+        minimal::accounts::InitializeConfig {
+            config: minimal::find_config_address().0,
+            admin,
+            system_program: system_program::ID,
+        },
+        minimal::instruction::InitializeConfig {
+            proof_target: [0xff; 32],
+            chain_target: [0xff; 32],
+        },
+    )
+    .unwrap();
+    admin
+}
+
+pub fn token_program() -> Pubkey {
+    spl_token::ID
+}
+
+// A verified proof at `owner`'s `height`, placed directly rather than mined
+pub fn proof(ledger: &mut Ledger, owner: Pubkey, height: u64, data_hash: [u8; 32]) -> Pubkey {
+    let key = minimal::find_proof_address(&owner, height).0;
+    let proof = minimal::ProofData {
+        owner,
+        data_hash,
+        timestamp: ledger.clock().unix_timestamp,
+        verified: true,
+        height,
+        ..Default::default()
+    };
+    ledger.set_program_account(key, &proof, minimal::ProofData::LEN);
+    key
+}
+
+// Rewrite `owner`'s profile in place
+pub fn update_profile(ledger: &mut Ledger, owner: &Pubkey, update: impl FnOnce(&mut minimal::UserProfile)) {
+    let mut user_profile = profile(ledger, owner);
+    update(&mut user_profile);
+    let key = minimal::find_user_profile_address(owner).0;
+    ledger.set_program_account(key, &user_profile, minimal::UserProfile::LEN);
+}
@@ -0,0 +1,129 @@
+// Delegated spending: each allowance has its own spend authority, so a
+// delegate can only move tokens through the approval made for it
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use anchor_spl::token::spl_token::error::TokenError;
+use common::*;
+use minimal::{accounts, instruction, ErrorCode};
+use span_test::{program_error, Ledger, ProgramError};
+
+type Outcome = std::result::Result<(), ProgramError>;
+
+struct Wallet {
+    owner: Pubkey,
+    mint: Pubkey,
+    token_account: Pubkey,
+}
+
+fn wallet(ledger: &mut Ledger) -> Wallet {
+    config(ledger);
+    let owner = user(ledger);
+    let mint = mint(ledger);
+    let token_account = token_account(ledger, mint, owner, 1_000);
+    Wallet { owner, mint, token_account }
+}
+
+fn approve(ledger: &mut Ledger, wallet: &Wallet, delegate: Pubkey, limit: u64) {
+    let expiry = ledger.clock().unix_timestamp + 3_600;
+    run(
+        ledger,
+        accounts::ApproveDelegate {
+            allowance: minimal::find_allowance_address(&wallet.owner, &delegate).0,
+            user_profile: minimal::find_user_profile_address(&wallet.owner).0,
+            token_account: wallet.token_account,
+            spend_authority: minimal::find_spend_authority_address(&wallet.owner, &delegate).0,
+            owner: wallet.owner,
+            token_program: token_program(),
+            system_program: system_program::ID,
+        },
+        instruction::ApproveDelegate { delegate, limit, expiry },
+    )
+    .unwrap();
+}
+
+fn revoke(ledger: &mut Ledger, wallet: &Wallet, delegate: Pubkey) {
+    run(
+        ledger,
+        accounts::RevokeDelegate {
+            allowance: minimal::find_allowance_address(&wallet.owner, &delegate).0,
+            user_profile: minimal::find_user_profile_address(&wallet.owner).0,
+            token_account: wallet.token_account,
+            spend_authority: minimal::find_spend_authority_address(&wallet.owner, &delegate).0,
+            owner: wallet.owner,
+            token_program: token_program(),
+        },
+        instruction::RevokeDelegate {},
+    )
+    .unwrap();
+}
+
+// `delegate` pays `amount` from the wallet into a fresh account
+fn spend(ledger: &mut Ledger, wallet: &Wallet, delegate: Pubkey, amount: u64) -> Outcome {
+    let to = token_account(ledger, wallet.mint, Pubkey::new_unique(), 0);
+    let treasury_token = token_account(ledger, wallet.mint, minimal::treasury_authority(), 0);
+    run(
+        ledger,
+        accounts::ProcessDelegatedInteraction {
+            allowance: minimal::find_allowance_address(&wallet.owner, &delegate).0,
+            user_profile: minimal::find_user_profile_address(&wallet.owner).0,
+            from: wallet.token_account,
+            to,
+            config: minimal::find_config_address().0,
+            treasury_token,
+            spend_authority: minimal::find_spend_authority_address(&wallet.owner, &delegate).0,
+            delegate,
+            token_program: token_program(),
+        },
+        instruction::ProcessDelegatedInteraction { amount },
+    )
+}
+
+#[test]
+fn delegates_spend_within_their_allowance() {
+    let mut ledger = Ledger::new();
+    let wallet = wallet(&mut ledger);
+    let delegate = Pubkey::new_unique();
+    approve(&mut ledger, &wallet, delegate, 100);
+
+    spend(&mut ledger, &wallet, delegate, 60).unwrap();
+    assert_eq!(ledger.token_balance(&wallet.token_account), 940);
+    assert_eq!(
+        spend(&mut ledger, &wallet, delegate, 60),
+        Err(program_error(ErrorCode::InvalidAllowance))
+    );
+}
+
+#[test]
+fn delegates_cannot_spend_through_another_allowance() {
+    let mut ledger = Ledger::new();
+    let wallet = wallet(&mut ledger);
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    approve(&mut ledger, &wallet, first, 500);
+    approve(&mut ledger, &wallet, second, 50);
+
+    // The token account now approves only the second delegate's authority
+    assert_eq!(
+        spend(&mut ledger, &wallet, first, 100),
+        Err(program_error(TokenError::OwnerMismatch as u32))
+    );
+    spend(&mut ledger, &wallet, second, 50).unwrap();
+    assert_eq!(ledger.token_balance(&wallet.token_account), 950);
+}
+
+#[test]
+fn revoking_a_replaced_allowance_keeps_the_current_approval() {
+    let mut ledger = Ledger::new();
+    let wallet = wallet(&mut ledger);
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    approve(&mut ledger, &wallet, first, 500);
+    approve(&mut ledger, &wallet, second, 50);
+
+    revoke(&mut ledger, &wallet, first);
+    spend(&mut ledger, &wallet, second, 50).unwrap();
+
+    revoke(&mut ledger, &wallet, second);
+    assert!(ledger.token_account(&wallet.token_account).delegate.is_none());
+}
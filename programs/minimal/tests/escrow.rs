@@ -0,0 +1,165 @@
+// Escrow lifecycle: tokens locked by create_escrow leave only through
+// release_escrow to the recipient or refund_escrow after the deadline
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use minimal::{accounts, instruction, ErrorCode};
+use span_test::{program_error, Ledger};
+
+struct Escrow {
+    owner: Pubkey,
+    recipient: Pubkey,
+    mint: Pubkey,
+    from: Pubkey,
+    escrow: Pubkey,
+    vault: Pubkey,
+}
+
+const AMOUNT: u64 = 1_000;
+const DEADLINE: i64 = 3_600;
+
+fn create_escrow(ledger: &mut Ledger) -> Escrow {
+    let owner = user(ledger);
+    let recipient = Pubkey::new_unique();
+    let mint = mint(ledger);
+    let from = token_account(ledger, mint, owner, 5_000);
+    let escrow = minimal::find_escrow_address(&owner, 7).0;
+    let vault = minimal::find_escrow_vault_address(&escrow).0;
+    run(
+        ledger,
+        accounts::CreateEscrow {
+            escrow,
+            vault,
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            mint,
+            from,
+            owner,
+            token_program: token_program(),
+            system_program: system_program::ID,
+        },
+        instruction::CreateEscrow {
+            escrow_id: 7,
+            recipient,
+            amount: AMOUNT,
+            deadline: ledger.clock().unix_timestamp + DEADLINE,
+        },
+    )
+    .unwrap();
+    Escrow { owner, recipient, mint, from, escrow, vault }
+}
+
+fn settle(ledger: &mut Ledger, escrow: &Escrow, to: Pubkey, release: bool) -> std::result::Result<(), span_test::ProgramError> {
+    let accounts = accounts::SettleEscrow {
+        escrow: escrow.escrow,
+        vault: escrow.vault,
+        user_profile: minimal::find_user_profile_address(&escrow.owner).0,
+        to,
+        owner: escrow.owner,
+        token_program: token_program(),
+    };
+    if release {
+        run(ledger, accounts, instruction::ReleaseEscrow {})
+    } else {
+        run(ledger, accounts, instruction::RefundEscrow {})
+    }
+}
+
+#[test]
+fn create_escrow_locks_the_tokens_in_the_vault() {
+    let mut ledger = Ledger::new();
+    let escrow = create_escrow(&mut ledger);
+    assert_eq!(ledger.token_balance(&escrow.from), 5_000 - AMOUNT);
+    assert_eq!(ledger.token_balance(&escrow.vault), AMOUNT);
+    assert_eq!(profile(&ledger, &escrow.owner).open_escrows, 1);
+}
+
+#[test]
+fn release_escrow_pays_the_recipient_and_closes_the_escrow() {
+    let mut ledger = Ledger::new();
+    let escrow = create_escrow(&mut ledger);
+    let to = token_account(&mut ledger, escrow.mint, escrow.recipient, 0);
+    settle(&mut ledger, &escrow, to, true).unwrap();
+
+    assert_eq!(ledger.token_balance(&to), AMOUNT);
+    assert!(!ledger.exists(&escrow.escrow));
+    assert!(!ledger.exists(&escrow.vault));
+    assert_eq!(profile(&ledger, &escrow.owner).open_escrows, 0);
+}
+
+#[test]
+fn release_escrow_only_pays_the_recipient() {
+    let mut ledger = Ledger::new();
+    let escrow = create_escrow(&mut ledger);
+    let to = token_account(&mut ledger, escrow.mint, Pubkey::new_unique(), 0);
+    assert_eq!(
+        settle(&mut ledger, &escrow, to, true),
+        Err(program_error(ErrorCode::InvalidEscrow))
+    );
+    assert_eq!(ledger.token_balance(&escrow.vault), AMOUNT);
+}
+
+#[test]
+fn refund_escrow_waits_for_the_deadline() {
+    let mut ledger = Ledger::new();
+    let escrow = create_escrow(&mut ledger);
+    assert_eq!(
+        settle(&mut ledger, &escrow, escrow.from, false),
+        Err(program_error(ErrorCode::EscrowNotExpired))
+    );
+
+    ledger.advance(DEADLINE);
+    settle(&mut ledger, &escrow, escrow.from, false).unwrap();
+    assert_eq!(ledger.token_balance(&escrow.from), 5_000);
+    assert!(!ledger.exists(&escrow.escrow));
+}
+
+fn transfer_profile(ledger: &mut Ledger, owner: Pubkey) -> std::result::Result<Pubkey, span_test::ProgramError> {
+    let new_owner = Pubkey::new_unique();
+    ledger.fund(new_owner, 10 * SOL);
+    run(
+        ledger,
+        accounts::ProposeOwner {
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            owner,
+        },
+        instruction::ProposeOwner {
+            new_owner: Some(new_owner),
+        },
+    )
+    .unwrap();
+    run(
+        ledger,
+        accounts::AcceptOwner {
+            old_profile: minimal::find_user_profile_address(&owner).0,
+            new_profile: minimal::find_user_profile_address(&new_owner).0,
+            username_record: None,
+            previous_owner: owner,
+            new_owner,
+            system_program: system_program::ID,
+        },
+        instruction::AcceptOwner {},
+    )?;
+    Ok(new_owner)
+}
+
+#[test]
+fn profile_transfer_waits_for_open_escrows() {
+    let mut ledger = Ledger::new();
+    let escrow = create_escrow(&mut ledger);
+    assert_eq!(
+        transfer_profile(&mut ledger, escrow.owner),
+        Err(program_error(ErrorCode::ProfileHasObligations))
+    );
+
+    // The escrow still settles against the owner's profile
+    let to = token_account(&mut ledger, escrow.mint, escrow.recipient, 0);
+    settle(&mut ledger, &escrow, to, true).unwrap();
+    assert_eq!(ledger.token_balance(&to), AMOUNT);
+
+    let new_owner = transfer_profile(&mut ledger, escrow.owner).unwrap();
+    assert_eq!(profile(&ledger, &new_owner).migrated_from, escrow.owner);
+    assert!(!ledger.exists(&minimal::find_user_profile_address(&escrow.owner).0));
+}
@@ -0,0 +1,189 @@
+// Proof marketplace: a listing sells a live, unspent proof once, and the
+// seller can withdraw it
+
+mod common;
+
+use anchor_lang::error::ErrorCode as AnchorError;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use minimal::{accounts, instruction, ErrorCode, FeeTokenTable, ProofData, PROTOCOL_FEE_BPS};
+use span_test::{program_error, Ledger, ProgramError};
+
+const PRICE: u64 = 10_000;
+
+struct Listing {
+    admin: Pubkey,
+    seller: Pubkey,
+    mint: Pubkey,
+    proof: Pubkey,
+    listing: Pubkey,
+}
+
+// A fee table accepting a fresh mint, and a proof listed in it
+fn listed(ledger: &mut Ledger) -> Listing {
+    let admin = Pubkey::new_unique();
+    ledger.fund(admin, SOL);
+    let fee_tokens = minimal::find_fee_tokens_address().0;
+    run(
+        ledger,
+        accounts::InitializeFeeTokens {
+            fee_tokens,
+            admin,
+            system_program: system_program::ID,
+        },
+        instruction::InitializeFeeTokens {},
+    )
+    .unwrap();
+    let mint = mint(ledger);
+    set_rate(ledger, admin, mint, FeeTokenTable::RATE_SCALE);
+
+    let seller = user(ledger);
+    let proof = proof(ledger, seller, 0, [1; 32]);
+    let listing = minimal::find_listing_address(&proof).0;
+    run(
+        ledger,
+        accounts::ListProofForSale {
+            proof,
+            listing,
+            payment_mint: mint,
+            fee_tokens,
+            seller,
+            system_program: system_program::ID,
+        },
+        instruction::ListProofForSale { price: PRICE },
+    )
+    .unwrap();
+    Listing { admin, seller, mint, proof, listing }
+}
+
+fn set_rate(ledger: &mut Ledger, admin: Pubkey, mint: Pubkey, rate: u64) {
+    run(
+        ledger,
+        accounts::UpdateFeeTokens {
+            fee_tokens: minimal::find_fee_tokens_address().0,
+            admin,
+        },
+        instruction::SetFeeToken { mint, rate },
+    )
+    .unwrap();
+}
+
+struct Sale {
+    buyer: Pubkey,
+    buyer_token: Pubkey,
+    seller_token: Pubkey,
+    treasury_token: Pubkey,
+}
+
+fn buy(ledger: &mut Ledger, listing: &Listing) -> (Sale, std::result::Result<(), ProgramError>) {
+    let buyer = user(ledger);
+    let buyer_token = token_account(ledger, listing.mint, buyer, 10 * PRICE);
+    let sale = Sale {
+        buyer,
+        buyer_token,
+        seller_token: token_account(ledger, listing.mint, listing.seller, 0),
+        treasury_token: token_account(ledger, listing.mint, minimal::treasury_authority(), 0),
+    };
+    let result = run(
+        ledger,
+        accounts::BuyProof {
+            proof: listing.proof,
+            listing: listing.listing,
+            buyer_token: sale.buyer_token,
+            seller_token: sale.seller_token,
+            treasury_token: sale.treasury_token,
+            fee_tokens: minimal::find_fee_tokens_address().0,
+            seller: listing.seller,
+            buyer,
+            token_program: token_program(),
+        },
+        instruction::BuyProof {},
+    );
+    (sale, result)
+}
+
+fn cancel(ledger: &mut Ledger, listing: &Listing, seller: Pubkey) -> std::result::Result<(), ProgramError> {
+    run(
+        ledger,
+        accounts::CancelListing {
+            listing: listing.listing,
+            seller,
+        },
+        instruction::CancelListing {},
+    )
+}
+
+fn update_proof(ledger: &mut Ledger, key: &Pubkey, update: impl FnOnce(&mut ProofData)) {
+    let mut proof: ProofData = ledger.program_account(key);
+    update(&mut proof);
+    ledger.set_program_account(*key, &proof, ProofData::LEN);
+}
+
+#[test]
+fn buy_proof_pays_the_seller_and_the_treasury() {
+    let mut ledger = Ledger::new();
+    let listing = listed(&mut ledger);
+    let (sale, result) = buy(&mut ledger, &listing);
+    result.unwrap();
+
+    let fee = PRICE * PROTOCOL_FEE_BPS / 10_000;
+    assert_eq!(ledger.token_balance(&sale.seller_token), PRICE - fee);
+    assert_eq!(ledger.token_balance(&sale.treasury_token), fee);
+    assert_eq!(ledger.program_account::<ProofData>(&listing.proof).owner, sale.buyer);
+    assert!(!ledger.exists(&listing.listing));
+}
+
+#[test]
+fn buy_proof_converts_the_price_at_the_current_rate() {
+    let mut ledger = Ledger::new();
+    let listing = listed(&mut ledger);
+    set_rate(&mut ledger, listing.admin, listing.mint, 3 * FeeTokenTable::RATE_SCALE / 2);
+    let (sale, result) = buy(&mut ledger, &listing);
+    result.unwrap();
+
+    let amount = 3 * PRICE / 2;
+    let fee = amount * PROTOCOL_FEE_BPS / 10_000;
+    assert_eq!(ledger.token_balance(&sale.buyer_token), 10 * PRICE - amount);
+    assert_eq!(ledger.token_balance(&sale.seller_token), amount - fee);
+    assert_eq!(ledger.token_balance(&sale.treasury_token), fee);
+}
+
+#[test]
+fn buy_proof_rejects_a_consumed_proof() {
+    let mut ledger = Ledger::new();
+    let listing = listed(&mut ledger);
+    update_proof(&mut ledger, &listing.proof, |proof| proof.consumed = true);
+    let (sale, result) = buy(&mut ledger, &listing);
+    assert_eq!(result, Err(program_error(ErrorCode::InvalidProof)));
+    assert_eq!(ledger.token_balance(&sale.seller_token), 0);
+}
+
+#[test]
+fn buy_proof_rejects_a_revoked_proof() {
+    let mut ledger = Ledger::new();
+    let listing = listed(&mut ledger);
+    update_proof(&mut ledger, &listing.proof, |proof| proof.revoked = true);
+    let (_, result) = buy(&mut ledger, &listing);
+    assert_eq!(result, Err(program_error(ErrorCode::ProofExpired)));
+}
+
+#[test]
+fn cancel_listing_returns_the_rent_to_the_seller() {
+    let mut ledger = Ledger::new();
+    let listing = listed(&mut ledger);
+    let rent = ledger.lamports(&listing.listing);
+    let before = ledger.lamports(&listing.seller);
+
+    let stranger = user(&mut ledger);
+    assert_eq!(
+        cancel(&mut ledger, &listing, stranger),
+        Err(program_error(AnchorError::ConstraintHasOne))
+    );
+    cancel(&mut ledger, &listing, listing.seller).unwrap();
+    assert_eq!(ledger.lamports(&listing.seller), before + rent);
+    assert!(!ledger.exists(&listing.listing));
+
+    let (_, result) = buy(&mut ledger, &listing);
+    assert_eq!(result, Err(program_error(AnchorError::AccountNotInitialized)));
+}
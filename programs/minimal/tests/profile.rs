@@ -0,0 +1,119 @@
+// Closing a profile: only an idle one, with nothing still keyed to it,
+// refunds its rent
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use minimal::{accounts, instruction, ErrorCode, Stake};
+use span_test::{program_error, Ledger, ProgramError};
+
+fn close(ledger: &mut Ledger, owner: Pubkey) -> std::result::Result<(), ProgramError> {
+    run(
+        ledger,
+        accounts::CloseUserProfile {
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            username_record: None,
+            stake: minimal::find_stake_address(&owner).0,
+            owner,
+        },
+        instruction::CloseUserProfile {},
+    )
+}
+
+#[test]
+fn close_user_profile_refunds_an_idle_profile() {
+    let mut ledger = Ledger::new();
+    let owner = user(&mut ledger);
+    let profile = minimal::find_user_profile_address(&owner).0;
+    let refund = ledger.lamports(&profile);
+    let before = ledger.lamports(&owner);
+
+    close(&mut ledger, owner).unwrap();
+    assert!(!ledger.exists(&profile));
+    assert_eq!(ledger.lamports(&owner), before + refund);
+}
+
+#[test]
+fn close_user_profile_keeps_a_profile_with_proofs() {
+    let mut ledger = Ledger::new();
+    let owner = user(&mut ledger);
+    update_profile(&mut ledger, &owner, |profile| profile.proof_count = 1);
+    assert_eq!(
+        close(&mut ledger, owner),
+        Err(program_error(ErrorCode::ProfileHasObligations))
+    );
+}
+
+#[test]
+fn close_user_profile_waits_for_the_stake() {
+    let mut ledger = Ledger::new();
+    let owner = user(&mut ledger);
+    let mut stake = Stake {
+        owner,
+        mint: mint(&mut ledger),
+        amount: 500,
+        staked_at: ledger.clock().unix_timestamp,
+    };
+    let key = minimal::find_stake_address(&owner).0;
+    ledger.set_program_account(key, &stake, Stake::LEN);
+    assert_eq!(
+        close(&mut ledger, owner),
+        Err(program_error(ErrorCode::ProfileHasObligations))
+    );
+
+    // A fully unstaked account no longer holds the profile open
+    stake.amount = 0;
+    ledger.set_program_account(key, &stake, Stake::LEN);
+    close(&mut ledger, owner).unwrap();
+}
+
+#[test]
+fn close_user_profile_waits_for_allowances() {
+    let mut ledger = Ledger::new();
+    let owner = user(&mut ledger);
+    let mint = mint(&mut ledger);
+    let token_account = token_account(&mut ledger, mint, owner, 1_000);
+    let delegate = Pubkey::new_unique();
+    let allowance = minimal::find_allowance_address(&owner, &delegate).0;
+    let expiry = ledger.clock().unix_timestamp + 3_600;
+    run(
+        &mut ledger,
+        accounts::ApproveDelegate {
+            allowance,
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            token_account,
+            spend_authority: minimal::find_spend_authority_address(&owner, &delegate).0,
+            owner,
+            token_program: token_program(),
+            system_program: system_program::ID,
+        },
+        instruction::ApproveDelegate {
+            delegate,
+            limit: 100,
+            expiry,
+        },
+    )
+    .unwrap();
+    assert_eq!(profile(&ledger, &owner).open_allowances, 1);
+    assert_eq!(
+        close(&mut ledger, owner),
+        Err(program_error(ErrorCode::ProfileHasObligations))
+    );
+
+    run(
+        &mut ledger,
+        accounts::RevokeDelegate {
+            allowance,
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            token_account,
+            spend_authority: minimal::find_spend_authority_address(&owner, &delegate).0,
+            owner,
+            token_program: token_program(),
+        },
+        instruction::RevokeDelegate {},
+    )
+    .unwrap();
+    close(&mut ledger, owner).unwrap();
+}
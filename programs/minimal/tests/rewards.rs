@@ -0,0 +1,132 @@
+// Proof rewards: the epoch cap limits what each epoch mints, and only
+// applies while epochs roll over
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use minimal::{accounts, instruction, Challenge, Config, ErrorCode};
+use span_test::{program_error, Ledger, ProgramError};
+
+type Outcome = std::result::Result<(), ProgramError>;
+
+const EPOCH: i64 = 3_600;
+
+fn set_retarget(ledger: &mut Ledger, admin: Pubkey, epoch_seconds: i64) -> Outcome {
+    run(
+        ledger,
+        accounts::UpdateConfig {
+            config: minimal::find_config_address().0,
+            admin,
+        },
+        instruction::SetRetarget {
+            target_proofs_per_epoch: 0,
+            epoch_seconds,
+        },
+    )
+}
+
+fn set_reward_schedule(ledger: &mut Ledger, admin: Pubkey, epoch_reward_cap: u64) -> Outcome {
+    run(
+        ledger,
+        accounts::UpdateConfig {
+            config: minimal::find_config_address().0,
+            admin,
+        },
+        instruction::SetRewardSchedule {
+            initial_reward: 100,
+            halving_interval: 0,
+            epoch_reward_cap,
+        },
+    )
+}
+
+// A config minting rewards, with a challenge salt in place
+fn rewarding(ledger: &mut Ledger) -> Pubkey {
+    let admin = config(ledger);
+    run(
+        ledger,
+        accounts::InitializeRewardMint {
+            config: minimal::find_config_address().0,
+            reward_mint: minimal::find_reward_mint_address().0,
+            admin,
+            token_program: token_program(),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeRewardMint { _decimals: 0 },
+    )
+    .unwrap();
+    let challenge = Challenge {
+        salt: [7; 32],
+        previous_salt: [0; 32],
+        epoch: 0,
+        refreshed_slot: ledger.clock().slot,
+    };
+    ledger.set_program_account(minimal::find_challenge_address().0, &challenge, Challenge::LEN);
+    admin
+}
+
+// Submit a proof from `owner`; any nonce meets the config's easiest target
+fn submit(ledger: &mut Ledger, owner: Pubkey, reward_account: Pubkey) -> Outcome {
+    let proof_count = profile(ledger, &owner).proof_count;
+    run(
+        ledger,
+        accounts::SubmitProof {
+            proof: minimal::find_proof_address(&owner, proof_count).0,
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            config: minimal::find_config_address().0,
+            challenge: minimal::find_challenge_address().0,
+            reward_mint: Some(minimal::find_reward_mint_address().0),
+            reward_account: Some(reward_account),
+            token_program: Some(token_program()),
+            leaderboard: None,
+            stake: None,
+            owner,
+            system_program: system_program::ID,
+        },
+        instruction::SubmitProof {
+            data_hash: [proof_count as u8; 32],
+            nonce: 0,
+        },
+    )
+}
+
+#[test]
+fn epoch_reward_cap_resets_each_epoch() {
+    let mut ledger = Ledger::new();
+    let admin = rewarding(&mut ledger);
+    set_retarget(&mut ledger, admin, EPOCH).unwrap();
+    set_reward_schedule(&mut ledger, admin, 150).unwrap();
+    let owner = user(&mut ledger);
+    let rewards = token_account(&mut ledger, minimal::find_reward_mint_address().0, owner, 0);
+
+    for _ in 0..3 {
+        submit(&mut ledger, owner, rewards).unwrap();
+    }
+    assert_eq!(ledger.token_balance(&rewards), 150);
+
+    ledger.advance(EPOCH);
+    submit(&mut ledger, owner, rewards).unwrap();
+    assert_eq!(ledger.token_balance(&rewards), 250);
+    let config: Config = ledger.program_account(&minimal::find_config_address().0);
+    assert_eq!(config.epoch_rewards, 100);
+}
+
+#[test]
+fn epoch_reward_cap_needs_an_epoch_length() {
+    let mut ledger = Ledger::new();
+    let admin = rewarding(&mut ledger);
+    assert_eq!(
+        set_reward_schedule(&mut ledger, admin, 150),
+        Err(program_error(ErrorCode::InvalidRewardSchedule))
+    );
+    set_reward_schedule(&mut ledger, admin, 0).unwrap();
+
+    set_retarget(&mut ledger, admin, EPOCH).unwrap();
+    set_reward_schedule(&mut ledger, admin, 150).unwrap();
+    assert_eq!(
+        set_retarget(&mut ledger, admin, 0),
+        Err(program_error(ErrorCode::InvalidRewardSchedule))
+    );
+}
@@ -0,0 +1,129 @@
+// Treasury: interaction fees collect under the treasury PDA and only the
+// config admin can pay them out
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use minimal::{accounts, instruction, ErrorCode};
+use span_test::{program_error, Ledger, ProgramError};
+
+type Outcome = std::result::Result<(), ProgramError>;
+
+const FEE_BPS: u16 = 500;
+
+// A config charging FEE_BPS on interactions
+fn charging(ledger: &mut Ledger) -> Pubkey {
+    let admin = config(ledger);
+    run(
+        ledger,
+        accounts::UpdateConfig {
+            config: minimal::find_config_address().0,
+            admin,
+        },
+        instruction::SetInteractionFee {
+            interaction_fee_bps: FEE_BPS,
+        },
+    )
+    .unwrap();
+    admin
+}
+
+fn withdraw_tokens(ledger: &mut Ledger, admin: Pubkey, mint: Pubkey, treasury_token: Pubkey, to: Pubkey, amount: u64) -> Outcome {
+    run(
+        ledger,
+        accounts::WithdrawTreasury {
+            config: minimal::find_config_address().0,
+            treasury: minimal::treasury_authority(),
+            mint,
+            treasury_token,
+            to,
+            admin,
+            token_program: token_program(),
+        },
+        instruction::WithdrawTreasury { amount },
+    )
+}
+
+fn withdraw_sol(ledger: &mut Ledger, admin: Pubkey, to: Pubkey, amount: u64) -> Outcome {
+    run(
+        ledger,
+        accounts::WithdrawTreasurySol {
+            config: minimal::find_config_address().0,
+            treasury: minimal::treasury_authority(),
+            to,
+            admin,
+            system_program: system_program::ID,
+        },
+        instruction::WithdrawTreasurySol { amount },
+    )
+}
+
+#[test]
+fn admin_withdraws_token_fees() {
+    let mut ledger = Ledger::new();
+    let admin = charging(&mut ledger);
+    let owner = user(&mut ledger);
+    let mint = mint(&mut ledger);
+    let from = token_account(&mut ledger, mint, owner, 10_000);
+    let to = token_account(&mut ledger, mint, Pubkey::new_unique(), 0);
+    let treasury_token = token_account(&mut ledger, mint, minimal::treasury_authority(), 0);
+    run(
+        &mut ledger,
+        accounts::ProcessInteraction {
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            mint,
+            from,
+            to,
+            config: minimal::find_config_address().0,
+            treasury_token,
+            owner,
+            token_program: token_program(),
+        },
+        instruction::ProcessInteraction { amount: 10_000 },
+    )
+    .unwrap();
+    assert_eq!(ledger.token_balance(&treasury_token), 500);
+
+    let payout = token_account(&mut ledger, mint, admin, 0);
+    assert_eq!(
+        withdraw_tokens(&mut ledger, owner, mint, treasury_token, from, 500),
+        Err(program_error(ErrorCode::Unauthorized))
+    );
+    withdraw_tokens(&mut ledger, admin, mint, treasury_token, payout, 500).unwrap();
+    assert_eq!(ledger.token_balance(&payout), 500);
+    assert_eq!(ledger.token_balance(&treasury_token), 0);
+}
+
+#[test]
+fn admin_withdraws_sol_fees() {
+    let mut ledger = Ledger::new();
+    let admin = charging(&mut ledger);
+    let owner = user(&mut ledger);
+    let treasury = minimal::treasury_authority();
+    run(
+        &mut ledger,
+        accounts::ProcessSolInteraction {
+            user_profile: minimal::find_user_profile_address(&owner).0,
+            to: Pubkey::new_unique(),
+            config: minimal::find_config_address().0,
+            treasury,
+            owner,
+            system_program: system_program::ID,
+        },
+        instruction::ProcessSolInteraction { amount: 2 * SOL },
+    )
+    .unwrap();
+    let fees = 2 * SOL * FEE_BPS as u64 / 10_000;
+    assert_eq!(ledger.lamports(&treasury), fees);
+
+    let payout = Pubkey::new_unique();
+    assert_eq!(
+        withdraw_sol(&mut ledger, owner, payout, fees),
+        Err(program_error(ErrorCode::Unauthorized))
+    );
+    withdraw_sol(&mut ledger, admin, payout, fees).unwrap();
+    assert_eq!(ledger.lamports(&payout), fees);
+    assert!(!ledger.exists(&treasury));
+}
@@ -1,5 +1,18 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::hash::{hash, Hash};
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::solana_program::hash::{hash, hashv, Hash};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::{ed25519_program, secp256k1_program};
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use spl_account_compression::program::SplAccountCompression;
+use spl_account_compression::{wrap_application_data_v1, Noop};
+use unicode_normalization::UnicodeNormalization;
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -7,138 +20,7976 @@ declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 pub mod nlp_chain {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        genesis: GenesisParams,
+        config: ChainConfig,
+    ) -> Result<()> {
+        require!(config.bucket_interval > 0, NLPChainError::InvalidChainConfig);
+        require!(
+            genesis.model_id.len() <= MAX_MODEL_ID_LEN
+                && config.vector_dim > 0
+                && config.vector_dim as usize <= MAX_VECTOR_DIM,
+            NLPChainError::InvalidChainConfig
+        );
+        require!(
+            config.max_text_len as usize <= MAX_TEXT_LEN
+                && genesis.title.len() <= config.max_text_len as usize
+                && config.max_metadata_entries as usize <= MAX_METADATA_ENTRIES,
+            NLPChainError::InvalidChainConfig
+        );
+
         let chain_state = &mut ctx.accounts.chain_state;
+        let genesis_block = &mut ctx.accounts.genesis_block;
+
+        // Block 0 carries the chain's provenance; its hash commits to every field
+        let genesis_hash = hash(&genesis.try_to_vec()?);
+        let genesis_block_model_id = genesis.model_id.clone();
+        genesis_block.version = BLOCK_VERSION;
+        genesis_block.authority = ctx.accounts.authority.key();
+        genesis_block.index = 0;
+        genesis_block.timestamp = Clock::get()?.unix_timestamp;
+        genesis_block.metadata = genesis.to_metadata();
+        genesis_block.license = License::from_spdx(&genesis.license);
+        genesis_block.rent_payer = ctx.accounts.authority.key();
+        validate_metadata(&genesis_block.metadata)?;
+        genesis_block.text = genesis.title;
+        genesis_block.vector = Vec::new();
+        genesis_block.data_hash = genesis_hash;
+        genesis_block.previous_hash = Hash::default();
+
+        chain_state.version = CHAIN_STATE_VERSION;
         chain_state.authority = ctx.accounts.authority.key();
-        chain_state.block_count = 0;
-        chain_state.last_hash = hash(&[0; 32]);
+        chain_state.block_count = 1;
+        chain_state.last_hash = genesis_hash;
+        chain_state.parent_chain = Pubkey::default();
+        chain_state.fork_index = 0;
+        chain_state.fork_count = 0;
+        chain_state.validator_epochs = 0;
+        chain_state.bucket_interval = config.bucket_interval;
+        chain_state.model_id = genesis_block_model_id;
+        chain_state.model_version = config.model_version;
+        chain_state.vector_dim = config.vector_dim;
+        chain_state.embedder = Pubkey::default();
+        chain_state.compressed_tree = Pubkey::default();
+        chain_state.compressed_count = 0;
+        chain_state.bloom_enabled = false;
+        chain_state.min_slot_gap = 0;
+        chain_state.rate_window_slots = 0;
+        chain_state.max_blocks_per_window = 0;
+        chain_state.fee_per_byte = 0;
+        chain_state.treasury = Pubkey::default();
+        chain_state.checkpoint_count = 0;
+        chain_state.archived_count = 0;
+        chain_state.archived_hash = Hash::default();
+        chain_state.pass_mint = Pubkey::default();
+        chain_state.pass_price = 0;
+        chain_state.pass_epoch_seconds = 0;
+        chain_state.pow_max_age = 0;
+        chain_state.stats_enabled = false;
+        chain_state.wormhole_enabled = false;
+        chain_state.features = 0;
+        chain_state.default_license = genesis_block.license;
+        chain_state.allowed_licenses = LICENSE_ALL;
+        chain_state.max_text_len = config.max_text_len;
+        chain_state.max_metadata_entries = config.max_metadata_entries;
+        chain_state.required_attestations = 0;
+        chain_state.checkpoint_interval = 0;
+        chain_state.checkpoint_bounty = 0;
+        chain_state.checkpointed_count = 0;
+        chain_state.nft_tree = Pubkey::default();
+        chain_state.block_ttl = 0;
+        chain_state.max_sub_vectors = 0;
+        chain_state.sparse_dim = 0;
+        chain_state.namespace_count = 0;
+        chain_state.pin_mint = Pubkey::default();
+        chain_state.required_approvals = 0;
+        chain_state.created_at = genesis_block.timestamp;
+        chain_state.genesis_config = config;
+        chain_state.quota_bytes = 0;
+        chain_state.quota_epoch_seconds = 0;
+        chain_state.source_checkpoint = None;
+        chain_state.clone_count = 0;
+        chain_state.max_unchecked_blocks = 0;
+        chain_state.linked_count = 1;
+        Ok(())
+    }
+
+    // Branch a new chain off `parent_chain` at `fork_index`. Blocks up to and
+    // including the fork point are shared with the parent, not copied.
+    pub fn fork_chain(ctx: Context<ForkChain>, fork_index: u64) -> Result<()> {
+        let parent_chain = &mut ctx.accounts.parent_chain;
+        let parent_block = &ctx.accounts.parent_block;
+        let child_chain = &mut ctx.accounts.child_chain;
+
+        derive_chain_state(
+            child_chain,
+            parent_chain,
+            ctx.accounts.authority.key(),
+            fork_index + 1,
+            parent_block.data_hash,
+        )?;
+
+        parent_chain.fork_count += 1;
+        Ok(())
+    }
+
+    // Start a derivative chain from one of `source_chain`'s checkpoints. Only
+    // the header is created: blocks [0, checkpoint.block_count) stay with the
+    // source and the clone continues from the checkpoint's last hash.
+    pub fn clone_chain(ctx: Context<CloneChain>, checkpoint_index: u64) -> Result<()> {
+        let source_chain = &mut ctx.accounts.source_chain;
+        let checkpoint = &ctx.accounts.checkpoint;
+        let clone = &mut ctx.accounts.clone_chain;
+
+        derive_chain_state(
+            clone,
+            source_chain,
+            ctx.accounts.authority.key(),
+            checkpoint.block_count,
+            checkpoint.last_hash,
+        )?;
+        clone.source_checkpoint = Some(checkpoint_index);
+
+        source_chain.clone_count += 1;
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn add_block(
         ctx: Context<AddBlock>,
         text: String,
+        minhash: Vec<u32>,
+        vector: Vec<f64>,
+        metadata: Vec<MetadataEntry>,
+        expected_index: u64,
+        expected_parent_hash: [u8; 32],
+        model: ModelRef,
+        encryption_pubkey: Option<Pubkey>,
+        license: Option<License>,
+        keywords: Vec<String>,
+    ) -> Result<()> {
+        append_block(
+            ctx,
+            text,
+            minhash,
+            vector,
+            metadata,
+            expected_index,
+            expected_parent_hash,
+            model,
+            encryption_pubkey,
+            license,
+            None,
+            keywords,
+        )
+    }
+
+    // Same as add_block, authored by an Ethereum address. A secp256k1 program
+    // instruction earlier in the transaction must carry `eth_author`'s
+    // signature over the block's data hash.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_block_secp(
+        ctx: Context<AddBlock>,
+        text: String,
+        minhash: Vec<u32>,
         vector: Vec<f64>,
-        metadata: String,
+        metadata: Vec<MetadataEntry>,
+        expected_index: u64,
+        expected_parent_hash: [u8; 32],
+        model: ModelRef,
+        encryption_pubkey: Option<Pubkey>,
+        license: Option<License>,
+        eth_author: [u8; 20],
+        keywords: Vec<String>,
     ) -> Result<()> {
+        append_block(
+            ctx,
+            text,
+            minhash,
+            vector,
+            metadata,
+            expected_index,
+            expected_parent_hash,
+            model,
+            encryption_pubkey,
+            license,
+            Some(eth_author),
+            keywords,
+        )
+    }
+
+    // Replace a block's embedding. The block is edited in place in its
+    // account data rather than deserialized, so text and metadata are never
+    // copied onto the heap.
+    pub fn update_vector(
+        ctx: Context<UpdateVector>,
+        _block_index: u64,
+        new_vector: Vec<f64>,
+        model: ModelRef,
+    ) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        require!(
+            !chain_state.has_feature(FEATURE_IMMUTABLE_VECTORS),
+            NLPChainError::FeatureDisabled
+        );
+        chain_state.check_embedding(&model, &new_vector)?;
+
+        let info = ctx.accounts.block.to_account_info();
+        let data_hash = {
+            let data = info.try_borrow_data()?;
+            check_block_data(&info, &data)?;
+            require!(
+                data[BLOCK_AUTHORITY_OFFSET..BLOCK_AUTHORITY_OFFSET + 32]
+                    == ctx.accounts.authority.key().to_bytes(),
+                NLPChainError::UnauthorizedUpdate
+            );
+            Hash::new_from_array(data[BLOCK_DATA_HASH_OFFSET..BLOCK_DATA_HASH_OFFSET + 32].try_into().unwrap())
+        };
+        grow_account(
+            &info,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            chain_state.block_space(),
+        )?;
+        if chain_state.embedder != Pubkey::default() {
+            let keyword_hashes: [u64; MAX_KEYWORDS] =
+                read_block_words(&info, BLOCK_KEYWORD_HASHES_OFFSET, MAX_KEYWORDS)?
+                    .try_into()
+                    .unwrap();
+            verify_embedder_signature(
+                ctx.accounts.instructions.as_ref(),
+                &chain_state.embedder,
+                &data_hash,
+                &new_vector,
+                &keyword_hashes,
+            )?;
+        }
+
+        let mut data = info.try_borrow_mut_data()?;
+        data[BLOCK_MODEL_VERSION_OFFSET..BLOCK_MODEL_VERSION_OFFSET + 4]
+            .copy_from_slice(&model.model_version.to_le_bytes());
+        data[BLOCK_VECTOR_NORM_OFFSET..BLOCK_VECTOR_NORM_OFFSET + 8]
+            .copy_from_slice(&fixed_point_norm(&new_vector).to_le_bytes());
+        for (i, word) in binary_quantize(&new_vector).iter().enumerate() {
+            let offset = BLOCK_BINARY_VECTOR_OFFSET + i * 8;
+            data[offset..offset + 8].copy_from_slice(&word.to_le_bytes());
+        }
+        let stored: &[f64] = if chain_state.has_feature(FEATURE_BINARY_ONLY) {
+            &[]
+        } else {
+            &new_vector
+        };
+        write_block_vector(&mut data, stored)
+    }
+
+    // View: current chain head (returned via return data for CPI callers)
+    pub fn chain_head(ctx: Context<ChainHeadView>) -> Result<ChainHead> {
+        let chain_state = &ctx.accounts.chain_state;
+        Ok(ChainHead {
+            block_count: chain_state.block_count,
+            last_hash: chain_state.last_hash.to_bytes(),
+        })
+    }
+
+    // Schedule a new attestation committee. The first set is active
+    // immediately; later sets activate after VALIDATOR_ROTATION_DELAY slots
+    // and retire their predecessor at that slot.
+    pub fn rotate_validators(ctx: Context<RotateValidators>, new_set: Vec<Pubkey>) -> Result<()> {
+        require!(
+            !new_set.is_empty() && new_set.len() <= ValidatorSet::MAX_VALIDATORS,
+            NLPChainError::InvalidValidatorSet
+        );
+
         let chain_state = &mut ctx.accounts.chain_state;
-        let block = &mut ctx.accounts.block;
+        let slot = Clock::get()?.slot;
+        let epoch = chain_state.validator_epochs;
 
-        // Update block data
-        block.authority = ctx.accounts.authority.key();
-        block.index = chain_state.block_count;
-        block.timestamp = Clock::get()?.unix_timestamp;
-        block.text = text;
-        block.vector = vector;
-        block.metadata = metadata;
-        
-        // Calculate and store hashes
-        let data_hash = hash(&block.text.as_bytes());
-        block.data_hash = data_hash;
-        block.previous_hash = chain_state.last_hash;
-        
-        // Update chain state
-        chain_state.last_hash = data_hash;
-        chain_state.block_count += 1;
+        let activation_slot = if epoch == 0 {
+            slot
+        } else {
+            let previous = ctx
+                .accounts
+                .previous_set
+                .as_mut()
+                .ok_or(NLPChainError::InvalidValidatorSet)?;
+            require!(
+                previous.chain == chain_state.key() && previous.epoch == epoch - 1,
+                NLPChainError::InvalidValidatorSet
+            );
+            let activation_slot = slot + VALIDATOR_ROTATION_DELAY;
+            previous.retired_slot = activation_slot;
+            activation_slot
+        };
 
+        let validator_set = &mut ctx.accounts.validator_set;
+        validator_set.chain = chain_state.key();
+        validator_set.epoch = epoch;
+        validator_set.validators = new_set;
+        validator_set.activation_slot = activation_slot;
+        validator_set.retired_slot = u64::MAX;
+
+        chain_state.validator_epochs += 1;
         Ok(())
     }
 
-    pub fn update_vector(
-        ctx: Context<UpdateVector>,
-        new_vector: Vec<f64>
+    // View: whether `attester` belonged to the committee active at `slot`
+    // and signed `attestation_message` for this chain. An earlier instruction
+    // in the transaction must be the attester's ed25519 verification.
+    pub fn verify_attestation(
+        ctx: Context<VerifyAttestation>,
+        attester: Pubkey,
+        slot: u64,
+        data_hash: [u8; 32],
+    ) -> Result<bool> {
+        let validator_set = &ctx.accounts.validator_set;
+        if !validator_set.is_active_at(slot) || !validator_set.validators.contains(&attester) {
+            return Ok(false);
+        }
+        verify_ed25519_message(
+            Some(&ctx.accounts.instructions),
+            &attester,
+            &attestation_message(&ctx.accounts.chain_state.key(), slot, &Hash::new_from_array(data_hash)),
+            NLPChainError::InvalidAttestationSignature,
+        )?;
+        Ok(true)
+    }
+
+    // Subscribe `target_chain` to blocks appended to `source_chain` from now on
+    pub fn syndicate(ctx: Context<Syndicate>) -> Result<()> {
+        let syndication = &mut ctx.accounts.syndication;
+        syndication.source_chain = ctx.accounts.source_chain.key();
+        syndication.target_chain = ctx.accounts.target_chain.key();
+        syndication.next_source_index = ctx.accounts.source_chain.block_count;
+        syndication.copied_count = 0;
+        syndication.skipped_count = 0;
+        Ok(())
+    }
+
+    // Crank: copy the next unsynced source block into the target chain and
+    // record an attribution edge back to its origin. Callable by anyone.
+    // Blocks that can't be copied are stepped past with skip_syndicated_block.
+    pub fn sync_syndicated_block(ctx: Context<SyncSyndicatedBlock>) -> Result<()> {
+        let syndication = &mut ctx.accounts.syndication;
+        let source_block = &ctx.accounts.source_block;
+        let target_chain = &mut ctx.accounts.target_chain;
+        require!(
+            source_block.is_finalized(&ctx.accounts.source_chain),
+            NLPChainError::BlockNotFinalized
+        );
+        require!(!source_block.retracted, NLPChainError::AlreadyRetracted);
+        require!(!source_block.pending, NLPChainError::BlockPending);
+        let source_model = ModelRef {
+            model_id: ctx.accounts.source_chain.model_id.clone(),
+            model_version: source_block.model_version,
+        };
+        target_chain.check_embedding(&source_model, &source_block.vector)?;
+        target_chain.check_content(&source_block.text, &source_block.metadata)?;
+        target_chain.check_backpressure()?;
+
+        let target_block = &mut ctx.accounts.target_block;
+
+        target_block.version = BLOCK_VERSION;
+        target_block.authority = source_block.authority;
+        target_block.index = target_chain.block_count;
+        target_block.timestamp = Clock::get()?.unix_timestamp;
+        target_block.text = source_block.text.clone();
+        target_block.vector = if target_chain.has_feature(FEATURE_BINARY_ONLY) {
+            Vec::new()
+        } else {
+            source_block.vector.clone()
+        };
+        target_block.vector_norm = source_block.vector_norm;
+        target_block.binary_vector = source_block.binary_vector;
+        target_block.keyword_hashes = source_block.keyword_hashes;
+        target_block.metadata = source_block.metadata.clone();
+        target_block.model_version = source_block.model_version;
+        target_block.encryption_pubkey = source_block.encryption_pubkey;
+        target_block.license = source_block.license;
+        target_block.rent_payer = ctx.accounts.cranker.key();
+        target_block.minhash = source_block.minhash.clone();
+        target_block.data_hash = source_block.data_hash;
+        target_block.hash_scheme = source_block.hash_scheme;
+
+        let registry = &mut ctx.accounts.hash_registry;
+        registry.chain = target_chain.key();
+        registry.data_hash = source_block.data_hash;
+        registry.block_index = target_block.index;
+        insert_into_bloom(
+            target_chain,
+            ctx.accounts.bloom_filter.as_ref(),
+            &source_block.data_hash,
+        )?;
+
+        let edge = &mut ctx.accounts.edge;
+        edge.target_chain = target_chain.key();
+        edge.target_index = target_block.index;
+        edge.source_chain = syndication.source_chain;
+        edge.source_index = source_block.index;
+        edge.source_hash = source_block.data_hash;
+
+        link_new_block(target_chain, target_block);
+        syndication.next_source_index += 1;
+        syndication.copied_count += 1;
+        Ok(())
+    }
+
+    // Crank: step past the next source block when it can never be copied:
+    // it was closed or retracted, its embedding or content doesn't fit the
+    // target chain, or the target already holds the same content. Callable
+    // by anyone. The target chain's authority may also skip a block that is
+    // still waiting for attestations.
+    pub fn skip_syndicated_block(ctx: Context<SkipSyndicatedBlock>) -> Result<()> {
+        let syndication = &mut ctx.accounts.syndication;
+        let source_chain = &ctx.accounts.source_chain;
+        let target_chain = &ctx.accounts.target_chain;
+        require!(
+            syndication.next_source_index < source_chain.block_count,
+            NLPChainError::BlockNotSkippable
+        );
+
+        let info = ctx.accounts.source_block.to_account_info();
+        let by_authority = ctx
+            .accounts
+            .authority
+            .as_ref()
+            .is_some_and(|authority| authority.key() == target_chain.authority);
+        let skippable = info.data_is_empty() || {
+            require_keys_eq!(*info.owner, crate::ID, NLPChainError::InvalidAccountVersion);
+            let source_block = Block::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            let source_model = ModelRef {
+                model_id: source_chain.model_id.clone(),
+                model_version: source_block.model_version,
+            };
+            let (registry_address, _) =
+                find_hash_registry_address(&target_chain.key(), &source_block.text);
+            source_block.retracted
+                || ctx.accounts.hash_registry.as_ref().is_some_and(|registry| registry.key() == registry_address)
+                || target_chain.check_embedding(&source_model, &source_block.vector).is_err()
+                || target_chain.check_content(&source_block.text, &source_block.metadata).is_err()
+                || (by_authority && !source_block.is_finalized(source_chain))
+        };
+        require!(skippable, NLPChainError::BlockNotSkippable);
+
+        syndication.next_source_index += 1;
+        syndication.skipped_count += 1;
+        Ok(())
+    }
+
+    // Grant `moderator` moderation rights on the chain
+    pub fn add_moderator(ctx: Context<AddModerator>, moderator: Pubkey) -> Result<()> {
+        let record = &mut ctx.accounts.moderator_record;
+        record.chain = ctx.accounts.chain_state.key();
+        record.moderator = moderator;
+        Ok(())
+    }
+
+    // Revoke a moderator; rent returns to the chain authority
+    pub fn remove_moderator(_ctx: Context<RemoveModerator>) -> Result<()> {
+        Ok(())
+    }
+
+    // Tag a block. Allowed for the block author, the chain authority, and moderators.
+    pub fn tag_block(ctx: Context<TagBlock>, tag: String, _block_index: u64) -> Result<()> {
+        let tag = normalize_tag(&tag)?;
+        let block = &ctx.accounts.block;
+        let tagger = ctx.accounts.tagger.key();
+        require!(
+            tagger == block.authority
+                || tagger == ctx.accounts.chain_state.authority
+                || ctx.accounts.moderator.is_some(),
+            NLPChainError::UnauthorizedUpdate
+        );
+
+        let tag_hash = hash(tag.as_bytes());
+        let entry = &mut ctx.accounts.tag_entry;
+        entry.chain = ctx.accounts.chain_state.key();
+        entry.tag_hash = tag_hash;
+        entry.block_index = block.index;
+        entry.tagged_by = tagger;
+
+        let counter = &mut ctx.accounts.tag_counter;
+        if counter.count == 0 {
+            counter.chain = ctx.accounts.chain_state.key();
+            counter.tag_hash = tag_hash;
+            counter.tag = tag;
+        }
+        counter.count += 1;
+        Ok(())
+    }
+
+    // Remove a tag. Allowed for whoever applied it, the chain authority, and moderators.
+    pub fn untag_block(
+        ctx: Context<UntagBlock>,
+        _tag_hash: [u8; 32],
+        _block_index: u64,
     ) -> Result<()> {
-        let block = &mut ctx.accounts.block;
+        let signer = ctx.accounts.signer.key();
         require!(
-            ctx.accounts.authority.key() == block.authority,
+            signer == ctx.accounts.tag_entry.tagged_by
+                || signer == ctx.accounts.chain_state.authority
+                || ctx.accounts.moderator.is_some(),
             NLPChainError::UnauthorizedUpdate
         );
-        
-        block.vector = new_vector;
+
+        let counter = &mut ctx.accounts.tag_counter;
+        counter.count = counter.count.saturating_sub(1);
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = ChainState::LEN
-    )]
-    pub chain_state: Account<'info, ChainState>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    // Record that block `source_index` cites block `target_index`. Only the
+    // citing block's author can add its citations.
+    pub fn cite_block(ctx: Context<CiteBlock>, source_index: u64, target_index: u64) -> Result<()> {
+        require!(source_index != target_index, NLPChainError::InvalidCitation);
+        require!(
+            ctx.accounts.source_block.authority == ctx.accounts.author.key(),
+            NLPChainError::UnauthorizedUpdate
+        );
 
-#[derive(Accounts)]
-pub struct AddBlock<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = Block::LEN,
-        seeds = [b"block", chain_state.block_count.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub block: Account<'info, Block>,
-    
-    #[account(mut)]
-    pub chain_state: Account<'info, ChainState>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        let citation = &mut ctx.accounts.citation;
+        citation.chain = ctx.accounts.chain_state.key();
+        citation.source_index = source_index;
+        citation.target_index = target_index;
+        citation.timestamp = Clock::get()?.unix_timestamp;
 
-#[derive(Accounts)]
-pub struct UpdateVector<'info> {
-    #[account(mut)]
-    pub block: Account<'info, Block>,
-    pub authority: Signer<'info>,
-}
+        let target_block = &mut ctx.accounts.target_block;
+        target_block.citation_count += 1;
+        Ok(())
+    }
 
-#[account]
-pub struct ChainState {
-    pub authority: Pubkey,
-    pub block_count: u64,
-    pub last_hash: Hash,
-}
+    // Record a typed relationship from block `from_index` to block `to_index`.
+    // Any writer on the chain may create links.
+    pub fn link_blocks(
+        ctx: Context<LinkBlocks>,
+        from_index: u64,
+        to_index: u64,
+        kind: LinkKind,
+        score_bps: u16,
+    ) -> Result<()> {
+        require!(from_index != to_index, NLPChainError::InvalidLink);
+        require!(score_bps <= 10_000, NLPChainError::InvalidLink);
 
-impl ChainState {
-    pub const LEN: usize = 8 + // discriminator
-        32 + // authority
-        8 + // block_count
-        32; // last_hash
-}
+        let link = &mut ctx.accounts.link;
+        link.chain = ctx.accounts.chain_state.key();
+        link.from_index = from_index;
+        link.to_index = to_index;
+        link.kind = kind;
+        link.score_bps = score_bps;
+        link.creator = ctx.accounts.creator.key();
+        link.timestamp = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
 
-#[account]
-pub struct Block {
-    pub authority: Pubkey,
-    pub index: u64,
-    pub timestamp: i64,
-    pub text: String,
-    pub vector: Vec<f64>,
-    pub metadata: String,
-    pub data_hash: Hash,
-    pub previous_hash: Hash,
-}
+    // Revoke a link. Allowed for the chain authority and moderators.
+    pub fn revoke_link(ctx: Context<RevokeLink>, _from_index: u64, _to_index: u64) -> Result<()> {
+        require!(
+            ctx.accounts.signer.key() == ctx.accounts.chain_state.authority
+                || ctx.accounts.moderator.is_some(),
+            NLPChainError::UnauthorizedUpdate
+        );
+        Ok(())
+    }
 
-impl Block {
-    pub const LEN: usize = 8 + // discriminator
-        32 + // authority
-        8 + // index
-        8 + // timestamp
-        4 + 1000 + // text (max 1000 chars)
-        4 + 768 * 8 + // vector (max 768 f64 values)
-        4 + 500 + // metadata (max 500 chars)
-        32 + // data_hash
-        32; // previous_hash
-}
+    // Admin migration: switch the chain to a new embedding model. Blocks
+    // written under the old model keep their recorded `model_version`.
+    pub fn migrate_model(
+        ctx: Context<MigrateModel>,
+        model_id: String,
+        model_version: u32,
+        vector_dim: u16,
+    ) -> Result<()> {
+        require!(
+            model_id.len() <= MAX_MODEL_ID_LEN
+                && vector_dim > 0
+                && vector_dim as usize <= MAX_VECTOR_DIM,
+            NLPChainError::InvalidChainConfig
+        );
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.model_id = model_id;
+        chain_state.model_version = model_version;
+        chain_state.vector_dim = vector_dim;
+        Ok(())
+    }
 
-#[error_code]
-pub enum NLPChainError {
-    #[msg("Only the authority can update block data")]
-    UnauthorizedUpdate,
-} 
\ No newline at end of file
+    // Register (or with the default pubkey, clear) the trusted embedder whose
+    // ed25519 signature must accompany every submitted vector
+    pub fn set_embedder(ctx: Context<SetEmbedder>, embedder: Pubkey) -> Result<()> {
+        ctx.accounts.chain_state.embedder = embedder;
+        Ok(())
+    }
+
+    // Replace the chain's FEATURE_* bitmask
+    pub fn set_features(ctx: Context<SetFeatures>, features: u64) -> Result<()> {
+        require!(features & !FEATURE_ALL == 0, NLPChainError::InvalidChainConfig);
+        ctx.accounts.chain_state.features = features;
+        Ok(())
+    }
+
+    // Allow `writer` to add blocks while FEATURE_ALLOWLIST_ONLY is set
+    pub fn add_writer(ctx: Context<AddWriter>, writer: Pubkey) -> Result<()> {
+        let permit = &mut ctx.accounts.writer_permit;
+        permit.chain = ctx.accounts.chain_state.key();
+        permit.writer = writer;
+        Ok(())
+    }
+
+    // Remove a writer from the allowlist; rent returns to the chain authority
+    pub fn remove_writer(_ctx: Context<RemoveWriter>) -> Result<()> {
+        Ok(())
+    }
+
+    // Set the license applied to blocks that don't name one, and the
+    // LICENSE_* bitmask of licenses writers may choose
+    pub fn set_license_policy(
+        ctx: Context<SetLicensePolicy>,
+        default_license: License,
+        allowed_licenses: u16,
+    ) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.default_license = default_license;
+        chain_state.allowed_licenses = allowed_licenses & LICENSE_ALL;
+        require!(
+            chain_state.allows_license(default_license),
+            NLPChainError::LicenseNotAllowed
+        );
+        Ok(())
+    }
+
+    // Set the lamports charged per stored byte and where they are paid.
+    // Fees are only charged while FEATURE_FEES is set.
+    pub fn set_storage_fee(
+        ctx: Context<SetStorageFee>,
+        fee_per_byte: u64,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        require!(
+            fee_per_byte == 0 || treasury != Pubkey::default(),
+            NLPChainError::InvalidChainConfig
+        );
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.fee_per_byte = fee_per_byte;
+        chain_state.treasury = treasury;
+        Ok(())
+    }
+
+    // Price reader passes for this chain. A zero price means encrypted
+    // blocks can be granted to any reader without a pass.
+    pub fn set_reader_pass_config(
+        ctx: Context<SetReaderPassConfig>,
+        mint: Pubkey,
+        price: u64,
+        epoch_seconds: i64,
+    ) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(
+            price == 0 || (epoch_seconds > 0 && chain_state.treasury != Pubkey::default()),
+            NLPChainError::InvalidChainConfig
+        );
+        chain_state.pass_mint = mint;
+        chain_state.pass_price = price;
+        chain_state.pass_epoch_seconds = epoch_seconds;
+        Ok(())
+    }
+
+    // Require writers to spend a minimal::ProofData no older than
+    // `max_age_seconds` per block. Zero turns the gate off.
+    pub fn set_pow_gate(ctx: Context<SetPowGate>, max_age_seconds: i64) -> Result<()> {
+        require!(max_age_seconds >= 0, NLPChainError::InvalidChainConfig);
+        ctx.accounts.chain_state.pow_max_age = max_age_seconds;
+        Ok(())
+    }
+
+    // Let anyone checkpoint every `interval` blocks for `bounty` lamports
+    // from the chain treasury. A zero interval leaves checkpoints to the
+    // authority.
+    pub fn set_checkpoint_crank(ctx: Context<SetCheckpointCrank>, interval: u64, bounty: u64) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.checkpoint_interval = interval;
+        chain_state.checkpoint_bounty = bounty;
+        Ok(())
+    }
+
+    // Make add_block fail once `max_unchecked_blocks` blocks have been added
+    // since the latest checkpoint, until create_checkpoint runs. Zero turns
+    // the limit off.
+    pub fn set_checkpoint_backpressure(
+        ctx: Context<SetCheckpointBackpressure>,
+        max_unchecked_blocks: u64,
+    ) -> Result<()> {
+        ctx.accounts.chain_state.max_unchecked_blocks = max_unchecked_blocks;
+        Ok(())
+    }
+
+    // Turn Wormhole replication of new blocks on or off
+    pub fn set_wormhole_replication(ctx: Context<SetWormholeReplication>, enabled: bool) -> Result<()> {
+        ctx.accounts.chain_state.wormhole_enabled = enabled;
+        Ok(())
+    }
+
+    // Configure per-writer rate limits. Zero disables the respective check.
+    pub fn set_rate_limit(
+        ctx: Context<SetRateLimit>,
+        min_slot_gap: u64,
+        rate_window_slots: u64,
+        max_blocks_per_window: u32,
+    ) -> Result<()> {
+        require!(
+            (rate_window_slots == 0) == (max_blocks_per_window == 0),
+            NLPChainError::InvalidChainConfig
+        );
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.min_slot_gap = min_slot_gap;
+        chain_state.rate_window_slots = rate_window_slots;
+        chain_state.max_blocks_per_window = max_blocks_per_window;
+        Ok(())
+    }
+
+    // Give `reader` access to an encrypted block by storing the content key
+    // wrapped to the reader's key. Only the block author can grant.
+    pub fn grant_access(
+        ctx: Context<GrantAccess>,
+        _block_index: u64,
+        reader: Pubkey,
+        wrapped_key: Vec<u8>,
+    ) -> Result<()> {
+        let block = &ctx.accounts.block;
+        require!(block.encryption_pubkey.is_some(), NLPChainError::NotEncrypted);
+        require!(
+            !wrapped_key.is_empty() && wrapped_key.len() <= AccessGrant::MAX_WRAPPED_KEY_LEN,
+            NLPChainError::InvalidWrappedKey
+        );
+        // Premium chains only release keys to readers holding a live pass
+        if ctx.accounts.chain_state.pass_price > 0 {
+            let pass = ctx
+                .accounts
+                .reader_pass
+                .as_ref()
+                .ok_or(NLPChainError::ReaderPassRequired)?;
+            require!(
+                pass.expires_at > Clock::get()?.unix_timestamp,
+                NLPChainError::ReaderPassRequired
+            );
+        }
+
+        let grant = &mut ctx.accounts.grant;
+        grant.chain = ctx.accounts.chain_state.key();
+        grant.block_index = block.index;
+        grant.reader = reader;
+        grant.wrapped_key = wrapped_key;
+        grant.granted_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Withdraw a reader's grant; rent returns to the block author
+    pub fn revoke_access(_ctx: Context<RevokeAccess>, _block_index: u64, _reader: Pubkey) -> Result<()> {
+        Ok(())
+    }
+
+    // Insert or replace one metadata entry on a block. Only the author can edit.
+    pub fn set_metadata_entry(
+        ctx: Context<SetMetadataEntry>,
+        _block_index: u64,
+        entry: MetadataEntry,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.chain_state.has_feature(FEATURE_IMMUTABLE_METADATA),
+            NLPChainError::FeatureDisabled
+        );
+        let block = &mut ctx.accounts.block;
+        match block.metadata.iter_mut().find(|existing| existing.key == entry.key) {
+            Some(existing) => existing.value = entry.value,
+            None => block.metadata.push(entry),
+        }
+        ctx.accounts.chain_state.check_content(&block.text, &block.metadata)?;
+        validate_metadata(&block.metadata)
+    }
+
+    // Attach an empty concurrent Merkle tree (allocated by the caller, owned
+    // by the account-compression program) as the chain's compressed backend
+    pub fn init_compressed_tree(
+        ctx: Context<InitCompressedTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(
+            chain_state.compressed_tree == Pubkey::default(),
+            NLPChainError::CompressedTreeMismatch
+        );
+
+        let chain_key = chain_state.key();
+        let bump = [ctx.bumps.tree_authority];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"tree-authority", chain_key.as_ref(), &bump]];
+        spl_account_compression::cpi::init_empty_merkle_tree(
+            CpiContext::new_with_signer(
+                ctx.accounts.compression_program.to_account_info(),
+                spl_account_compression::cpi::accounts::Initialize {
+                    merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+                    authority: ctx.accounts.tree_authority.to_account_info(),
+                    noop: ctx.accounts.noop_program.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            max_depth,
+            max_buffer_size,
+        )?;
+
+        chain_state.compressed_tree = ctx.accounts.merkle_tree.key();
+        Ok(())
+    }
+
+    // Append a block as a Merkle leaf instead of an account. The full block is
+    // emitted through the noop program for indexers; the leaf is the hash of
+    // its serialization. Compressed blocks are registered for dedup (flagged
+    // `compressed`, which also lets archive_range pass over them) and in the
+    // Bloom filter, but skip the author and time indexes. Writers pass the
+    // same gates, fees and quotas as add_block.
+    pub fn append_compressed_block(
+        ctx: Context<AppendCompressedBlock>,
+        text: String,
+        vector: Vec<f64>,
+        metadata: Vec<MetadataEntry>,
+        expected_index: u64,
+        expected_parent_hash: [u8; 32],
+        model: ModelRef,
+    ) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        let text = normalize_text(&text);
+        chain_state.check_embedding(&model, &vector)?;
+        chain_state.check_content(&text, &metadata)?;
+        validate_metadata(&metadata)?;
+        // A leaf can't wait out approval or earlier blocks' linking, so it is
+        // only appended straight onto a fully linked head
+        require!(
+            chain_state.required_approvals == 0 && chain_state.linked_count == chain_state.block_count,
+            NLPChainError::FeatureDisabled
+        );
+        let data_hash = block_data_hash(&text, &[]);
+        if chain_state.embedder != Pubkey::default() {
+            verify_embedder_signature(
+                ctx.accounts.instructions.as_ref(),
+                &chain_state.embedder,
+                &data_hash,
+                &vector,
+                &[0; MAX_KEYWORDS],
+            )?;
+        }
+        require!(
+            chain_state.block_count == expected_index
+                && chain_state.last_hash.to_bytes() == expected_parent_hash,
+            NLPChainError::StaleChainHead
+        );
+        chain_state.check_backpressure()?;
+
+        admit_writer(
+            chain_state,
+            &ctx.accounts.authority,
+            &ctx.accounts.authority,
+            WriterGates {
+                proof: ctx.accounts.proof.as_deref(),
+                minimal_program: ctx.accounts.minimal_program.as_ref(),
+                writer_permit: ctx.accounts.writer_permit.as_deref(),
+                writer_committee: ctx.accounts.writer_committee.as_deref(),
+                treasury: ctx.accounts.treasury.as_ref(),
+            },
+            &ctx.accounts.system_program,
+            chain_state.storage_fee(&text, &vector, &metadata)?,
+        )?;
+        let now = Clock::get()?;
+        let contributor = &mut ctx.accounts.contributor;
+        contributor.record_block(chain_state, now.slot)?;
+        contributor.record_bytes(
+            chain_state,
+            stored_bytes(&text, &vector, &metadata)? as u64,
+            now.unix_timestamp,
+        )?;
+        contributor.chain = chain_state.key();
+        contributor.author = ctx.accounts.authority.key();
+        contributor.block_count += 1;
+
+        let block = CompressedBlock {
+            chain: chain_state.key(),
+            leaf_index: chain_state.compressed_count,
+            authority: ctx.accounts.authority.key(),
+            index: chain_state.block_count,
+            timestamp: now.unix_timestamp,
+            data_hash,
+            hash_scheme: HASH_SCHEME_V1,
+            previous_hash: chain_state.last_hash,
+            model_version: model.model_version,
+            text,
+            vector,
+            metadata,
+        };
+        let payload = block.try_to_vec()?;
+        let leaf = hash(&payload).to_bytes();
+        wrap_application_data_v1(payload, &ctx.accounts.noop_program)?;
+
+        let registry = &mut ctx.accounts.hash_registry;
+        registry.chain = chain_state.key();
+        registry.data_hash = data_hash;
+        registry.block_index = chain_state.block_count;
+        registry.compressed = true;
+        insert_into_bloom(chain_state, ctx.accounts.bloom_filter.as_ref(), &data_hash)?;
+
+        let chain_key = chain_state.key();
+        let bump = [ctx.bumps.tree_authority];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"tree-authority", chain_key.as_ref(), &bump]];
+        spl_account_compression::cpi::append(
+            CpiContext::new_with_signer(
+                ctx.accounts.compression_program.to_account_info(),
+                spl_account_compression::cpi::accounts::Modify {
+                    merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+                    authority: ctx.accounts.tree_authority.to_account_info(),
+                    noop: ctx.accounts.noop_program.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            leaf,
+        )?;
+
+        chain_state.last_hash = data_hash;
+        chain_state.block_count += 1;
+        chain_state.linked_count += 1;
+        chain_state.compressed_count += 1;
+        Ok(())
+    }
+
+    // Check that `leaf` is at `leaf_index` under `root`; the proof path is
+    // passed as remaining accounts
+    pub fn verify_compressed_leaf<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VerifyCompressedLeaf<'info>>,
+        root: [u8; 32],
+        leaf: [u8; 32],
+        leaf_index: u32,
+    ) -> Result<()> {
+        spl_account_compression::cpi::verify_leaf(
+            CpiContext::new(
+                ctx.accounts.compression_program.to_account_info(),
+                spl_account_compression::cpi::accounts::VerifyLeaf {
+                    merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            root,
+            leaf,
+            leaf_index,
+        )
+    }
+
+    // Create the chain's Bloom filter; from then on every registered data
+    // hash is also inserted into it
+    pub fn init_bloom_filter(ctx: Context<InitBloomFilter>) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        // Hashes registered before the filter existed would be false negatives
+        require!(
+            chain_state.block_count <= 1,
+            NLPChainError::BloomFilterUnavailable
+        );
+        let mut bloom_filter = ctx.accounts.bloom_filter.load_init()?;
+        bloom_filter.chain = chain_state.key();
+        chain_state.bloom_enabled = true;
+        Ok(())
+    }
+
+    // View: false means `data_hash` is definitely not registered on the chain;
+    // true means it probably is and the HashRegistry PDA should be checked
+    pub fn probably_contains(ctx: Context<ProbablyContains>, data_hash: [u8; 32]) -> Result<bool> {
+        let bloom_filter = ctx.accounts.bloom_filter.load()?;
+        Ok(bloom_filter.probably_contains(&data_hash))
+    }
+
+    // View: Jaccard similarity of two blocks' texts estimated from their
+    // MinHash signatures, in basis points
+    pub fn compare_signatures(
+        ctx: Context<CompareSignatures>,
+        _index_a: u64,
+        _index_b: u64,
+    ) -> Result<u16> {
+        let (a, b) = (&ctx.accounts.block_a.minhash, &ctx.accounts.block_b.minhash);
+        require!(
+            a.len() == MINHASH_SIZE && b.len() == MINHASH_SIZE,
+            NLPChainError::InvalidMinHash
+        );
+        let matches = a.iter().zip(b).filter(|(x, y)| x == y).count();
+        Ok((matches * 10_000 / MINHASH_SIZE) as u16)
+    }
+
+    // Create centroid `centroid_id` (ids are dense: the next id is
+    // `centroid_count`) or replace an existing centroid's vector
+    pub fn upsert_centroid(
+        ctx: Context<UpsertCentroid>,
+        centroid_id: u16,
+        vector: Vec<f64>,
+    ) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(
+            vector.len() == chain_state.vector_dim as usize,
+            NLPChainError::ModelMismatch
+        );
+        require!(
+            centroid_id <= chain_state.centroid_count,
+            NLPChainError::InvalidCentroid
+        );
+        if centroid_id == chain_state.centroid_count {
+            chain_state.centroid_count += 1;
+        }
+
+        let centroid = &mut ctx.accounts.centroid;
+        centroid.chain = chain_state.key();
+        centroid.centroid_id = centroid_id;
+        centroid.norm = fixed_point_norm(&vector);
+        centroid.vector = vector;
+        Ok(())
+    }
+
+    // Crank: assign a block to its nearest centroid by cosine similarity.
+    // Every centroid must be passed, in id order, as remaining accounts.
+    pub fn assign_cluster<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AssignCluster<'info>>,
+        _block_index: u64,
+    ) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        let block = &mut ctx.accounts.block;
+        require!(
+            ctx.remaining_accounts.len() == chain_state.centroid_count as usize
+                && chain_state.centroid_count > 0,
+            NLPChainError::InvalidCentroid
+        );
+
+        let mut centroids = Vec::with_capacity(ctx.remaining_accounts.len());
+        for (id, info) in ctx.remaining_accounts.iter().enumerate() {
+            let centroid = Account::<Centroid>::try_from(info)?;
+            require!(
+                centroid.chain == chain_state.key() && centroid.centroid_id as usize == id,
+                NLPChainError::InvalidCentroid
+            );
+            centroids.push(centroid);
+        }
+
+        let nearest = centroids
+            .iter()
+            .map(|centroid| centroid.similarity(block))
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(id, _)| id)
+            .unwrap();
+
+        if block.clustered {
+            let previous = &mut centroids[block.cluster_id as usize];
+            previous.member_count = previous.member_count.saturating_sub(1);
+        }
+        centroids[nearest].member_count += 1;
+        block.cluster_id = nearest as u16;
+        block.clustered = true;
+
+        for centroid in &centroids {
+            centroid.exit(ctx.program_id)?;
+        }
+        Ok(())
+    }
+
+    // Report a block for review. Anyone can flag a block once.
+    pub fn flag_block(ctx: Context<FlagBlock>, _block_index: u64, reason: String) -> Result<()> {
+        require!(
+            !reason.is_empty() && reason.len() <= MAX_REASON_LEN,
+            NLPChainError::InvalidReason
+        );
+        let block = &mut ctx.accounts.block;
+        let flag = &mut ctx.accounts.flag;
+        flag.chain = ctx.accounts.chain_state.key();
+        flag.block_index = block.index;
+        flag.flagger = ctx.accounts.flagger.key();
+        flag.reason = reason;
+        flag.timestamp = Clock::get()?.unix_timestamp;
+        flag.status = FlagStatus::Open;
+
+        block.flag_count += 1;
+        Ok(())
+    }
+
+    // Moderator decision on a flag: uphold (usually followed by retract_block) or dismiss
+    pub fn review_flag(
+        ctx: Context<ReviewFlag>,
+        _block_index: u64,
+        _flagger: Pubkey,
+        uphold: bool,
+    ) -> Result<()> {
+        let flag = &mut ctx.accounts.flag;
+        require!(flag.status == FlagStatus::Open, NLPChainError::FlagAlreadyReviewed);
+        flag.status = if uphold {
+            FlagStatus::Upheld
+        } else {
+            FlagStatus::Dismissed
+        };
+        flag.reviewer = ctx.accounts.moderator.key();
+        Ok(())
+    }
+
+    // Tombstone a block's content. The data hash and linkage are preserved so
+    // the chain still verifies; an audit record captures who and why.
+    pub fn retract_block(ctx: Context<RetractBlock>, _block_index: u64, reason: String) -> Result<()> {
+        require!(
+            !reason.is_empty() && reason.len() <= MAX_REASON_LEN,
+            NLPChainError::InvalidReason
+        );
+        let block = &mut ctx.accounts.block;
+        require!(!block.retracted, NLPChainError::AlreadyRetracted);
+
+        block.text = String::new();
+        block.vector = Vec::new();
+        block.vector_norm = 0;
+        block.metadata = Vec::new();
+        block.retracted = true;
+
+        let record = &mut ctx.accounts.retraction;
+        record.chain = ctx.accounts.chain_state.key();
+        record.block_index = block.index;
+        record.data_hash = block.data_hash;
+        record.moderator = ctx.accounts.moderator.key();
+        record.reason = reason;
+        record.timestamp = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Tip a block's author. Pays in SPL tokens when both token accounts are
+    // supplied, otherwise in lamports straight to the author's wallet.
+    pub fn tip_block(ctx: Context<TipBlock>, _block_index: u64, amount: u64) -> Result<()> {
+        require!(amount > 0, NLPChainError::InvalidTip);
+
+        match (&ctx.accounts.reader_token, &ctx.accounts.author_token, &ctx.accounts.token_program) {
+            (Some(reader_token), Some(author_token), Some(token_program)) => {
+                token::transfer(
+                    CpiContext::new(
+                        token_program.to_account_info(),
+                        token::Transfer {
+                            from: reader_token.to_account_info(),
+                            to: author_token.to_account_info(),
+                            authority: ctx.accounts.reader.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+            }
+            (None, None, _) => {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.reader.to_account_info(),
+                            to: ctx.accounts.author.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+            }
+            _ => return err!(NLPChainError::InvalidTip),
+        }
+
+        let block = &mut ctx.accounts.block;
+        block.tips_received += 1;
+        Ok(())
+    }
+
+    // Seal the current chain head. Archives can only cover checkpointed blocks.
+    // The authority may checkpoint at any time; once `checkpoint_interval`
+    // blocks have accumulated anyone may, and is paid the bounty.
+    pub fn create_checkpoint(ctx: Context<CreateCheckpoint>) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        let due = chain_state.checkpoint_interval > 0
+            && chain_state.block_count >= chain_state.checkpointed_count + chain_state.checkpoint_interval;
+        require!(
+            due || ctx.accounts.cranker.key() == chain_state.authority,
+            NLPChainError::CheckpointNotDue
+        );
+
+        let checkpoint = &mut ctx.accounts.checkpoint;
+        let clock = Clock::get()?;
+        checkpoint.chain = chain_state.key();
+        checkpoint.index = chain_state.checkpoint_count;
+        checkpoint.block_count = chain_state.linked_count;
+        checkpoint.last_hash = chain_state.last_hash;
+        checkpoint.slot = clock.slot;
+        checkpoint.timestamp = clock.unix_timestamp;
+
+        chain_state.checkpoint_count += 1;
+        chain_state.checkpointed_count = chain_state.linked_count;
+
+        if due && chain_state.checkpoint_bounty > 0 {
+            let treasury = ctx
+                .accounts
+                .treasury
+                .as_ref()
+                .ok_or(NLPChainError::TreasuryRequired)?
+                .to_account_info();
+            // Pay what the treasury can spare above its rent reserve
+            let reserve = Rent::get()?.minimum_balance(treasury.data_len());
+            let bounty = chain_state
+                .checkpoint_bounty
+                .min(treasury.lamports().saturating_sub(reserve));
+            **treasury.try_borrow_mut_lamports()? -= bounty;
+            **ctx.accounts.cranker.try_borrow_mut_lamports()? += bounty;
+        }
+        Ok(())
+    }
+
+    // Fold blocks [start_index, end_index) into a compact Archive. The blocks
+    // are passed as remaining_accounts in index order and must extend the
+    // previously archived range and lie within `checkpoint_index`. A
+    // compressed block is passed as its HashRegistry; it was appended at the
+    // head, so it links to its predecessor by construction.
+    pub fn archive_range<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ArchiveRange<'info>>,
+        start_index: u64,
+        end_index: u64,
+        _checkpoint_index: u64,
+    ) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        let checkpoint = &ctx.accounts.checkpoint;
+        require!(
+            start_index == chain_state.archived_count
+                && end_index > start_index
+                && end_index <= checkpoint.block_count
+                && ctx.remaining_accounts.len() as u64 == end_index - start_index,
+            NLPChainError::InvalidArchiveRange
+        );
+
+        let chain_key = chain_state.key();
+        let mut previous_hash = chain_state.archived_hash;
+        let mut leaves = Vec::with_capacity(ctx.remaining_accounts.len());
+        for (index, info) in (start_index..end_index).zip(ctx.remaining_accounts) {
+            let (expected, _) = find_block_address(&chain_key, index);
+            // A rejected block's index is empty; the chain links across it
+            if info.key() == expected && info.data_is_empty() {
+                continue;
+            }
+            let data_hash = if info.key() == expected {
+                let block = Account::<Block>::try_from(info)?;
+                require!(
+                    block.index == index && block.previous_hash == previous_hash,
+                    NLPChainError::InvalidArchiveRange
+                );
+                block.data_hash
+            } else {
+                let registry = Account::<HashRegistry>::try_from(info)?;
+                require!(
+                    registry.compressed && registry.chain == chain_key && registry.block_index == index,
+                    NLPChainError::InvalidArchiveRange
+                );
+                registry.data_hash
+            };
+            previous_hash = data_hash;
+            leaves.push(data_hash);
+        }
+        // A range that ends at the checkpoint must end at its sealed head
+        if end_index == checkpoint.block_count {
+            require!(previous_hash == checkpoint.last_hash, NLPChainError::InvalidArchiveRange);
+        }
+
+        let archive = &mut ctx.accounts.archive;
+        archive.chain = chain_key;
+        archive.start_index = start_index;
+        archive.end_index = end_index;
+        archive.count = end_index - start_index;
+        archive.merkle_root = merkle_root(&leaves);
+        archive.first_previous_hash = chain_state.archived_hash;
+        archive.last_hash = previous_hash;
+        archive.checkpoint_index = checkpoint.index;
+
+        chain_state.archived_count = end_index;
+        chain_state.archived_hash = previous_hash;
+        Ok(())
+    }
+
+    // Refund the rent of a block already summarized by an Archive to whoever
+    // paid it
+    pub fn close_block(ctx: Context<CloseBlock>, _block_index: u64) -> Result<()> {
+        if ctx.accounts.chain_state.stats_enabled {
+            let block = &ctx.accounts.block;
+            let stats = ctx
+                .accounts
+                .chain_stats
+                .as_ref()
+                .ok_or(NLPChainError::ChainStatsUnavailable)?;
+            stats.load_mut()?.remove_block(block.stats_bytes, block.vector_norm);
+        }
+        Ok(())
+    }
+
+    // Start keeping ChainStats. Like the Bloom filter, only possible before
+    // the first non-genesis block so the counters are exact.
+    pub fn init_chain_stats(ctx: Context<InitChainStats>) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(
+            chain_state.block_count <= 1,
+            NLPChainError::ChainStatsUnavailable
+        );
+        let mut stats = ctx.accounts.chain_stats.load_init()?;
+        stats.chain = chain_state.key();
+        chain_state.stats_enabled = true;
+        Ok(())
+    }
+
+    // Buy or extend a reader pass for `epochs` pass epochs, paid in the
+    // chain's pass mint to a token account owned by the chain treasury.
+    pub fn buy_reader_pass(ctx: Context<BuyReaderPass>, epochs: u32) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        require!(chain_state.pass_price > 0 && epochs > 0, NLPChainError::InvalidReaderPass);
+        let amount = chain_state
+            .pass_price
+            .checked_mul(epochs as u64)
+            .ok_or(NLPChainError::InvalidReaderPass)?;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.reader_token.to_account_info(),
+                    to: ctx.accounts.treasury_token.to_account_info(),
+                    authority: ctx.accounts.reader.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        // Extend from now if the pass has lapsed, otherwise from its expiry
+        let now = Clock::get()?.unix_timestamp;
+        let pass = &mut ctx.accounts.reader_pass;
+        pass.chain = chain_state.key();
+        pass.reader = ctx.accounts.reader.key();
+        pass.expires_at = pass.expires_at.max(now) + chain_state.pass_epoch_seconds * epochs as i64;
+        Ok(())
+    }
+
+    // Upgrade a block written under an older layout to BLOCK_VERSION in
+    // place. Permissionless; the payer covers any extra rent.
+    pub fn migrate_block(ctx: Context<MigrateBlock>, _block_index: u64) -> Result<()> {
+        let info = ctx.accounts.block.to_account_info();
+        let version = *info
+            .try_borrow_data()?
+            .get(BLOCK_VERSION_OFFSET)
+            .ok_or(NLPChainError::InvalidAccountVersion)?;
+        migrate_layout(
+            &info,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            &Block::DISCRIMINATOR,
+            BLOCK_VERSION_OFFSET,
+            BLOCK_VERSION,
+            0,
+            BLOCK_LAYOUT_CHANGES,
+        )?;
+        if version < 14 {
+            // Older blocks were counted at the size they have now
+            let block = Block::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            let bytes = stored_bytes(&block.text, &block.vector, &block.metadata)? as u64;
+            info.try_borrow_mut_data()?[BLOCK_STATS_BYTES_OFFSET..BLOCK_STATS_BYTES_OFFSET + 8]
+                .copy_from_slice(&bytes.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    // Upgrade a ChainState written under an older layout to CHAIN_STATE_VERSION
+    pub fn migrate_chain_state(ctx: Context<MigrateChainState>) -> Result<()> {
+        let chain_state = ctx.accounts.chain_state.to_account_info();
+        let version = {
+            // The old layout can't be deserialized, so check the authority in place
+            let data = chain_state.try_borrow_data()?;
+            let authority = data
+                .get(CHAIN_STATE_AUTHORITY_OFFSET..CHAIN_STATE_AUTHORITY_OFFSET + 32)
+                .ok_or(NLPChainError::InvalidAccountVersion)?;
+            require!(
+                authority == ctx.accounts.authority.key().as_ref(),
+                NLPChainError::UnauthorizedUpdate
+            );
+            data[CHAIN_STATE_VERSION_OFFSET]
+        };
+        migrate_layout(
+            &chain_state,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &ChainState::DISCRIMINATOR,
+            CHAIN_STATE_VERSION_OFFSET,
+            CHAIN_STATE_VERSION,
+            ChainState::LEN,
+            CHAIN_STATE_LAYOUT_CHANGES,
+        )?;
+
+        // New fields come out zeroed; give them the behavior the chain had
+        // before they existed
+        let mut state = ChainState::try_deserialize(&mut &chain_state.try_borrow_data()?[..])?;
+        if version < 2 && state.fee_per_byte > 0 {
+            // Fees were always charged before FEATURE_FEES gated them
+            state.features |= FEATURE_FEES;
+        }
+        if version < 3 {
+            // Chains took blocks under any license before the allowlist
+            state.allowed_licenses = LICENSE_ALL;
+        }
+        if version < 4 {
+            let defaults = ChainConfig::default();
+            state.max_text_len = defaults.max_text_len;
+            state.max_metadata_entries = defaults.max_metadata_entries;
+        }
+        if version < 18 {
+            // Blocks were linked as they were added or approved
+            state.linked_count = state.block_count;
+        }
+        let mut data = chain_state.try_borrow_mut_data()?;
+        state.try_serialize(&mut &mut data[..])?;
+        Ok(())
+    }
+
+    // Mark block `old_index` as corrected by the later block `new_index`.
+    // Allowed for the chain authority, or the author of both blocks.
+    pub fn supersede_block(ctx: Context<SupersedeBlock>, old_index: u64, new_index: u64) -> Result<()> {
+        require!(old_index > 0 && new_index > old_index, NLPChainError::InvalidSupersede);
+        let signer = ctx.accounts.signer.key();
+        let old_block = &mut ctx.accounts.old_block;
+        let new_block = &mut ctx.accounts.new_block;
+        require!(
+            signer == ctx.accounts.chain_state.authority
+                || (signer == old_block.authority && signer == new_block.authority),
+            NLPChainError::UnauthorizedUpdate
+        );
+        require!(
+            old_block.superseded_by == 0 && new_block.supersedes == 0,
+            NLPChainError::InvalidSupersede
+        );
+
+        old_block.superseded_by = new_index;
+        new_block.supersedes = old_index;
+        Ok(())
+    }
+
+    // Register the guardians that can recover the chain authority. Open
+    // recovery proposals were backed by the old set and lapse.
+    pub fn set_guardians(ctx: Context<SetGuardians>, guardians: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        require!(
+            guardians.len() <= GuardianSet::MAX_GUARDIANS
+                && threshold > 0
+                && threshold as usize <= guardians.len(),
+            NLPChainError::InvalidGuardianSet
+        );
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        guardian_set.chain = ctx.accounts.chain_state.key();
+        guardian_set.guardians = guardians;
+        guardian_set.threshold = threshold;
+        ctx.accounts.chain_state.recovery_nonce += 1;
+        Ok(())
+    }
+
+    // A guardian proposes replacing a lost authority key with `new_authority`.
+    // Proposals are keyed by the new authority, so guardians backing
+    // different keys don't block each other. A proposal that lapsed with a
+    // recovery or guardian change can be proposed afresh.
+    pub fn propose_recovery(ctx: Context<ProposeRecovery>, new_authority: Pubkey) -> Result<()> {
+        let guardian = ctx.accounts.guardian.key();
+        let guardian_set = &ctx.accounts.guardian_set;
+        let chain_state = &ctx.accounts.chain_state;
+        require!(
+            guardian_set.guardians.contains(&guardian),
+            NLPChainError::NotAGuardian
+        );
+
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.approvals.is_empty() || proposal.recovery_nonce != chain_state.recovery_nonce,
+            NLPChainError::InvalidRecovery
+        );
+        proposal.chain = chain_state.key();
+        proposal.new_authority = new_authority;
+        proposal.recovery_nonce = chain_state.recovery_nonce;
+        proposal.approvals = vec![guardian];
+        proposal.executable_at = 0;
+        proposal.start_timelock_if_approved(guardian_set.threshold)?;
+        Ok(())
+    }
+
+    // Another guardian backs the open proposal. The timelock starts once the
+    // threshold is met.
+    pub fn approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
+        let guardian = ctx.accounts.guardian.key();
+        let guardian_set = &ctx.accounts.guardian_set;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            guardian_set.guardians.contains(&guardian),
+            NLPChainError::NotAGuardian
+        );
+        require!(
+            proposal.recovery_nonce == ctx.accounts.chain_state.recovery_nonce
+                && !proposal.approvals.contains(&guardian),
+            NLPChainError::InvalidRecovery
+        );
+        proposal.approvals.push(guardian);
+        proposal.start_timelock_if_approved(guardian_set.threshold)?;
+        Ok(())
+    }
+
+    // Hand the chain to the proposed authority once the timelock has passed
+    // and the current guardians still meet the threshold. Callable by
+    // anyone. Every other open proposal lapses.
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let guardian_set = &ctx.accounts.guardian_set;
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(
+            proposal.recovery_nonce == chain_state.recovery_nonce,
+            NLPChainError::InvalidRecovery
+        );
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|guardian| guardian_set.guardians.contains(guardian))
+            .count();
+        require!(
+            approvals >= guardian_set.threshold as usize
+                && proposal.executable_at > 0
+                && Clock::get()?.unix_timestamp >= proposal.executable_at,
+            NLPChainError::RecoveryTimelocked
+        );
+        chain_state.authority = proposal.new_authority;
+        chain_state.recovery_nonce += 1;
+        Ok(())
+    }
+
+    // The current authority still holds its key and vetoes the recovery
+    pub fn cancel_recovery(_ctx: Context<CancelRecovery>) -> Result<()> {
+        Ok(())
+    }
+
+    // Create the chain treasury PDA and route storage fees and pass sales to it
+    pub fn init_treasury(ctx: Context<InitTreasury>) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.chain = ctx.accounts.chain_state.key();
+        treasury.shares = Vec::new();
+        treasury.pending_shares = Vec::new();
+        treasury.pending_effective_at = 0;
+        ctx.accounts.chain_state.treasury = treasury.key();
+        Ok(())
+    }
+
+    // Schedule a new payout split. It takes effect after TREASURY_TIMELOCK so
+    // recipients can react to changes before the next withdrawal.
+    pub fn propose_treasury_shares(
+        ctx: Context<ProposeTreasuryShares>,
+        shares: Vec<TreasuryShare>,
+    ) -> Result<()> {
+        require!(
+            !shares.is_empty()
+                && shares.len() <= Treasury::MAX_SHARES
+                && shares.iter().map(|share| share.bps as u32).sum::<u32>() == BPS_DENOMINATOR as u32,
+            NLPChainError::InvalidTreasuryShares
+        );
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.pending_shares = shares;
+        treasury.pending_effective_at = Clock::get()?.unix_timestamp + TREASURY_TIMELOCK;
+        Ok(())
+    }
+
+    // Pay out the treasury's spare lamports by share. Recipients are passed
+    // as remaining_accounts in share order. Callable by anyone.
+    pub fn withdraw<'info>(ctx: Context<'_, '_, 'info, 'info, Withdraw<'info>>) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.activate_pending_shares()?;
+        require!(
+            ctx.remaining_accounts.len() == treasury.shares.len() && !treasury.shares.is_empty(),
+            NLPChainError::InvalidTreasuryShares
+        );
+
+        let treasury_info = treasury.to_account_info();
+        let reserve = Rent::get()?.minimum_balance(treasury_info.data_len());
+        let available = treasury_info.lamports().saturating_sub(reserve);
+        for (share, recipient) in treasury.shares.iter().zip(ctx.remaining_accounts) {
+            require_keys_eq!(recipient.key(), share.recipient, NLPChainError::InvalidTreasuryShares);
+            let amount = share.portion(available);
+            **treasury_info.try_borrow_mut_lamports()? -= amount;
+            **recipient.try_borrow_mut_lamports()? += amount;
+        }
+        Ok(())
+    }
+
+    // Pay out a treasury-owned token account by share. Recipient token
+    // accounts are passed as remaining_accounts in share order.
+    pub fn withdraw_tokens<'info>(ctx: Context<'_, '_, 'info, 'info, WithdrawTokens<'info>>) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.activate_pending_shares()?;
+        require!(
+            ctx.remaining_accounts.len() == treasury.shares.len() && !treasury.shares.is_empty(),
+            NLPChainError::InvalidTreasuryShares
+        );
+
+        let chain_key = ctx.accounts.chain_state.key();
+        let seeds: &[&[u8]] = &[b"treasury", chain_key.as_ref(), &[ctx.bumps.treasury]];
+        let available = ctx.accounts.treasury_token.amount;
+        for (share, info) in treasury.shares.iter().zip(ctx.remaining_accounts) {
+            let recipient = Account::<TokenAccount>::try_from(info)?;
+            require!(
+                recipient.owner == share.recipient
+                    && recipient.mint == ctx.accounts.treasury_token.mint,
+                NLPChainError::InvalidTreasuryShares
+            );
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.treasury_token.to_account_info(),
+                        to: info.clone(),
+                        authority: treasury.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                share.portion(available),
+            )?;
+        }
+        Ok(())
+    }
+
+    // View: Hamming distance between `query` and each block passed in
+    // remaining_accounts, in order. Reads the binary vector straight from
+    // account data, so dozens of blocks fit in one transaction.
+    pub fn hamming_compare<'info>(
+        ctx: Context<'_, '_, 'info, 'info, HammingCompare>,
+        query: [u64; BINARY_VECTOR_WORDS],
+    ) -> Result<Vec<u16>> {
+        ctx.remaining_accounts
+            .iter()
+            .map(|info| {
+                let words = read_block_words(info, BLOCK_BINARY_VECTOR_OFFSET, BINARY_VECTOR_WORDS)?;
+                let distance = words
+                    .iter()
+                    .zip(&query)
+                    .map(|(word, q)| (word ^ q).count_ones())
+                    .sum::<u32>();
+                Ok(distance as u16)
+            })
+            .collect()
+    }
+
+    // View: whether each block passed in remaining_accounts carries the
+    // keyword with hash `keyword_hash` (see keyword_hash)
+    pub fn match_keyword<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MatchKeyword>,
+        keyword_hash: u64,
+    ) -> Result<Vec<bool>> {
+        require!(keyword_hash != 0, NLPChainError::InvalidKeyword);
+        ctx.remaining_accounts
+            .iter()
+            .map(|info| {
+                let words = read_block_words(info, BLOCK_KEYWORD_HASHES_OFFSET, MAX_KEYWORDS)?;
+                Ok(words.contains(&keyword_hash))
+            })
+            .collect()
+    }
+
+    // Register `oracle` as an attester for this chain
+    pub fn add_oracle(ctx: Context<AddOracle>, oracle: Pubkey) -> Result<()> {
+        let record = &mut ctx.accounts.oracle_record;
+        record.chain = ctx.accounts.chain_state.key();
+        record.oracle = oracle;
+        Ok(())
+    }
+
+    // Deregister an oracle; its past attestations still count
+    pub fn remove_oracle(_ctx: Context<RemoveOracle>) -> Result<()> {
+        Ok(())
+    }
+
+    // Number of oracle attestations a block needs before it counts as finalized
+    pub fn set_required_attestations(
+        ctx: Context<SetRequiredAttestations>,
+        required_attestations: u8,
+    ) -> Result<()> {
+        ctx.accounts.chain_state.required_attestations = required_attestations;
+        Ok(())
+    }
+
+    // A registered oracle vouches for a block, e.g. "toxicity-checked".
+    // Each oracle attests a block at most once; the attestation pins the
+    // data_hash it saw.
+    pub fn attest_block(ctx: Context<AttestBlock>, _block_index: u64, claim: String) -> Result<()> {
+        require!(
+            !claim.is_empty() && claim.len() <= MAX_CLAIM_LEN,
+            NLPChainError::InvalidClaim
+        );
+        let block = &mut ctx.accounts.block;
+        require!(!block.retracted, NLPChainError::AlreadyRetracted);
+
+        let attestation = &mut ctx.accounts.attestation;
+        attestation.chain = ctx.accounts.chain_state.key();
+        attestation.block_index = block.index;
+        attestation.data_hash = block.data_hash;
+        attestation.oracle = ctx.accounts.oracle.key();
+        attestation.claim = claim;
+        attestation.timestamp = Clock::get()?.unix_timestamp;
+
+        block.attestation_count += 1;
+        Ok(())
+    }
+
+    // Point block NFTs at a Bubblegum tree. The tree's delegate must be the
+    // chain's nft-authority PDA.
+    pub fn set_block_nft_tree(ctx: Context<SetBlockNftTree>, tree: Pubkey) -> Result<()> {
+        ctx.accounts.chain_state.nft_tree = tree;
+        Ok(())
+    }
+
+    // Mint a compressed NFT for a block, owned by its author. The metadata
+    // URI names the chain, index and data_hash. Callable by anyone, once
+    // per block.
+    pub fn mint_block_nft(ctx: Context<MintBlockNft>, _block_index: u64) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        require!(
+            chain_state.has_feature(FEATURE_BLOCK_NFTS),
+            NLPChainError::FeatureDisabled
+        );
+        let block = &ctx.accounts.block;
+        let chain_key = chain_state.key();
+
+        let leaf_index = ctx
+            .accounts
+            .tree_config
+            .try_borrow_data()?
+            .get(BUBBLEGUM_NUM_MINTED_OFFSET..BUBBLEGUM_NUM_MINTED_OFFSET + 8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or(NLPChainError::CompressedTreeMismatch)?;
+
+        let metadata = BubblegumMetadata {
+            name: format!("span #{}", block.index),
+            symbol: BLOCK_NFT_SYMBOL.to_string(),
+            uri: format!("span://{}/{}/{}", chain_key, block.index, block.data_hash),
+            seller_fee_basis_points: 0,
+            primary_sale_happened: false,
+            is_mutable: false,
+            edition_nonce: None,
+            token_standard: Some(0),
+            collection: None,
+            uses: None,
+            token_program_version: 0,
+            creators: vec![(block.authority, false, 100)],
+        };
+        let mut data = BUBBLEGUM_MINT_V1.to_vec();
+        metadata.serialize(&mut data)?;
+
+        let accounts = &ctx.accounts;
+        let instruction = Instruction {
+            program_id: BUBBLEGUM_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(accounts.tree_config.key(), false),
+                AccountMeta::new_readonly(accounts.leaf_owner.key(), false),
+                AccountMeta::new_readonly(accounts.leaf_owner.key(), false),
+                AccountMeta::new(accounts.merkle_tree.key(), false),
+                AccountMeta::new(accounts.payer.key(), true),
+                AccountMeta::new_readonly(accounts.nft_authority.key(), true),
+                AccountMeta::new_readonly(accounts.noop_program.key(), false),
+                AccountMeta::new_readonly(accounts.compression_program.key(), false),
+                AccountMeta::new_readonly(accounts.system_program.key(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &instruction,
+            &[
+                accounts.tree_config.to_account_info(),
+                accounts.leaf_owner.to_account_info(),
+                accounts.merkle_tree.to_account_info(),
+                accounts.payer.to_account_info(),
+                accounts.nft_authority.to_account_info(),
+                accounts.noop_program.to_account_info(),
+                accounts.compression_program.to_account_info(),
+                accounts.system_program.to_account_info(),
+                accounts.bubblegum_program.to_account_info(),
+            ],
+            &[&[b"nft-authority", chain_key.as_ref(), &[ctx.bumps.nft_authority]]],
+        )?;
+
+        let receipt = &mut ctx.accounts.block_nft;
+        receipt.chain = chain_key;
+        receipt.block_index = block.index;
+        receipt.tree = ctx.accounts.merkle_tree.key();
+        receipt.leaf_index = leaf_index;
+        Ok(())
+    }
+
+    // Let blocks older than `ttl_seconds` be expired. Zero keeps blocks forever.
+    pub fn set_block_ttl(ctx: Context<SetBlockTtl>, ttl_seconds: i64) -> Result<()> {
+        require!(ttl_seconds >= 0, NLPChainError::InvalidChainConfig);
+        ctx.accounts.chain_state.block_ttl = ttl_seconds;
+        Ok(())
+    }
+
+    // Change the per-block content limits. Existing blocks keep their size;
+    // new blocks are allocated for the new limits.
+    pub fn set_limits(ctx: Context<SetLimits>, max_text_len: u16, max_metadata_entries: u8) -> Result<()> {
+        require!(
+            max_text_len as usize <= MAX_TEXT_LEN
+                && max_metadata_entries as usize <= MAX_METADATA_ENTRIES,
+            NLPChainError::InvalidChainConfig
+        );
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.max_text_len = max_text_len;
+        chain_state.max_metadata_entries = max_metadata_entries;
+        Ok(())
+    }
+
+    // Crank: close a block past the chain's TTL and refund its rent to
+    // whoever paid it. The block must be covered by a checkpoint, whose
+    // last_hash still commits to its content. Callable by anyone.
+    pub fn expire_block(ctx: Context<ExpireBlock>, _block_index: u64, _checkpoint_index: u64) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        let block = &ctx.accounts.block;
+        require!(
+            chain_state.block_ttl > 0
+                && block.index > 0
+                && block.timestamp.saturating_add(chain_state.block_ttl) <= Clock::get()?.unix_timestamp,
+            NLPChainError::BlockNotExpired
+        );
+        // Free the content for reuse; a retracted block's text no longer
+        // derives its entry
+        require!(
+            block.retracted || ctx.accounts.hash_registry.is_some(),
+            NLPChainError::HashRegistryRequired
+        );
+        if chain_state.stats_enabled {
+            let stats = ctx
+                .accounts
+                .chain_stats
+                .as_ref()
+                .ok_or(NLPChainError::ChainStatsUnavailable)?;
+            stats.load_mut()?.remove_block(block.stats_bytes, block.vector_norm);
+        }
+        Ok(())
+    }
+
+    // Record block `block_index` as a reply to the earlier block
+    // `parent_index` and append it to the parent's ThreadIndex. Allowed for
+    // the reply's author, once per block.
+    pub fn reply_to_block(ctx: Context<ReplyToBlock>, block_index: u64, parent_index: u64) -> Result<()> {
+        require!(
+            parent_index > 0 && block_index > parent_index,
+            NLPChainError::InvalidReply
+        );
+        let block = &mut ctx.accounts.block;
+        let parent = &mut ctx.accounts.parent_block;
+        require!(block.reply_to == 0, NLPChainError::InvalidReply);
+
+        let thread_index = &mut ctx.accounts.thread_index;
+        if thread_index.reply_indexes.is_empty() {
+            thread_index.chain = ctx.accounts.chain_state.key();
+            thread_index.parent_index = parent_index;
+            thread_index.page = parent.reply_page();
+        }
+        thread_index.reply_indexes.push(block_index);
+
+        block.reply_to = parent_index;
+        block.thread_root = if parent.reply_to == 0 {
+            parent_index
+        } else {
+            parent.thread_root
+        };
+        parent.reply_count += 1;
+        Ok(())
+    }
+
+    // Summarize blocks [start_index, end_index) with new text and an
+    // embedding. The blocks are passed as remaining_accounts in index order;
+    // the summary commits to them through range_hash, so it can be checked
+    // against the underlying blocks, archives or other summaries.
+    pub fn add_summary<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AddSummary<'info>>,
+        start_index: u64,
+        end_index: u64,
+        text: String,
+        vector: Vec<f64>,
+    ) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        require!(
+            end_index > start_index
+                && end_index <= chain_state.block_count
+                && ctx.remaining_accounts.len() as u64 == end_index - start_index,
+            NLPChainError::InvalidArchiveRange
+        );
+        let text = normalize_text(&text);
+        let model = ModelRef {
+            model_id: chain_state.model_id.clone(),
+            model_version: chain_state.model_version,
+        };
+        chain_state.check_embedding(&model, &vector)?;
+        chain_state.check_content(&text, &[])?;
+
+        // Read only the two hashes of each block; closed blocks are skipped
+        // and break the link check
+        let chain_key = chain_state.key();
+        let mut first_previous_hash = None;
+        let mut previous_hash = None;
+        let mut last_hash = Hash::default();
+        let mut data_hashes = Vec::with_capacity(ctx.remaining_accounts.len());
+        for (index, info) in (start_index..end_index).zip(ctx.remaining_accounts) {
+            let (expected, _) = find_block_address(&chain_key, index);
+            require_keys_eq!(info.key(), expected, NLPChainError::InvalidArchiveRange);
+            if info.data_is_empty() {
+                previous_hash = None;
+                continue;
+            }
+            require_keys_eq!(*info.owner, crate::ID, NLPChainError::InvalidAccountVersion);
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() >= BLOCK_PREVIOUS_HASH_OFFSET + 32 && data[..8] == Block::DISCRIMINATOR,
+                NLPChainError::InvalidAccountVersion
+            );
+            let data_hash = Hash::new_from_array(
+                data[BLOCK_DATA_HASH_OFFSET..BLOCK_DATA_HASH_OFFSET + 32].try_into().unwrap(),
+            );
+            let block_previous_hash = Hash::new_from_array(
+                data[BLOCK_PREVIOUS_HASH_OFFSET..BLOCK_PREVIOUS_HASH_OFFSET + 32].try_into().unwrap(),
+            );
+            if let Some(hash) = previous_hash {
+                require!(block_previous_hash == hash, NLPChainError::InvalidArchiveRange);
+            }
+            first_previous_hash.get_or_insert(block_previous_hash);
+            previous_hash = Some(data_hash);
+            last_hash = data_hash;
+            data_hashes.push(data_hash);
+        }
+
+        let summary = &mut ctx.accounts.summary;
+        summary.chain = chain_key;
+        summary.start_index = start_index;
+        summary.end_index = end_index;
+        summary.range_hash = range_hash(&data_hashes);
+        summary.first_previous_hash = first_previous_hash.unwrap_or_default();
+        summary.last_hash = last_hash;
+        summary.authority = ctx.accounts.authority.key();
+        summary.timestamp = Clock::get()?.unix_timestamp;
+        summary.model_version = chain_state.model_version;
+        summary.vector_norm = fixed_point_norm(&vector);
+        summary.text = text;
+        summary.vector = vector;
+        Ok(())
+    }
+
+    // Allow up to `max_sub_vectors` per-sentence embeddings per block. Zero
+    // turns sub-vectors off. A block's SubVectors account must fit in one
+    // account creation.
+    pub fn set_max_sub_vectors(ctx: Context<SetMaxSubVectors>, max_sub_vectors: u8) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(
+            SubVectors::space(max_sub_vectors as usize, chain_state.vector_dim as usize)
+                <= MAX_PERMITTED_DATA_INCREASE,
+            NLPChainError::InvalidChainConfig
+        );
+        chain_state.max_sub_vectors = max_sub_vectors;
+        Ok(())
+    }
+
+    // Attach per-sentence embeddings to a block. `sentence_offsets` are the
+    // byte offsets in the block text where each sentence starts. Allowed for
+    // the block's author, once; signed by the embedder over
+    // sub_vectors_message.
+    pub fn set_sub_vectors(
+        ctx: Context<SetSubVectors>,
+        _block_index: u64,
+        sentence_offsets: Vec<u32>,
+        vectors: Vec<Vec<f64>>,
+    ) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        let block = &ctx.accounts.block;
+        let dim = chain_state.vector_dim as usize;
+        require!(
+            !vectors.is_empty()
+                && vectors.len() <= chain_state.max_sub_vectors as usize
+                && sentence_offsets.len() == vectors.len()
+                && vectors.iter().all(|v| v.len() == dim)
+                && sentence_offsets.windows(2).all(|w| w[0] < w[1])
+                && sentence_offsets.iter().all(|o| (*o as usize) < block.text.len()),
+            NLPChainError::InvalidSubVectors
+        );
+        let flat: Vec<f64> = vectors.iter().flatten().copied().collect();
+        if chain_state.embedder != Pubkey::default() {
+            verify_ed25519_message(
+                ctx.accounts.instructions.as_ref(),
+                &chain_state.embedder,
+                &sub_vectors_message(&block.data_hash, &sentence_offsets, &flat),
+                NLPChainError::InvalidEmbedderSignature,
+            )?;
+        }
+
+        let sub_vectors = &mut ctx.accounts.sub_vectors;
+        sub_vectors.chain = chain_state.key();
+        sub_vectors.block_index = block.index;
+        sub_vectors.dim = chain_state.vector_dim;
+        sub_vectors.sentence_offsets = sentence_offsets;
+        sub_vectors.norms = vectors.iter().map(|v| fixed_point_norm(v)).collect();
+        sub_vectors.vectors = flat;
+        Ok(())
+    }
+
+    // View: cosine similarity of two blocks in basis points. `sub_a` and
+    // `sub_b` pick a sentence sub-vector (its SubVectors account must be
+    // passed); None compares the main vector.
+    pub fn compare_vectors(
+        ctx: Context<CompareVectors>,
+        _index_a: u64,
+        sub_a: Option<u8>,
+        _index_b: u64,
+        sub_b: Option<u8>,
+    ) -> Result<i16> {
+        let a = pick_vector(&ctx.accounts.block_a, ctx.accounts.sub_vectors_a.as_deref(), sub_a)?;
+        let b = pick_vector(&ctx.accounts.block_b, ctx.accounts.sub_vectors_b.as_deref(), sub_b)?;
+        let similarity = cosine_similarity(a.0, a.1, b.0, b.1).ok_or(NLPChainError::ModelMismatch)?;
+        Ok((similarity.clamp(-1.0, 1.0) * 10_000.0).round() as i16)
+    }
+
+    // Accept sparse embeddings over a vocabulary of `sparse_dim` terms. Zero
+    // turns sparse vectors off.
+    pub fn set_sparse_dim(ctx: Context<SetSparseDim>, sparse_dim: u32) -> Result<()> {
+        ctx.accounts.chain_state.sparse_dim = sparse_dim;
+        Ok(())
+    }
+
+    // Attach a sparse embedding (e.g. SPLADE) to a block as index/value pairs
+    // sorted by index. Allowed for the block's author, once; signed by the
+    // embedder over sparse_vector_message.
+    pub fn set_sparse_vector(
+        ctx: Context<SetSparseVector>,
+        _block_index: u64,
+        indices: Vec<u32>,
+        values: Vec<f64>,
+    ) -> Result<()> {
+        let chain_state = &ctx.accounts.chain_state;
+        let block = &ctx.accounts.block;
+        require!(
+            !indices.is_empty()
+                && indices.len() <= MAX_SPARSE_NNZ
+                && indices.len() == values.len()
+                && indices.windows(2).all(|w| w[0] < w[1])
+                && indices.last().is_some_and(|i| *i < chain_state.sparse_dim)
+                && values.iter().all(|v| v.is_finite()),
+            NLPChainError::InvalidSparseVector
+        );
+        if chain_state.embedder != Pubkey::default() {
+            verify_ed25519_message(
+                ctx.accounts.instructions.as_ref(),
+                &chain_state.embedder,
+                &sparse_vector_message(&block.data_hash, &indices, &values),
+                NLPChainError::InvalidEmbedderSignature,
+            )?;
+        }
+
+        let sparse = &mut ctx.accounts.sparse_vector;
+        sparse.chain = chain_state.key();
+        sparse.block_index = block.index;
+        sparse.indices = indices;
+        sparse.values = values;
+        Ok(())
+    }
+
+    // View: dot product of two blocks' sparse vectors
+    pub fn compare_sparse(ctx: Context<CompareSparse>, _index_a: u64, _index_b: u64) -> Result<f64> {
+        Ok(ctx.accounts.sparse_a.dot(&ctx.accounts.sparse_b))
+    }
+
+    // Create a namespace: a named collection of blocks within the chain that
+    // is enumerable on its own. Ids start at 1; 0 means no namespace.
+    pub fn create_namespace(ctx: Context<CreateNamespace>, name: String) -> Result<()> {
+        require!(
+            !name.is_empty() && name.len() <= MAX_NAMESPACE_NAME_LEN,
+            NLPChainError::InvalidNamespace
+        );
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.namespace_count += 1;
+
+        let namespace = &mut ctx.accounts.namespace;
+        namespace.chain = chain_state.key();
+        namespace.id = chain_state.namespace_count;
+        namespace.name = name;
+        namespace.block_count = 0;
+        Ok(())
+    }
+
+    // File a block under a namespace and append it to the namespace's
+    // index. Allowed for the block's author and the chain authority, once
+    // per block.
+    pub fn file_block(ctx: Context<FileBlock>, block_index: u64, _namespace_id: u16) -> Result<()> {
+        let signer = ctx.accounts.signer.key();
+        let block = &mut ctx.accounts.block;
+        require!(
+            signer == block.authority || signer == ctx.accounts.chain_state.authority,
+            NLPChainError::UnauthorizedUpdate
+        );
+        require!(block.namespace_id == 0, NLPChainError::InvalidNamespace);
+
+        let namespace = &mut ctx.accounts.namespace;
+        let namespace_index = &mut ctx.accounts.namespace_index;
+        if namespace_index.block_indexes.is_empty() {
+            namespace_index.chain = ctx.accounts.chain_state.key();
+            namespace_index.namespace_id = namespace.id;
+            namespace_index.page = namespace.page();
+        }
+        namespace_index.block_indexes.push(block_index);
+
+        block.namespace_id = namespace.id;
+        namespace.block_count += 1;
+        Ok(())
+    }
+
+    // Escrow `amount` tokens for an annotation task on a block. One bounty
+    // per (block, poster, task kind).
+    pub fn post_bounty(
+        ctx: Context<PostBounty>,
+        block_index: u64,
+        amount: u64,
+        task_kind: AnnotationTask,
+    ) -> Result<()> {
+        require!(amount > 0, NLPChainError::InvalidBounty);
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.poster_token.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.poster.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let bounty = &mut ctx.accounts.bounty;
+        bounty.chain = ctx.accounts.chain_state.key();
+        bounty.block_index = block_index;
+        bounty.poster = ctx.accounts.poster.key();
+        bounty.task_kind = task_kind;
+        bounty.mint = ctx.accounts.mint.key();
+        bounty.amount = amount;
+        bounty.status = BountyStatus::Open;
+        bounty.annotation = Pubkey::default();
+        bounty.created_at = Clock::get()?.unix_timestamp;
+        bounty.bump = ctx.bumps.bounty;
+        Ok(())
+    }
+
+    // Submit work for an open bounty. The annotation itself lives off-chain
+    // at `uri`; `content_hash` pins it.
+    pub fn submit_annotation(ctx: Context<SubmitAnnotation>, content_hash: [u8; 32], uri: String) -> Result<()> {
+        require!(uri.len() <= MAX_ANNOTATION_URI_LEN, NLPChainError::InvalidBounty);
+        require!(
+            ctx.accounts.bounty.status == BountyStatus::Open,
+            NLPChainError::BountyClosed
+        );
+        let annotation = &mut ctx.accounts.annotation;
+        annotation.bounty = ctx.accounts.bounty.key();
+        annotation.annotator = ctx.accounts.annotator.key();
+        annotation.content_hash = content_hash;
+        annotation.uri = uri;
+        annotation.submitted_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Accept an annotation for a bounty. Allowed for the block's author and
+    // moderators; the annotator can then claim the escrow.
+    pub fn approve_annotation(ctx: Context<ApproveAnnotation>) -> Result<()> {
+        require!(
+            ctx.accounts.approver.key() == ctx.accounts.block.authority
+                || ctx.accounts.moderator_record.is_some(),
+            NLPChainError::UnauthorizedUpdate
+        );
+        let bounty = &mut ctx.accounts.bounty;
+        require!(bounty.status == BountyStatus::Open, NLPChainError::BountyClosed);
+        bounty.status = BountyStatus::Approved;
+        bounty.annotation = ctx.accounts.annotation.key();
+        Ok(())
+    }
+
+    // Pay an approved annotation's annotator out of escrow and close the
+    // bounty; its rent goes back to the poster
+    pub fn claim_bounty(ctx: Context<ClaimBounty>) -> Result<()> {
+        let bounty = &ctx.accounts.bounty;
+        require!(bounty.status == BountyStatus::Approved, NLPChainError::BountyClosed);
+        let block_index = bounty.block_index.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"bounty",
+            bounty.chain.as_ref(),
+            &block_index,
+            bounty.poster.as_ref(),
+            &[bounty.task_kind as u8],
+            &[bounty.bump],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.annotator_token.to_account_info(),
+                    authority: bounty.to_account_info(),
+                },
+                &[seeds],
+            ),
+            bounty.amount,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.vault.to_account_info(),
+                destination: ctx.accounts.poster.to_account_info(),
+                authority: bounty.to_account_info(),
+            },
+            &[seeds],
+        ))?;
+        Ok(())
+    }
+
+    // Withdraw an open bounty's escrow and close it
+    pub fn cancel_bounty(ctx: Context<CancelBounty>) -> Result<()> {
+        let bounty = &ctx.accounts.bounty;
+        require!(bounty.status == BountyStatus::Open, NLPChainError::BountyClosed);
+        let block_index = bounty.block_index.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"bounty",
+            bounty.chain.as_ref(),
+            &block_index,
+            bounty.poster.as_ref(),
+            &[bounty.task_kind as u8],
+            &[bounty.bump],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.poster_token.to_account_info(),
+                    authority: bounty.to_account_info(),
+                },
+                &[seeds],
+            ),
+            bounty.amount,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.vault.to_account_info(),
+                destination: ctx.accounts.poster.to_account_info(),
+                authority: bounty.to_account_info(),
+            },
+            &[seeds],
+        ))?;
+        Ok(())
+    }
+
+    // Set the token pin_block locks. Changing it while pins are outstanding
+    // would mix units in pin_weight, so it can only be set once.
+    pub fn set_pin_mint(ctx: Context<SetPinMint>, mint: Pubkey) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(chain_state.pin_mint == Pubkey::default(), NLPChainError::PinMintAlreadySet);
+        chain_state.pin_mint = mint;
+        Ok(())
+    }
+
+    // Lock `amount` pin_mint tokens against a block for `duration` seconds,
+    // adding them to the block's pin_weight. Indexers can rank by pin_weight
+    // as a curation signal. One pin per (block, owner) at a time.
+    pub fn pin_block(ctx: Context<PinBlock>, block_index: u64, amount: u64, duration: i64) -> Result<()> {
+        require!(
+            ctx.accounts.chain_state.pin_mint != Pubkey::default(),
+            NLPChainError::PinningDisabled
+        );
+        require!(
+            amount > 0 && duration > 0 && duration <= MAX_PIN_DURATION,
+            NLPChainError::InvalidPin
+        );
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.owner_token.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let block = &mut ctx.accounts.block;
+        block.pin_weight = block.pin_weight.saturating_add(amount);
+
+        let pin = &mut ctx.accounts.pin;
+        pin.chain = ctx.accounts.chain_state.key();
+        pin.block_index = block_index;
+        pin.owner = ctx.accounts.owner.key();
+        pin.amount = amount;
+        pin.unlock_at = Clock::get()?.unix_timestamp + duration;
+        pin.bump = ctx.bumps.pin;
+        Ok(())
+    }
+
+    // Return a pin's tokens once it has expired and remove its weight from
+    // the block, if the block still exists
+    pub fn unpin(ctx: Context<Unpin>, _block_index: u64) -> Result<()> {
+        let pin = &ctx.accounts.pin;
+        require!(
+            Clock::get()?.unix_timestamp >= pin.unlock_at,
+            NLPChainError::PinLocked
+        );
+        let block_index = pin.block_index.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"pin",
+            pin.chain.as_ref(),
+            &block_index,
+            pin.owner.as_ref(),
+            &[pin.bump],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.owner_token.to_account_info(),
+                    authority: pin.to_account_info(),
+                },
+                &[seeds],
+            ),
+            pin.amount,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.vault.to_account_info(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: pin.to_account_info(),
+            },
+            &[seeds],
+        ))?;
+
+        // A closed block has no weight left to remove
+        let info = ctx.accounts.block.to_account_info();
+        if !info.data_is_empty() {
+            let mut data = info.try_borrow_mut_data()?;
+            check_block_data(&info, &data)?;
+            let range = BLOCK_PIN_WEIGHT_OFFSET..BLOCK_PIN_WEIGHT_OFFSET + 8;
+            let pin_weight = u64::from_le_bytes(data[range.clone()].try_into().unwrap());
+            data[range].copy_from_slice(&pin_weight.saturating_sub(pin.amount).to_le_bytes());
+        }
+        Ok(())
+    }
+
+    // Register an approver for curated chains
+    pub fn add_approver(ctx: Context<AddApprover>, approver: Pubkey) -> Result<()> {
+        let record = &mut ctx.accounts.approver_record;
+        record.chain = ctx.accounts.chain_state.key();
+        record.approver = approver;
+        Ok(())
+    }
+
+    // Deregister an approver; approvals they already gave still count
+    pub fn remove_approver(_ctx: Context<RemoveApprover>) -> Result<()> {
+        Ok(())
+    }
+
+    // Number of approvers that must sign off on a block before it joins the
+    // chain head. Non-zero puts add_block into pending mode. Blocks still
+    // waiting on the old quorum would hold back the head, so it can only
+    // change once every block is linked.
+    pub fn set_required_approvals(ctx: Context<SetRequiredApprovals>, required_approvals: u8) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        require!(
+            chain_state.linked_count == chain_state.block_count,
+            NLPChainError::BlockPending
+        );
+        chain_state.required_approvals = required_approvals;
+        Ok(())
+    }
+
+    // A registered approver signs off on a pending block, once. Reaching the
+    // quorum releases the block; the hash chain follows index order, so it is
+    // linked now if every earlier block is, and by link_released_blocks
+    // otherwise.
+    pub fn approve_block(ctx: Context<ApproveBlock>, _block_index: u64) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        let block = &mut ctx.accounts.block;
+        require!(block.pending, NLPChainError::BlockNotPending);
+
+        let approval = &mut ctx.accounts.approval;
+        approval.chain = chain_state.key();
+        approval.block_index = block.index;
+        approval.approver = ctx.accounts.approver.key();
+        approval.timestamp = Clock::get()?.unix_timestamp;
+
+        block.approval_count += 1;
+        if block.approval_count >= chain_state.required_approvals {
+            block.pending = false;
+            if block.index == chain_state.linked_count {
+                block.previous_hash = chain_state.last_hash;
+                chain_state.last_hash = block.data_hash;
+                chain_state.linked_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // Crank: link released blocks onto the chain head in index order. The
+    // blocks from `linked_count` on are passed as remaining accounts; a
+    // rejected block's empty index is stepped over, and the crank stops at
+    // the first block still awaiting approval. Callable by anyone.
+    pub fn link_released_blocks<'info>(ctx: Context<'_, '_, 'info, 'info, LinkReleasedBlocks<'info>>) -> Result<()> {
+        let chain_state = &mut ctx.accounts.chain_state;
+        let chain_key = chain_state.key();
+        for info in ctx.remaining_accounts {
+            let index = chain_state.linked_count;
+            require!(index < chain_state.block_count, NLPChainError::InvalidArchiveRange);
+            let (expected, _) = find_block_address(&chain_key, index);
+            require_keys_eq!(info.key(), expected, NLPChainError::InvalidArchiveRange);
+            if !info.data_is_empty() {
+                let mut data = info.try_borrow_mut_data()?;
+                check_block_data(info, &data)?;
+                if data[BLOCK_PENDING_OFFSET] != 0 {
+                    break;
+                }
+                data[BLOCK_PREVIOUS_HASH_OFFSET..BLOCK_PREVIOUS_HASH_OFFSET + 32]
+                    .copy_from_slice(chain_state.last_hash.as_ref());
+                chain_state.last_hash = Hash::new_from_array(
+                    data[BLOCK_DATA_HASH_OFFSET..BLOCK_DATA_HASH_OFFSET + 32].try_into().unwrap(),
+                );
+            }
+            chain_state.linked_count += 1;
+        }
+        Ok(())
+    }
+
+    // A registered approver turns down a pending block: the block and its
+    // dedup entry are closed to the payer so the text can be resubmitted.
+    // The index is left empty and skipped when the chain is linked.
+    pub fn reject_block(ctx: Context<RejectBlock>, _block_index: u64) -> Result<()> {
+        let block = &ctx.accounts.block;
+        require!(block.pending, NLPChainError::BlockNotPending);
+        let chain_state = &mut ctx.accounts.chain_state;
+        if block.index == chain_state.linked_count {
+            chain_state.linked_count += 1;
+        }
+        if chain_state.stats_enabled {
+            let stats = ctx
+                .accounts
+                .chain_stats
+                .as_ref()
+                .ok_or(NLPChainError::ChainStatsUnavailable)?;
+            stats.load_mut()?.remove_block(block.stats_bytes, block.vector_norm);
+        }
+        Ok(())
+    }
+
+    // Set the chain's display name, description and content URI for
+    // discovery tooling. Creates the profile on first use.
+    pub fn update_chain_metadata(
+        ctx: Context<UpdateChainMetadata>,
+        name: String,
+        description: String,
+        content_uri: String,
+    ) -> Result<()> {
+        require!(
+            name.len() <= MAX_CHAIN_NAME_LEN
+                && description.len() <= MAX_CHAIN_DESCRIPTION_LEN
+                && content_uri.len() <= MAX_CHAIN_URI_LEN,
+            NLPChainError::InvalidChainMetadata
+        );
+        let profile = &mut ctx.accounts.chain_profile;
+        profile.chain = ctx.accounts.chain_state.key();
+        profile.name = name;
+        profile.description = description;
+        profile.content_uri = content_uri;
+        profile.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Register the round-robin writer set for FEATURE_WRITER_EPOCHS. Each
+    // epoch lasts `epoch_slots` slots; replacing the set restarts at epoch 0
+    // with the first writer.
+    pub fn set_writer_committee(
+        ctx: Context<SetWriterCommittee>,
+        writers: Vec<Pubkey>,
+        epoch_slots: u64,
+    ) -> Result<()> {
+        require!(
+            !writers.is_empty() && writers.len() <= WriterCommittee::MAX_WRITERS && epoch_slots > 0,
+            NLPChainError::InvalidWriterCommittee
+        );
+        let committee = &mut ctx.accounts.writer_committee;
+        committee.chain = ctx.accounts.chain_state.key();
+        committee.writers = writers;
+        committee.epoch_slots = epoch_slots;
+        committee.epoch = 0;
+        committee.epoch_start_slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    // Record the epochs that have run out in `epoch`. Writers take turns by
+    // the clock either way; this keeps the stored epoch current for readers.
+    // Permissionless.
+    pub fn advance_epoch(ctx: Context<AdvanceEpoch>) -> Result<()> {
+        let committee = &mut ctx.accounts.writer_committee;
+        let elapsed = committee.effective_epoch(Clock::get()?.slot) - committee.epoch;
+        require!(elapsed > 0, NLPChainError::EpochNotOver);
+        committee.epoch += elapsed;
+        committee.epoch_start_slot += elapsed * committee.epoch_slots;
+        Ok(())
+    }
+
+    // Cap the bytes each contributor may store per quota epoch. A zero
+    // quota turns quotas off; a zero epoch never resets usage.
+    pub fn set_quota(ctx: Context<SetQuota>, quota_bytes: u64, quota_epoch_seconds: i64) -> Result<()> {
+        require!(quota_epoch_seconds >= 0, NLPChainError::InvalidChainConfig);
+        let chain_state = &mut ctx.accounts.chain_state;
+        chain_state.quota_bytes = quota_bytes;
+        chain_state.quota_epoch_seconds = quota_epoch_seconds;
+        Ok(())
+    }
+
+    // Give one contributor a quota other than the chain's: u64::MAX for
+    // unlimited, 0 to fall back to the chain default. `reset_usage` clears
+    // their usage for the current epoch.
+    pub fn set_contributor_quota(
+        ctx: Context<SetContributorQuota>,
+        author: Pubkey,
+        quota_override: u64,
+        reset_usage: bool,
+    ) -> Result<()> {
+        let contributor = &mut ctx.accounts.contributor;
+        contributor.chain = ctx.accounts.chain_state.key();
+        contributor.author = author;
+        contributor.quota_override = quota_override;
+        if reset_usage {
+            contributor.epoch_bytes = 0;
+        }
+        Ok(())
+    }
+
+    // Fold blocks [next_index, next_index + n) of a stats window into its
+    // VectorStats, passing the n block accounts as remaining_accounts in
+    // index order. Windows are keyed by their first block, so comparing two
+    // windows shows embedding drift. Expired or rejected blocks are skipped.
+    // Callable by anyone.
+    pub fn accumulate_stats<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AccumulateStats<'info>>,
+        start_index: u64,
+    ) -> Result<()> {
+        let chain_key = ctx.accounts.chain_state.key();
+        let stats = &mut ctx.accounts.vector_stats;
+        if stats.chain == Pubkey::default() {
+            stats.chain = chain_key;
+            stats.start_index = start_index;
+            stats.next_index = start_index;
+            stats.min = [f64::MAX; VECTOR_STATS_BUCKETS];
+            stats.max = [f64::MIN; VECTOR_STATS_BUCKETS];
+        }
+        let end_index = stats.next_index + ctx.remaining_accounts.len() as u64;
+        require!(
+            end_index <= ctx.accounts.chain_state.block_count,
+            NLPChainError::InvalidArchiveRange
+        );
+        for (index, info) in (stats.next_index..end_index).zip(ctx.remaining_accounts) {
+            let (expected, _) = find_block_address(&chain_key, index);
+            require_keys_eq!(info.key(), expected, NLPChainError::InvalidArchiveRange);
+            if info.data_is_empty() {
+                continue;
+            }
+            let block = Account::<Block>::try_from(info)?;
+            stats.record(&block);
+        }
+        stats.next_index = end_index;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(genesis: GenesisParams, config: ChainConfig)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = ChainState::LEN
+    )]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        // Genesis carries provenance metadata but no embedding
+        space = Block::space(config.max_text_len as usize, 0, MAX_METADATA_ENTRIES),
+        seeds = [b"block", chain_state.key().as_ref(), 0u64.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub genesis_block: Box<Account<'info, Block>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(fork_index: u64)]
+pub struct ForkChain<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = ChainState::LEN
+    )]
+    pub child_chain: Box<Account<'info, ChainState>>,
+
+    #[account(mut)]
+    pub parent_chain: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", parent_chain.key().as_ref(), fork_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = fork_index < parent_chain.linked_count @ NLPChainError::BlockPending
+    )]
+    pub parent_block: Box<Account<'info, Block>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(checkpoint_index: u64)]
+pub struct CloneChain<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = ChainState::LEN
+    )]
+    pub clone_chain: Box<Account<'info, ChainState>>,
+
+    #[account(mut)]
+    pub source_chain: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"checkpoint", source_chain.key().as_ref(), checkpoint_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub checkpoint: Account<'info, Checkpoint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(text: String, minhash: Vec<u32>)]
+pub struct AddBlock<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = chain_state.block_space(),
+        seeds = [b"block", chain_state.key().as_ref(), chain_state.block_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = HashRegistry::LEN,
+        seeds = [b"dedup", chain_state.key().as_ref(), dedup_key(&text).as_ref()],
+        bump
+    )]
+    pub hash_registry: Box<Account<'info, HashRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Contributor::LEN,
+        seeds = [b"contributor", chain_state.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub contributor: Box<Account<'info, Contributor>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = AuthorIndex::LEN,
+        seeds = [b"author-index", chain_state.key().as_ref(), authority.key().as_ref(), contributor.page().to_le_bytes().as_ref()],
+        bump
+    )]
+    pub author_index: Box<Account<'info, AuthorIndex>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = TimeBucket::LEN,
+        seeds = [b"time-bucket", chain_state.key().as_ref(), (Clock::get()?.unix_timestamp / chain_state.bucket_interval).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub time_bucket: Box<Account<'info, TimeBucket>>,
+    
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    /// CHECK: instructions sysvar, required when the chain has an embedder
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    // Required when the chain has a Bloom filter
+    #[account(mut, seeds = [b"bloom", chain_state.key().as_ref()], bump)]
+    pub bloom_filter: Option<AccountLoader<'info, BloomFilter>>,
+
+    /// CHECK: receives the storage fee, required when the chain charges one
+    #[account(mut, address = chain_state.treasury)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    // Required when the chain keeps ChainStats
+    #[account(mut, seeds = [b"stats", chain_state.key().as_ref()], bump)]
+    pub chain_stats: Option<AccountLoader<'info, ChainStats>>,
+
+    // Required when the chain replicates over Wormhole
+    /// CHECK: Wormhole bridge config, validated by the core bridge
+    #[account(mut)]
+    pub wormhole_bridge: Option<UncheckedAccount<'info>>,
+    /// CHECK: message account created by the core bridge
+    #[account(
+        mut,
+        seeds = [b"wormhole-message", chain_state.key().as_ref(), chain_state.block_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub wormhole_message: Option<UncheckedAccount<'info>>,
+    /// CHECK: per-chain emitter PDA, signs the message
+    #[account(seeds = [b"emitter", chain_state.key().as_ref()], bump)]
+    pub wormhole_emitter: Option<UncheckedAccount<'info>>,
+    /// CHECK: emitter sequence, validated by the core bridge
+    #[account(mut)]
+    pub wormhole_sequence: Option<UncheckedAccount<'info>>,
+    /// CHECK: fee collector, validated by the core bridge
+    #[account(mut)]
+    pub wormhole_fee_collector: Option<UncheckedAccount<'info>>,
+    /// CHECK: Wormhole core bridge program
+    #[account(address = WORMHOLE_PROGRAM_ID)]
+    pub wormhole_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: clock sysvar, read by the core bridge
+    #[account(address = anchor_lang::solana_program::sysvar::clock::ID)]
+    pub clock: Option<UncheckedAccount<'info>>,
+    /// CHECK: rent sysvar, read by the core bridge
+    #[account(address = anchor_lang::solana_program::sysvar::rent::ID)]
+    pub rent: Option<UncheckedAccount<'info>>,
+
+    // Required when the chain has FEATURE_ALLOWLIST_ONLY
+    #[account(seeds = [b"writer", chain_state.key().as_ref(), authority.key().as_ref()], bump)]
+    pub writer_permit: Option<Box<Account<'info, WriterPermit>>>,
+
+    // Required when the chain has FEATURE_WRITER_EPOCHS
+    #[account(seeds = [b"committee", chain_state.key().as_ref()], bump)]
+    pub writer_committee: Option<Box<Account<'info, WriterCommittee>>>,
+
+    // Required when the chain has a proof-of-work gate
+    #[account(mut)]
+    pub proof: Option<Box<Account<'info, minimal::ProofData>>>,
+    pub minimal_program: Option<Program<'info, minimal::program::Minimal>>,
+    
+    // Writer; the block, dedup and index accounts are keyed to this signer
+    pub authority: Signer<'info>,
+
+    // Funds rent and the storage fee; may be an application sponsor
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct UpdateVector<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+    /// CHECK: owner, discriminator, version and author are checked in
+    /// update_vector, which edits the data in place and grows blocks sized
+    /// for a smaller vector_dim before a model migration.
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar, required when the chain has an embedder
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChainHeadView<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+}
+
+#[derive(Accounts)]
+pub struct RotateValidators<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ValidatorSet::LEN,
+        seeds = [b"validator-set", chain_state.key().as_ref(), chain_state.validator_epochs.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub validator_set: Account<'info, ValidatorSet>,
+
+    // Required once a set exists: the set being replaced
+    #[account(mut)]
+    pub previous_set: Option<Account<'info, ValidatorSet>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyAttestation<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+    #[account(constraint = validator_set.chain == chain_state.key() @ NLPChainError::InvalidValidatorSet)]
+    pub validator_set: Account<'info, ValidatorSet>,
+
+    /// CHECK: instructions sysvar, read for the attester's ed25519 verification
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Syndicate<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Syndication::LEN,
+        seeds = [b"syndication", source_chain.key().as_ref(), target_chain.key().as_ref()],
+        bump
+    )]
+    pub syndication: Account<'info, Syndication>,
+
+    pub source_chain: Box<Account<'info, ChainState>>,
+
+    #[account(has_one = authority)]
+    pub target_chain: Box<Account<'info, ChainState>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SyncSyndicatedBlock<'info> {
+    #[account(
+        mut,
+        seeds = [b"syndication", source_chain.key().as_ref(), target_chain.key().as_ref()],
+        bump
+    )]
+    pub syndication: Account<'info, Syndication>,
+
+    pub source_chain: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", source_chain.key().as_ref(), syndication.next_source_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub source_block: Box<Account<'info, Block>>,
+
+    #[account(mut)]
+    pub target_chain: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = target_chain.block_space(),
+        seeds = [b"block", target_chain.key().as_ref(), target_chain.block_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub target_block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = HashRegistry::LEN,
+        seeds = [b"dedup", target_chain.key().as_ref(), dedup_key(&source_block.text).as_ref()],
+        bump
+    )]
+    pub hash_registry: Account<'info, HashRegistry>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = SyndicationEdge::LEN,
+        seeds = [b"syndicated", target_chain.key().as_ref(), target_chain.block_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub edge: Account<'info, SyndicationEdge>,
+
+    // Required when the target chain has a Bloom filter
+    #[account(mut, seeds = [b"bloom", target_chain.key().as_ref()], bump)]
+    pub bloom_filter: Option<AccountLoader<'info, BloomFilter>>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SkipSyndicatedBlock<'info> {
+    #[account(
+        mut,
+        seeds = [b"syndication", source_chain.key().as_ref(), target_chain.key().as_ref()],
+        bump
+    )]
+    pub syndication: Account<'info, Syndication>,
+
+    pub source_chain: Box<Account<'info, ChainState>>,
+
+    /// CHECK: may have been closed; deserialized in skip_syndicated_block
+    /// when it still holds a block
+    #[account(
+        seeds = [b"block", source_chain.key().as_ref(), syndication.next_source_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub source_block: UncheckedAccount<'info>,
+
+    pub target_chain: Box<Account<'info, ChainState>>,
+
+    // The target chain's entry for the source block's content, when it
+    // already holds that content
+    pub hash_registry: Option<Account<'info, HashRegistry>>,
+
+    // The target chain's authority, to skip a block that isn't finalized
+    pub authority: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(moderator: Pubkey)]
+pub struct AddModerator<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Moderator::LEN,
+        seeds = [b"moderator", chain_state.key().as_ref(), moderator.as_ref()],
+        bump
+    )]
+    pub moderator_record: Account<'info, Moderator>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveModerator<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"moderator", chain_state.key().as_ref(), moderator_record.moderator.as_ref()],
+        bump
+    )]
+    pub moderator_record: Account<'info, Moderator>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tag: String, block_index: u64)]
+pub struct TagBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = tagger,
+        space = TagEntry::LEN,
+        seeds = [b"tag", chain_state.key().as_ref(), tag_seed(&tag).as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tag_entry: Account<'info, TagEntry>,
+
+    #[account(
+        init_if_needed,
+        payer = tagger,
+        space = TagCounter::LEN,
+        seeds = [b"tag-counter", chain_state.key().as_ref(), tag_seed(&tag).as_ref()],
+        bump
+    )]
+    pub tag_counter: Account<'info, TagCounter>,
+
+    #[account(
+        seeds = [b"moderator", chain_state.key().as_ref(), tagger.key().as_ref()],
+        bump
+    )]
+    pub moderator: Option<Account<'info, Moderator>>,
+
+    #[account(mut)]
+    pub tagger: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tag_hash: [u8; 32], block_index: u64)]
+pub struct UntagBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = tagged_by,
+        seeds = [b"tag", chain_state.key().as_ref(), tag_hash.as_ref(), block_index.to_le_bytes().as_ref()],
+        bump,
+        has_one = tagged_by
+    )]
+    pub tag_entry: Account<'info, TagEntry>,
+
+    #[account(
+        mut,
+        seeds = [b"tag-counter", chain_state.key().as_ref(), tag_hash.as_ref()],
+        bump
+    )]
+    pub tag_counter: Account<'info, TagCounter>,
+
+    #[account(
+        seeds = [b"moderator", chain_state.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub moderator: Option<Account<'info, Moderator>>,
+
+    /// CHECK: receives the tag entry rent; must match `tag_entry.tagged_by`
+    #[account(mut)]
+    pub tagged_by: UncheckedAccount<'info>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_index: u64, target_index: u64)]
+pub struct CiteBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), source_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub source_block: Box<Account<'info, Block>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), target_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub target_block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = author,
+        space = Citation::LEN,
+        seeds = [b"citation", chain_state.key().as_ref(), source_index.to_le_bytes().as_ref(), target_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub citation: Account<'info, Citation>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(from_index: u64, to_index: u64)]
+pub struct LinkBlocks<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), from_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub from_block: Box<Account<'info, Block>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), to_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub to_block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = SemanticLink::LEN,
+        seeds = [b"link", chain_state.key().as_ref(), from_index.to_le_bytes().as_ref(), to_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub link: Account<'info, SemanticLink>,
+
+    // Only accounts that have written to the chain can link its blocks
+    #[account(
+        seeds = [b"contributor", chain_state.key().as_ref(), creator.key().as_ref()],
+        bump,
+        constraint = contributor.block_count > 0 @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub contributor: Account<'info, Contributor>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(from_index: u64, to_index: u64)]
+pub struct RevokeLink<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = creator,
+        seeds = [b"link", chain_state.key().as_ref(), from_index.to_le_bytes().as_ref(), to_index.to_le_bytes().as_ref()],
+        bump,
+        has_one = creator
+    )]
+    pub link: Account<'info, SemanticLink>,
+
+    #[account(
+        seeds = [b"moderator", chain_state.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub moderator: Option<Account<'info, Moderator>>,
+
+    /// CHECK: receives the link rent; must match `link.creator`
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateModel<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEmbedder<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64, reader: Pubkey)]
+pub struct GrantAccess<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = block.authority == author.key() @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = author,
+        space = AccessGrant::LEN,
+        seeds = [b"grant", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), reader.as_ref()],
+        bump
+    )]
+    pub grant: Account<'info, AccessGrant>,
+
+    // Required when the chain sells reader passes
+    #[account(seeds = [b"reader-pass", chain_state.key().as_ref(), reader.as_ref()], bump)]
+    pub reader_pass: Option<Account<'info, ReaderPass>>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64, reader: Pubkey)]
+pub struct RevokeAccess<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = block.authority == author.key() @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        mut,
+        close = author,
+        seeds = [b"grant", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), reader.as_ref()],
+        bump
+    )]
+    pub grant: Account<'info, AccessGrant>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct SetMetadataEntry<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = block.authority == authority.key() @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitCompressedTree<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    /// CHECK: initialized by the account-compression program
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: PDA signing for the tree
+    #[account(seeds = [b"tree-authority", chain_state.key().as_ref()], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub noop_program: Program<'info, Noop>,
+}
+
+#[derive(Accounts)]
+#[instruction(text: String)]
+pub struct AppendCompressedBlock<'info> {
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    /// CHECK: the chain's tree, validated against ChainState
+    #[account(
+        mut,
+        address = chain_state.compressed_tree @ NLPChainError::CompressedTreeMismatch
+    )]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: PDA signing for the tree
+    #[account(seeds = [b"tree-authority", chain_state.key().as_ref()], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar, required when the chain has an embedder
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = HashRegistry::LEN,
+        seeds = [b"dedup", chain_state.key().as_ref(), dedup_key(&text).as_ref()],
+        bump
+    )]
+    pub hash_registry: Account<'info, HashRegistry>,
+
+    // Required when the chain has a Bloom filter
+    #[account(mut, seeds = [b"bloom", chain_state.key().as_ref()], bump)]
+    pub bloom_filter: Option<AccountLoader<'info, BloomFilter>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = Contributor::LEN,
+        seeds = [b"contributor", chain_state.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub contributor: Box<Account<'info, Contributor>>,
+
+    /// CHECK: receives the storage fee, required when the chain charges one
+    #[account(mut, address = chain_state.treasury)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    // Required when the chain has FEATURE_ALLOWLIST_ONLY
+    #[account(seeds = [b"writer", chain_state.key().as_ref(), authority.key().as_ref()], bump)]
+    pub writer_permit: Option<Box<Account<'info, WriterPermit>>>,
+
+    // Required when the chain has FEATURE_WRITER_EPOCHS
+    #[account(seeds = [b"committee", chain_state.key().as_ref()], bump)]
+    pub writer_committee: Option<Box<Account<'info, WriterCommittee>>>,
+
+    // Required when the chain has a proof-of-work gate
+    #[account(mut)]
+    pub proof: Option<Box<Account<'info, minimal::ProofData>>>,
+    pub minimal_program: Option<Program<'info, minimal::program::Minimal>>,
+
+    // Writer; also pays rent and the storage fee
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub noop_program: Program<'info, Noop>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyCompressedLeaf<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    /// CHECK: the chain's tree, validated against ChainState
+    #[account(address = chain_state.compressed_tree @ NLPChainError::CompressedTreeMismatch)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    pub compression_program: Program<'info, SplAccountCompression>,
+}
+
+#[derive(Accounts)]
+pub struct InitBloomFilter<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = BloomFilter::LEN,
+        seeds = [b"bloom", chain_state.key().as_ref()],
+        bump
+    )]
+    pub bloom_filter: AccountLoader<'info, BloomFilter>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProbablyContains<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+    #[account(seeds = [b"bloom", chain_state.key().as_ref()], bump)]
+    pub bloom_filter: AccountLoader<'info, BloomFilter>,
+}
+
+#[derive(Accounts)]
+#[instruction(index_a: u64, index_b: u64)]
+pub struct CompareSignatures<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), index_a.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block_a: Box<Account<'info, Block>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), index_b.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block_b: Box<Account<'info, Block>>,
+}
+
+#[derive(Accounts)]
+#[instruction(centroid_id: u16)]
+pub struct UpsertCentroid<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = Centroid::LEN,
+        seeds = [b"centroid", chain_state.key().as_ref(), centroid_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub centroid: Account<'info, Centroid>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct AssignCluster<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct FlagBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = flagger,
+        space = Flag::LEN,
+        seeds = [b"flag", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), flagger.key().as_ref()],
+        bump
+    )]
+    pub flag: Account<'info, Flag>,
+
+    #[account(mut)]
+    pub flagger: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64, flagger: Pubkey)]
+pub struct ReviewFlag<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"flag", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), flagger.as_ref()],
+        bump
+    )]
+    pub flag: Account<'info, Flag>,
+
+    #[account(
+        seeds = [b"moderator", chain_state.key().as_ref(), moderator.key().as_ref()],
+        bump
+    )]
+    pub moderator_record: Account<'info, Moderator>,
+
+    pub moderator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct RetractBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = moderator,
+        space = Retraction::LEN,
+        seeds = [b"retraction", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub retraction: Account<'info, Retraction>,
+
+    #[account(
+        seeds = [b"moderator", chain_state.key().as_ref(), moderator.key().as_ref()],
+        bump
+    )]
+    pub moderator_record: Account<'info, Moderator>,
+
+    #[account(mut)]
+    pub moderator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct TipBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    /// CHECK: lamport tips are paid to the block's author
+    #[account(mut, address = block.authority)]
+    pub author: UncheckedAccount<'info>,
+
+    #[account(mut, constraint = reader_token.owner == reader.key())]
+    pub reader_token: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = author_token.owner == block.authority,
+        constraint = Some(author_token.mint) == reader_token.as_ref().map(|t| t.mint)
+    )]
+    pub author_token: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub reader: Signer<'info>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeatures<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(writer: Pubkey)]
+pub struct AddWriter<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = WriterPermit::LEN,
+        seeds = [b"writer", chain_state.key().as_ref(), writer.as_ref()],
+        bump
+    )]
+    pub writer_permit: Account<'info, WriterPermit>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveWriter<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"writer", chain_state.key().as_ref(), writer_permit.writer.as_ref()],
+        bump
+    )]
+    pub writer_permit: Account<'info, WriterPermit>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCheckpointCrank<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCheckpointBackpressure<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetLicensePolicy<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetStorageFee<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPowGate<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetWormholeReplication<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRateLimit<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateCheckpoint<'info> {
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = Checkpoint::LEN,
+        seeds = [b"checkpoint", chain_state.key().as_ref(), chain_state.checkpoint_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub checkpoint: Account<'info, Checkpoint>,
+
+    // Pays the crank bounty, required when the chain offers one
+    #[account(mut, seeds = [b"treasury", chain_state.key().as_ref()], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    // The chain authority, or anyone once a checkpoint is due
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_index: u64, end_index: u64, checkpoint_index: u64)]
+pub struct ArchiveRange<'info> {
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"checkpoint", chain_state.key().as_ref(), checkpoint_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub checkpoint: Account<'info, Checkpoint>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = Archive::LEN,
+        seeds = [b"archive", chain_state.key().as_ref(), start_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub archive: Account<'info, Archive>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct CloseBlock<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"archive", chain_state.key().as_ref(), archive.start_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = archive.start_index <= block_index && block_index < archive.end_index
+            @ NLPChainError::InvalidArchiveRange
+    )]
+    pub archive: Account<'info, Archive>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    /// CHECK: receives the block's rent; the chain authority for blocks
+    /// written before the payer was recorded
+    #[account(
+        mut,
+        constraint = rent_payer.key() == block.rent_payer
+            || (block.rent_payer == Pubkey::default() && rent_payer.key() == authority.key())
+            @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // Required when the chain keeps ChainStats
+    #[account(mut, seeds = [b"stats", chain_state.key().as_ref()], bump)]
+    pub chain_stats: Option<AccountLoader<'info, ChainStats>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetReaderPassConfig<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BuyReaderPass<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init_if_needed,
+        payer = reader,
+        space = ReaderPass::LEN,
+        seeds = [b"reader-pass", chain_state.key().as_ref(), reader.key().as_ref()],
+        bump
+    )]
+    pub reader_pass: Account<'info, ReaderPass>,
+
+    #[account(
+        mut,
+        constraint = reader_token.owner == reader.key(),
+        constraint = reader_token.mint == chain_state.pass_mint @ NLPChainError::InvalidReaderPass
+    )]
+    pub reader_token: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_token.owner == chain_state.treasury @ NLPChainError::InvalidReaderPass,
+        constraint = treasury_token.mint == chain_state.pass_mint @ NLPChainError::InvalidReaderPass
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reader: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitChainStats<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ChainStats::LEN,
+        seeds = [b"stats", chain_state.key().as_ref()],
+        bump
+    )]
+    pub chain_stats: AccountLoader<'info, ChainStats>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct MigrateBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    /// CHECK: may hold an older Block layout; validated by migrate_layout
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateChainState<'info> {
+    /// CHECK: may hold an older ChainState layout; validated by migrate_layout
+    #[account(mut, owner = crate::ID)]
+    pub chain_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(old_index: u64, new_index: u64)]
+pub struct SupersedeBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), old_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub old_block: Box<Account<'info, Block>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), new_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_block: Box<Account<'info, Block>>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardians<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = GuardianSet::LEN,
+        seeds = [b"guardians", chain_state.key().as_ref()],
+        bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_authority: Pubkey)]
+pub struct ProposeRecovery<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(seeds = [b"guardians", chain_state.key().as_ref()], bump)]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        init_if_needed,
+        payer = guardian,
+        space = RecoveryProposal::LEN,
+        seeds = [b"recovery", chain_state.key().as_ref(), new_authority.as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, RecoveryProposal>,
+
+    #[account(mut)]
+    pub guardian: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(seeds = [b"guardians", chain_state.key().as_ref()], bump)]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery", chain_state.key().as_ref(), proposal.new_authority.as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, RecoveryProposal>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(seeds = [b"guardians", chain_state.key().as_ref()], bump)]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        mut,
+        close = new_authority,
+        seeds = [b"recovery", chain_state.key().as_ref(), new_authority.key().as_ref()],
+        bump,
+        has_one = new_authority
+    )]
+    pub proposal: Account<'info, RecoveryProposal>,
+
+    /// CHECK: receives the chain and the proposal's rent
+    #[account(mut)]
+    pub new_authority: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRecovery<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"recovery", chain_state.key().as_ref(), proposal.new_authority.as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, RecoveryProposal>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitTreasury<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Treasury::LEN,
+        seeds = [b"treasury", chain_state.key().as_ref()],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeTreasuryShares<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(mut, seeds = [b"treasury", chain_state.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(mut, seeds = [b"treasury", chain_state.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTokens<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(mut, seeds = [b"treasury", chain_state.key().as_ref()], bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut, constraint = treasury_token.owner == treasury.key() @ NLPChainError::InvalidTreasuryShares)]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct HammingCompare {}
+
+#[derive(Accounts)]
+pub struct MatchKeyword {}
+
+#[derive(Accounts)]
+#[instruction(oracle: Pubkey)]
+pub struct AddOracle<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Oracle::LEN,
+        seeds = [b"oracle", chain_state.key().as_ref(), oracle.as_ref()],
+        bump
+    )]
+    pub oracle_record: Account<'info, Oracle>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveOracle<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"oracle", chain_state.key().as_ref(), oracle_record.oracle.as_ref()],
+        bump
+    )]
+    pub oracle_record: Account<'info, Oracle>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRequiredAttestations<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct AttestBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = oracle,
+        space = Attestation::LEN,
+        seeds = [b"attestation", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), oracle.key().as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    #[account(
+        seeds = [b"oracle", chain_state.key().as_ref(), oracle.key().as_ref()],
+        bump
+    )]
+    pub oracle_record: Account<'info, Oracle>,
+
+    #[account(mut)]
+    pub oracle: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetBlockNftTree<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct MintBlockNft<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = BlockNft::LEN,
+        seeds = [b"block-nft", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block_nft: Account<'info, BlockNft>,
+
+    /// CHECK: Bubblegum TreeConfig for the tree
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = BUBBLEGUM_PROGRAM_ID
+    )]
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: the chain's NFT tree, validated against ChainState
+    #[account(
+        mut,
+        address = chain_state.nft_tree @ NLPChainError::CompressedTreeMismatch
+    )]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: receives the NFT
+    #[account(address = block.authority)]
+    pub leaf_owner: UncheckedAccount<'info>,
+
+    /// CHECK: PDA delegated on the tree, signs the mint
+    #[account(seeds = [b"nft-authority", chain_state.key().as_ref()], bump)]
+    pub nft_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Bubblegum program
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub noop_program: Program<'info, Noop>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetBlockTtl<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetLimits<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64, checkpoint_index: u64)]
+pub struct ExpireBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"checkpoint", chain_state.key().as_ref(), checkpoint_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = block_index < checkpoint.block_count @ NLPChainError::BlockNotExpired
+    )]
+    pub checkpoint: Account<'info, Checkpoint>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    /// CHECK: receives the block's rent
+    #[account(
+        mut,
+        address = block.rent_payer,
+        constraint = block.rent_payer != Pubkey::default() @ NLPChainError::InvalidAccountVersion
+    )]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // Closed with the block unless the block was retracted
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"dedup", chain_state.key().as_ref(), dedup_key(&block.text).as_ref()],
+        bump,
+        constraint = hash_registry.block_index == block.index @ NLPChainError::HashRegistryRequired
+    )]
+    pub hash_registry: Option<Account<'info, HashRegistry>>,
+
+    // Required when the chain keeps ChainStats
+    #[account(mut, seeds = [b"stats", chain_state.key().as_ref()], bump)]
+    pub chain_stats: Option<AccountLoader<'info, ChainStats>>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64, parent_index: u64)]
+pub struct ReplyToBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump,
+        has_one = authority @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), parent_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub parent_block: Box<Account<'info, Block>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = ThreadIndex::LEN,
+        seeds = [b"thread", chain_state.key().as_ref(), parent_index.to_le_bytes().as_ref(), parent_block.reply_page().to_le_bytes().as_ref()],
+        bump
+    )]
+    pub thread_index: Account<'info, ThreadIndex>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_index: u64, end_index: u64)]
+pub struct AddSummary<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = SummaryBlock::space(chain_state.max_text_len as usize, chain_state.vector_dim as usize),
+        seeds = [b"summary", chain_state.key().as_ref(), start_index.to_le_bytes().as_ref(), end_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub summary: Account<'info, SummaryBlock>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxSubVectors<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct SetSubVectors<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump,
+        has_one = authority @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = SubVectors::space(chain_state.max_sub_vectors as usize, chain_state.vector_dim as usize),
+        seeds = [b"sub-vectors", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub sub_vectors: Account<'info, SubVectors>,
+
+    /// CHECK: instructions sysvar, required when the chain has an embedder
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index_a: u64, sub_a: Option<u8>, index_b: u64)]
+pub struct CompareVectors<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), index_a.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block_a: Box<Account<'info, Block>>,
+
+    #[account(
+        seeds = [b"sub-vectors", chain_state.key().as_ref(), index_a.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub sub_vectors_a: Option<Account<'info, SubVectors>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), index_b.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block_b: Box<Account<'info, Block>>,
+
+    #[account(
+        seeds = [b"sub-vectors", chain_state.key().as_ref(), index_b.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub sub_vectors_b: Option<Account<'info, SubVectors>>,
+}
+
+#[derive(Accounts)]
+pub struct SetSparseDim<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct SetSparseVector<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump,
+        has_one = authority @ NLPChainError::UnauthorizedUpdate
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = SparseVector::LEN,
+        seeds = [b"sparse", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub sparse_vector: Account<'info, SparseVector>,
+
+    /// CHECK: instructions sysvar, required when the chain has an embedder
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index_a: u64, index_b: u64)]
+pub struct CompareSparse<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"sparse", chain_state.key().as_ref(), index_a.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub sparse_a: Account<'info, SparseVector>,
+
+    #[account(
+        seeds = [b"sparse", chain_state.key().as_ref(), index_b.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub sparse_b: Account<'info, SparseVector>,
+}
+
+#[derive(Accounts)]
+pub struct CreateNamespace<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Namespace::LEN,
+        seeds = [b"namespace", chain_state.key().as_ref(), (chain_state.namespace_count + 1).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub namespace: Account<'info, Namespace>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64, namespace_id: u16)]
+pub struct FileBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        mut,
+        seeds = [b"namespace", chain_state.key().as_ref(), namespace_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub namespace: Account<'info, Namespace>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = NamespaceIndex::LEN,
+        seeds = [b"ns-index", chain_state.key().as_ref(), namespace_id.to_le_bytes().as_ref(), namespace.page().to_le_bytes().as_ref()],
+        bump
+    )]
+    pub namespace_index: Account<'info, NamespaceIndex>,
+
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64, amount: u64, task_kind: AnnotationTask)]
+pub struct PostBounty<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = poster,
+        space = Bounty::LEN,
+        seeds = [b"bounty", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), poster.key().as_ref(), &[task_kind as u8]],
+        bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(
+        init,
+        payer = poster,
+        token::mint = mint,
+        token::authority = bounty,
+        seeds = [b"bounty-vault", bounty.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut, constraint = poster_token.owner == poster.key() && poster_token.mint == mint.key())]
+    pub poster_token: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub poster: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitAnnotation<'info> {
+    #[account(
+        seeds = [b"bounty", bounty.chain.as_ref(), bounty.block_index.to_le_bytes().as_ref(), bounty.poster.as_ref(), &[bounty.task_kind as u8]],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(
+        init,
+        payer = annotator,
+        space = Annotation::LEN,
+        seeds = [b"annotation", bounty.key().as_ref(), annotator.key().as_ref()],
+        bump
+    )]
+    pub annotation: Account<'info, Annotation>,
+
+    #[account(mut)]
+    pub annotator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAnnotation<'info> {
+    #[account(
+        seeds = [b"block", bounty.chain.as_ref(), bounty.block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        mut,
+        seeds = [b"bounty", bounty.chain.as_ref(), bounty.block_index.to_le_bytes().as_ref(), bounty.poster.as_ref(), &[bounty.task_kind as u8]],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(has_one = bounty)]
+    pub annotation: Account<'info, Annotation>,
+
+    // Present when the approver moderates the chain
+    #[account(
+        seeds = [b"moderator", bounty.chain.as_ref(), approver.key().as_ref()],
+        bump
+    )]
+    pub moderator_record: Option<Account<'info, Moderator>>,
+
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBounty<'info> {
+    #[account(
+        mut,
+        close = poster,
+        has_one = poster,
+        seeds = [b"bounty", bounty.chain.as_ref(), bounty.block_index.to_le_bytes().as_ref(), bounty.poster.as_ref(), &[bounty.task_kind as u8]],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(mut, seeds = [b"bounty-vault", bounty.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(address = bounty.annotation, has_one = annotator)]
+    pub annotation: Account<'info, Annotation>,
+
+    #[account(mut, constraint = annotator_token.owner == annotator.key() && annotator_token.mint == bounty.mint)]
+    pub annotator_token: Account<'info, TokenAccount>,
+
+    /// CHECK: receives the bounty's and the vault's rent
+    #[account(mut)]
+    pub poster: UncheckedAccount<'info>,
+
+    pub annotator: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBounty<'info> {
+    #[account(
+        mut,
+        close = poster,
+        has_one = poster,
+        seeds = [b"bounty", bounty.chain.as_ref(), bounty.block_index.to_le_bytes().as_ref(), bounty.poster.as_ref(), &[bounty.task_kind as u8]],
+        bump = bounty.bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+
+    #[account(mut, seeds = [b"bounty-vault", bounty.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = poster_token.owner == poster.key() && poster_token.mint == bounty.mint)]
+    pub poster_token: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub poster: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetPinMint<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct PinBlock<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Pin::LEN,
+        seeds = [b"pin", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub pin: Account<'info, Pin>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = mint,
+        token::authority = pin,
+        seeds = [b"pin-vault", pin.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(address = chain_state.pin_mint @ NLPChainError::InvalidPin)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut, constraint = owner_token.owner == owner.key() && owner_token.mint == mint.key())]
+    pub owner_token: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct Unpin<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    /// CHECK: the pinned block, possibly closed since; read and written at
+    /// fixed offsets
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"pin", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump = pin.bump
+    )]
+    pub pin: Account<'info, Pin>,
+
+    #[account(mut, seeds = [b"pin-vault", pin.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = owner_token.owner == owner.key() && owner_token.mint == vault.mint)]
+    pub owner_token: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(approver: Pubkey)]
+pub struct AddApprover<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Approver::LEN,
+        seeds = [b"approver", chain_state.key().as_ref(), approver.as_ref()],
+        bump
+    )]
+    pub approver_record: Account<'info, Approver>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveApprover<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"approver", chain_state.key().as_ref(), approver_record.approver.as_ref()],
+        bump
+    )]
+    pub approver_record: Account<'info, Approver>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRequiredApprovals<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct ApproveBlock<'info> {
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        seeds = [b"approver", chain_state.key().as_ref(), approver.key().as_ref()],
+        bump
+    )]
+    pub approver_record: Account<'info, Approver>,
+
+    #[account(
+        init,
+        payer = approver,
+        space = Approval::LEN,
+        seeds = [b"approval", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref(), approver.key().as_ref()],
+        bump
+    )]
+    pub approval: Account<'info, Approval>,
+
+    #[account(mut)]
+    pub approver: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LinkReleasedBlocks<'info> {
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+}
+
+#[derive(Accounts)]
+#[instruction(block_index: u64)]
+pub struct RejectBlock<'info> {
+    #[account(mut)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"block", chain_state.key().as_ref(), block_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub block: Box<Account<'info, Block>>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"dedup", chain_state.key().as_ref(), dedup_key(&block.text).as_ref()],
+        bump
+    )]
+    pub hash_registry: Account<'info, HashRegistry>,
+
+    /// CHECK: receives the block's rent
+    #[account(mut, address = block.rent_payer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"approver", chain_state.key().as_ref(), approver.key().as_ref()],
+        bump
+    )]
+    pub approver_record: Account<'info, Approver>,
+
+    pub approver: Signer<'info>,
+
+    // Required when the chain keeps ChainStats
+    #[account(mut, seeds = [b"stats", chain_state.key().as_ref()], bump)]
+    pub chain_stats: Option<AccountLoader<'info, ChainStats>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateChainMetadata<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = ChainProfile::LEN,
+        seeds = [b"chain-profile", chain_state.key().as_ref()],
+        bump
+    )]
+    pub chain_profile: Account<'info, ChainProfile>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetWriterCommittee<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = WriterCommittee::LEN,
+        seeds = [b"committee", chain_state.key().as_ref()],
+        bump
+    )]
+    pub writer_committee: Account<'info, WriterCommittee>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceEpoch<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(mut, seeds = [b"committee", chain_state.key().as_ref()], bump)]
+    pub writer_committee: Account<'info, WriterCommittee>,
+}
+
+#[derive(Accounts)]
+pub struct SetQuota<'info> {
+    #[account(mut, has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(author: Pubkey)]
+pub struct SetContributorQuota<'info> {
+    #[account(has_one = authority)]
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = Contributor::LEN,
+        seeds = [b"contributor", chain_state.key().as_ref(), author.as_ref()],
+        bump
+    )]
+    pub contributor: Account<'info, Contributor>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_index: u64)]
+pub struct AccumulateStats<'info> {
+    pub chain_state: Box<Account<'info, ChainState>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = VectorStats::LEN,
+        seeds = [b"vector-stats", chain_state.key().as_ref(), start_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vector_stats: Account<'info, VectorStats>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(Default)]
+pub struct ChainState {
+    pub version: u8, // layout version, see CHAIN_STATE_LAYOUT_CHANGES
+    pub authority: Pubkey,
+    pub block_count: u64,
+    pub last_hash: Hash,
+    pub parent_chain: Pubkey, // default for root chains
+    pub fork_index: u64,
+    pub fork_count: u64,
+    pub validator_epochs: u64, // number of validator sets ever scheduled
+    pub bucket_interval: i64, // seconds per TimeBucket
+    pub model_id: String,
+    pub model_version: u32,
+    pub vector_dim: u16,
+    pub embedder: Pubkey, // default when vectors need no signature
+    pub compressed_tree: Pubkey, // default when compression is not enabled
+    pub compressed_count: u64, // leaves appended to compressed_tree
+    pub bloom_enabled: bool,
+    pub centroid_count: u16,
+    pub min_slot_gap: u64, // minimum slots between a writer's blocks
+    pub rate_window_slots: u64,
+    pub max_blocks_per_window: u32, // per writer, per rate window
+    pub fee_per_byte: u64, // lamports per stored byte of text, vector and metadata
+    pub treasury: Pubkey,
+    pub checkpoint_count: u64,
+    pub archived_count: u64, // blocks [.., archived_count) are covered by Archives
+    pub archived_hash: Hash, // data_hash of the last archived block
+    pub pass_mint: Pubkey,
+    pub pass_price: u64, // pass_mint base units per pass epoch; 0 when passes are off
+    pub pass_epoch_seconds: i64,
+    pub pow_max_age: i64, // max proof age in seconds; 0 when no proof is required
+    pub stats_enabled: bool,
+    pub wormhole_enabled: bool,
+    pub features: u64, // FEATURE_* bitmask
+    pub default_license: License,
+    pub allowed_licenses: u16, // bit `License as u8` set for each allowed license
+    pub max_text_len: u16,
+    pub max_metadata_entries: u8,
+    pub required_attestations: u8, // oracle attestations before a block is finalized
+    pub checkpoint_interval: u64, // blocks between permissionless checkpoints; 0 for authority only
+    pub checkpoint_bounty: u64, // lamports from the treasury per permissionless checkpoint
+    pub checkpointed_count: u64, // block_count at the latest checkpoint
+    pub nft_tree: Pubkey, // Bubblegum tree for block NFTs
+    pub block_ttl: i64, // seconds until a block may be expired; 0 for never
+    pub max_sub_vectors: u8, // per-sentence embeddings per block; 0 when off
+    pub sparse_dim: u32, // sparse vocabulary size; 0 when sparse vectors are off
+    pub namespace_count: u16,
+    pub pin_mint: Pubkey, // token locked by pin_block; default when pinning is off
+    pub required_approvals: u8, // approver sign-offs before a new block joins the head; 0 when off
+    pub created_at: i64,
+    pub genesis_config: ChainConfig, // as passed to initialize, or the parent's at fork time
+    pub quota_bytes: u64, // stored bytes per contributor per quota epoch; 0 for unlimited
+    pub quota_epoch_seconds: i64, // 0 when quotas never reset
+    pub source_checkpoint: Option<u64>, // parent checkpoint a clone_chain copy started from
+    pub clone_count: u64,
+    pub max_unchecked_blocks: u64, // blocks allowed past the latest checkpoint; 0 for unbounded
+    pub linked_count: u64, // blocks [.., linked_count) are in the hash chain ending at last_hash
+    pub recovery_nonce: u64, // bumped by set_guardians and execute_recovery, lapsing open proposals
+}
+
+impl ChainState {
+    pub const LEN: usize = 8 + // discriminator
+        1 + // version
+        32 + // authority
+        8 + // block_count
+        32 + // last_hash
+        32 + // parent_chain
+        8 + // fork_index
+        8 + // fork_count
+        8 + // validator_epochs
+        8 + // bucket_interval
+        4 + MAX_MODEL_ID_LEN + // model_id
+        4 + // model_version
+        2 + // vector_dim
+        32 + // embedder
+        32 + // compressed_tree
+        8 + // compressed_count
+        1 + // bloom_enabled
+        2 + // centroid_count
+        8 + // min_slot_gap
+        8 + // rate_window_slots
+        4 + // max_blocks_per_window
+        8 + // fee_per_byte
+        32 + // treasury
+        8 + // checkpoint_count
+        8 + // archived_count
+        32 + // archived_hash
+        32 + // pass_mint
+        8 + // pass_price
+        8 + // pass_epoch_seconds
+        8 + // pow_max_age
+        1 + // stats_enabled
+        1 + // wormhole_enabled
+        8 + // features
+        1 + // default_license
+        2 + // allowed_licenses
+        2 + // max_text_len
+        1 + // max_metadata_entries
+        1 + // required_attestations
+        8 + // checkpoint_interval
+        8 + // checkpoint_bounty
+        8 + // checkpointed_count
+        32 + // nft_tree
+        8 + // block_ttl
+        1 + // max_sub_vectors
+        4 + // sparse_dim
+        2 + // namespace_count
+        32 + // pin_mint
+        1 + // required_approvals
+        8 + // created_at
+        ChainConfig::LEN + // genesis_config
+        8 + // quota_bytes
+        8 + // quota_epoch_seconds
+        1 + 8 + // source_checkpoint
+        8 + // clone_count
+        8 + // max_unchecked_blocks
+        8 + // linked_count
+        8; // recovery_nonce
+
+    // Account size of a block on this chain
+    pub fn block_space(&self) -> usize {
+        let vector_dim = if self.has_feature(FEATURE_BINARY_ONLY | FEATURE_SPARSE_ONLY) {
+            0
+        } else {
+            self.vector_dim as usize
+        };
+        Block::space(
+            self.max_text_len as usize,
+            vector_dim,
+            self.max_metadata_entries as usize,
+        )
+    }
+
+    // Enforce the chain's text and metadata limits
+    pub fn check_content(&self, text: &str, metadata: &[MetadataEntry]) -> Result<()> {
+        require!(
+            text.len() <= self.max_text_len as usize,
+            NLPChainError::ContentTooLarge
+        );
+        require!(
+            metadata.len() <= self.max_metadata_entries as usize,
+            NLPChainError::ContentTooLarge
+        );
+        Ok(())
+    }
+
+    pub fn allows_license(&self, license: License) -> bool {
+        self.allowed_licenses & license.bit() != 0
+    }
+
+    // Current operational settings, in the shape initialize takes them
+    pub fn config(&self) -> ChainConfig {
+        ChainConfig {
+            bucket_interval: self.bucket_interval,
+            model_version: self.model_version,
+            vector_dim: self.vector_dim,
+            max_text_len: self.max_text_len,
+            max_metadata_entries: self.max_metadata_entries,
+        }
+    }
+
+    pub fn blocks_since_checkpoint(&self) -> u64 {
+        self.block_count.saturating_sub(self.checkpointed_count)
+    }
+
+    // Refuse new blocks once max_unchecked_blocks have piled up since the
+    // latest checkpoint
+    pub fn check_backpressure(&self) -> Result<()> {
+        require!(
+            self.max_unchecked_blocks == 0 || self.blocks_since_checkpoint() < self.max_unchecked_blocks,
+            NLPChainError::CheckpointRequired
+        );
+        Ok(())
+    }
+
+    pub fn has_feature(&self, feature: u64) -> bool {
+        self.features & feature != 0
+    }
+
+    // Lamports owed for storing a block's content
+    pub fn storage_fee(
+        &self,
+        text: &str,
+        vector: &[f64],
+        metadata: &[MetadataEntry],
+    ) -> Result<u64> {
+        (stored_bytes(text, vector, metadata)? as u64)
+            .checked_mul(self.fee_per_byte)
+            .ok_or_else(|| error!(NLPChainError::InvalidChainConfig))
+    }
+
+    // Reject embeddings from a different model or of the wrong dimension
+    pub fn check_embedding(&self, model: &ModelRef, vector: &[f64]) -> Result<()> {
+        require!(
+            model.model_id == self.model_id && model.model_version == self.model_version,
+            NLPChainError::ModelMismatch
+        );
+        require!(
+            vector.len() == self.vector_dim as usize
+                || (vector.is_empty() && self.has_feature(FEATURE_SPARSE_ONLY)),
+            NLPChainError::ModelMismatch
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GenesisParams {
+    pub title: String,
+    pub model_id: String,
+    pub license: String,
+    pub external_root_hash: [u8; 32],
+}
+
+impl GenesisParams {
+    // Metadata entries for block 0
+    fn to_metadata(&self) -> Vec<MetadataEntry> {
+        let root_hash: String = self
+            .external_root_hash
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        vec![
+            MetadataEntry::new("model_id", MetadataValue::Str(self.model_id.clone())),
+            MetadataEntry::new("license", MetadataValue::Str(self.license.clone())),
+            MetadataEntry::new("external_root_hash", MetadataValue::Str(root_hash)),
+        ]
+    }
+}
+
+// Content license of a block. Proprietary is variant 0, so blocks migrated
+// from before licenses existed get the most restrictive one.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum License {
+    #[default]
+    Proprietary,
+    Cc0,
+    CcBy,
+    CcBySa,
+    CcByNc,
+    Mit,
+    Apache2,
+}
+
+// Every License variant in ChainState.allowed_licenses
+pub const LICENSE_ALL: u16 = (1 << 7) - 1;
+
+impl License {
+    pub fn bit(self) -> u16 {
+        1 << self as u8
+    }
+
+    // Map an SPDX identifier to a License; unknown identifiers are proprietary
+    pub fn from_spdx(id: &str) -> Self {
+        match id {
+            "CC0-1.0" => License::Cc0,
+            "CC-BY-4.0" => License::CcBy,
+            "CC-BY-SA-4.0" => License::CcBySa,
+            "CC-BY-NC-4.0" => License::CcByNc,
+            "MIT" => License::Mit,
+            "Apache-2.0" => License::Apache2,
+            _ => License::Proprietary,
+        }
+    }
+}
+
+pub const MAX_METADATA_ENTRIES: usize = 8;
+pub const MAX_METADATA_KEY_LEN: usize = 32;
+pub const MAX_METADATA_STR_LEN: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum MetadataValue {
+    Str(String),
+    U64(u64),
+    Pubkey(Pubkey),
+    Bool(bool),
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MetadataEntry {
+    pub key: String,
+    pub value: MetadataValue,
+}
+
+impl MetadataEntry {
+    pub const MAX_LEN: usize = 4 + MAX_METADATA_KEY_LEN + // key
+        1 + 4 + MAX_METADATA_STR_LEN; // value (largest variant)
+
+    pub fn new(key: &str, value: MetadataValue) -> Self {
+        Self {
+            key: key.to_string(),
+            value,
+        }
+    }
+}
+
+// Operational settings chosen at initialize (not part of the genesis hash)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChainConfig {
+    pub bucket_interval: i64,
+    pub model_version: u32,
+    pub vector_dim: u16,
+    pub max_text_len: u16, // bytes, at most MAX_TEXT_LEN
+    pub max_metadata_entries: u8, // at most MAX_METADATA_ENTRIES
+}
+
+impl ChainConfig {
+    pub const LEN: usize = 8 + // bucket_interval
+        4 + // model_version
+        2 + // vector_dim
+        2 + // max_text_len
+        1; // max_metadata_entries
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            bucket_interval: 86_400, // daily
+            model_version: 1,
+            vector_dim: MAX_VECTOR_DIM as u16,
+            max_text_len: 1000,
+            max_metadata_entries: MAX_METADATA_ENTRIES as u8,
+        }
+    }
+}
+
+// Embedding model a writer declares for the vector it submits
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ModelRef {
+    pub model_id: String,
+    pub model_version: u32,
+}
+
+pub const MAX_MODEL_ID_LEN: usize = 64;
+pub const MAX_VECTOR_DIM: usize = 768;
+// Cap on ChainConfig::max_text_len; keeps Block::LEN under the 10 KiB CPI allocation limit
+pub const MAX_TEXT_LEN: usize = 2048;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChainHead {
+    pub block_count: u64,
+    pub last_hash: [u8; 32],
+}
+
+// Fixed-size fields come first so their offsets (BLOCK_*_OFFSET) are stable
+// for memcmp filters. New fixed-size fields go before `encryption_pubkey`;
+// bump BLOCK_VERSION and record the insertion in BLOCK_LAYOUT_CHANGES.
+#[account]
+#[derive(Default)]
+pub struct Block {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub index: u64,
+    pub timestamp: i64,
+    pub data_hash: Hash,
+    pub previous_hash: Hash,
+    pub citation_count: u64,
+    pub model_version: u32,
+    pub vector_norm: u64, // L2 norm of `vector`, scaled by VECTOR_NORM_SCALE
+    pub cluster_id: u16, // nearest centroid, valid when `clustered`
+    pub clustered: bool,
+    pub flag_count: u32,
+    pub retracted: bool, // content tombstoned by a moderator
+    pub tips_received: u64, // number of tips paid to the author
+    pub eth_author: [u8; 20], // Ethereum author for add_block_secp blocks, zero otherwise
+    pub superseded_by: u64, // correcting block's index, 0 when current
+    pub supersedes: u64, // index of the block this one corrects, 0 when none
+    pub license: License,
+    pub binary_vector: [u64; BINARY_VECTOR_WORDS], // sign bit per dimension, see binary_quantize
+    pub attestation_count: u16,
+    pub rent_payer: Pubkey, // refunded when the block is expired
+    pub reply_to: u64, // parent block's index, 0 when not a reply
+    pub thread_root: u64, // first block of the reply chain, 0 when not a reply
+    pub reply_count: u32, // direct replies
+    pub keyword_hashes: [u64; MAX_KEYWORDS], // see keyword_hash; zero for unused slots
+    pub namespace_id: u16, // 0 when not filed under a namespace
+    pub pin_weight: u64, // pin_mint base units currently locked against the block
+    pub pending: bool, // awaiting approvals; not yet linked into the chain head
+    pub approval_count: u8,
+    pub hash_scheme: u8, // how data_hash was computed, see HASH_SCHEME_V1
+    pub stats_bytes: u64, // bytes added to ChainStats.total_bytes at append, removed with the block
+    pub encryption_pubkey: Option<Pubkey>, // set for confidential blocks
+    pub text: String,
+    pub vector: Vec<f64>,
+    pub metadata: Vec<MetadataEntry>,
+    pub minhash: Vec<u32>, // empty, or MINHASH_SIZE values
+}
+
+impl Block {
+    // Largest block any chain can configure
+    pub const LEN: usize = Self::space(MAX_TEXT_LEN, MAX_VECTOR_DIM, MAX_METADATA_ENTRIES);
+
+    pub const fn space(max_text_len: usize, vector_dim: usize, max_metadata_entries: usize) -> usize {
+        8 + // discriminator
+            1 + // version
+            32 + // authority
+            8 + // index
+            8 + // timestamp
+            32 + // data_hash
+            32 + // previous_hash
+            8 + // citation_count
+            4 + // model_version
+            8 + // vector_norm
+            2 + // cluster_id
+            1 + // clustered
+            4 + // flag_count
+            1 + // retracted
+            8 + // tips_received
+            20 + // eth_author
+            8 + // superseded_by
+            8 + // supersedes
+            1 + // license
+            BINARY_VECTOR_WORDS * 8 + // binary_vector
+            2 + // attestation_count
+            32 + // rent_payer
+            8 + // reply_to
+            8 + // thread_root
+            4 + // reply_count
+            MAX_KEYWORDS * 8 + // keyword_hashes
+            2 + // namespace_id
+            8 + // pin_weight
+            1 + // pending
+            1 + // approval_count
+            1 + // hash_scheme
+            8 + // stats_bytes
+            1 + 32 + // encryption_pubkey
+            4 + max_text_len + // text
+            4 + vector_dim * 8 + // vector
+            4 + max_metadata_entries * MetadataEntry::MAX_LEN + // metadata
+            4 + MINHASH_SIZE * 4 // minhash
+    }
+
+    // ThreadIndex page the next reply will be recorded in
+    pub fn reply_page(&self) -> u32 {
+        self.reply_count / ThreadIndex::PAGE_SIZE as u32
+    }
+
+    // Whether enough oracles have attested this block
+    pub fn is_finalized(&self, chain_state: &ChainState) -> bool {
+        self.attestation_count >= chain_state.required_attestations as u16
+    }
+}
+
+pub const MINHASH_SIZE: usize = 32;
+
+// Block::hash_scheme values. Blocks migrated from before the field carry
+// HASH_SCHEME_LEGACY; block 0's data_hash is the genesis hash under either.
+#[constant]
+pub const HASH_SCHEME_LEGACY: u8 = 0;
+#[constant]
+pub const HASH_SCHEME_V1: u8 = 1;
+
+// Domain separation tag for block_data_hash
+pub const BLOCK_HASH_DOMAIN: &[u8] = b"span:block:v1";
+
+// 1 bit per dimension: MAX_VECTOR_DIM bits as little-endian u64 words
+pub const BINARY_VECTOR_WORDS: usize = MAX_VECTOR_DIM / 64;
+
+// Fixed-point scale for Block::vector_norm (micro-units)
+pub const VECTOR_NORM_SCALE: f64 = 1_000_000.0;
+
+// Cosine similarity of two vectors given their fixed-point norms; None when
+// the lengths differ or either norm is zero
+pub fn cosine_similarity(a: &[f64], norm_a: u64, b: &[f64], norm_b: u64) -> Option<f64> {
+    if a.len() != b.len() || norm_a == 0 || norm_b == 0 {
+        return None;
+    }
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    Some(dot / ((norm_a as f64 / VECTOR_NORM_SCALE) * (norm_b as f64 / VECTOR_NORM_SCALE)))
+}
+
+// Byte offsets of Block's fixed-size fields (after the 8-byte discriminator)
+#[constant]
+pub const BLOCK_VERSION_OFFSET: usize = 8;
+#[constant]
+pub const BLOCK_AUTHORITY_OFFSET: usize = BLOCK_VERSION_OFFSET + 1;
+#[constant]
+pub const BLOCK_INDEX_OFFSET: usize = BLOCK_AUTHORITY_OFFSET + 32;
+#[constant]
+pub const BLOCK_TIMESTAMP_OFFSET: usize = BLOCK_INDEX_OFFSET + 8;
+#[constant]
+pub const BLOCK_DATA_HASH_OFFSET: usize = BLOCK_TIMESTAMP_OFFSET + 8;
+#[constant]
+pub const BLOCK_PREVIOUS_HASH_OFFSET: usize = BLOCK_DATA_HASH_OFFSET + 32;
+#[constant]
+pub const BLOCK_CITATION_COUNT_OFFSET: usize = BLOCK_PREVIOUS_HASH_OFFSET + 32;
+#[constant]
+pub const BLOCK_MODEL_VERSION_OFFSET: usize = BLOCK_CITATION_COUNT_OFFSET + 8;
+#[constant]
+pub const BLOCK_VECTOR_NORM_OFFSET: usize = BLOCK_MODEL_VERSION_OFFSET + 4;
+#[constant]
+pub const BLOCK_CLUSTER_ID_OFFSET: usize = BLOCK_VECTOR_NORM_OFFSET + 8;
+#[constant]
+pub const BLOCK_CLUSTERED_OFFSET: usize = BLOCK_CLUSTER_ID_OFFSET + 2;
+#[constant]
+pub const BLOCK_FLAG_COUNT_OFFSET: usize = BLOCK_CLUSTERED_OFFSET + 1;
+#[constant]
+pub const BLOCK_RETRACTED_OFFSET: usize = BLOCK_FLAG_COUNT_OFFSET + 4;
+#[constant]
+pub const BLOCK_TIPS_RECEIVED_OFFSET: usize = BLOCK_RETRACTED_OFFSET + 1;
+#[constant]
+pub const BLOCK_ETH_AUTHOR_OFFSET: usize = BLOCK_TIPS_RECEIVED_OFFSET + 8;
+#[constant]
+pub const BLOCK_SUPERSEDED_BY_OFFSET: usize = BLOCK_ETH_AUTHOR_OFFSET + 20;
+#[constant]
+pub const BLOCK_SUPERSEDES_OFFSET: usize = BLOCK_SUPERSEDED_BY_OFFSET + 8;
+#[constant]
+pub const BLOCK_LICENSE_OFFSET: usize = BLOCK_SUPERSEDES_OFFSET + 8;
+#[constant]
+pub const BLOCK_BINARY_VECTOR_OFFSET: usize = BLOCK_LICENSE_OFFSET + 1;
+#[constant]
+pub const BLOCK_ATTESTATION_COUNT_OFFSET: usize = BLOCK_BINARY_VECTOR_OFFSET + BINARY_VECTOR_WORDS * 8;
+#[constant]
+pub const BLOCK_RENT_PAYER_OFFSET: usize = BLOCK_ATTESTATION_COUNT_OFFSET + 2;
+#[constant]
+pub const BLOCK_REPLY_TO_OFFSET: usize = BLOCK_RENT_PAYER_OFFSET + 32;
+#[constant]
+pub const BLOCK_THREAD_ROOT_OFFSET: usize = BLOCK_REPLY_TO_OFFSET + 8;
+#[constant]
+pub const BLOCK_REPLY_COUNT_OFFSET: usize = BLOCK_THREAD_ROOT_OFFSET + 8;
+#[constant]
+pub const BLOCK_KEYWORD_HASHES_OFFSET: usize = BLOCK_REPLY_COUNT_OFFSET + 4;
+#[constant]
+pub const BLOCK_NAMESPACE_ID_OFFSET: usize = BLOCK_KEYWORD_HASHES_OFFSET + MAX_KEYWORDS * 8;
+#[constant]
+pub const BLOCK_PIN_WEIGHT_OFFSET: usize = BLOCK_NAMESPACE_ID_OFFSET + 2;
+#[constant]
+pub const BLOCK_PENDING_OFFSET: usize = BLOCK_PIN_WEIGHT_OFFSET + 8;
+#[constant]
+pub const BLOCK_APPROVAL_COUNT_OFFSET: usize = BLOCK_PENDING_OFFSET + 1;
+#[constant]
+pub const BLOCK_HASH_SCHEME_OFFSET: usize = BLOCK_APPROVAL_COUNT_OFFSET + 1;
+#[constant]
+pub const BLOCK_STATS_BYTES_OFFSET: usize = BLOCK_HASH_SCHEME_OFFSET + 1;
+#[constant]
+pub const BLOCK_ENCRYPTION_PUBKEY_OFFSET: usize = BLOCK_STATS_BYTES_OFFSET + 8;
+
+// Per-writer state on a chain
+#[account]
+pub struct Contributor {
+    pub chain: Pubkey,
+    pub author: Pubkey,
+    pub block_count: u64,
+    pub last_block_slot: u64,
+    pub window_start_slot: u64,
+    pub window_count: u32, // blocks written since window_start_slot
+    pub bytes_written: u64, // stored bytes across all blocks
+    pub quota_epoch_start: i64,
+    pub epoch_bytes: u64, // stored bytes since quota_epoch_start
+    pub quota_override: u64, // replaces the chain's quota_bytes when non-zero; u64::MAX for unlimited
+}
+
+impl Contributor {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32 + // author
+        8 + // block_count
+        8 + // last_block_slot
+        8 + // window_start_slot
+        4 + // window_count
+        8 + // bytes_written
+        8 + // quota_epoch_start
+        8 + // epoch_bytes
+        8; // quota_override
+
+    // Enforce the chain's rate limits for a block written at `slot`
+    pub fn record_block(&mut self, chain_state: &ChainState, slot: u64) -> Result<()> {
+        if self.block_count > 0 {
+            require!(
+                slot >= self.last_block_slot.saturating_add(chain_state.min_slot_gap),
+                NLPChainError::RateLimited
+            );
+        }
+        if chain_state.max_blocks_per_window > 0 {
+            if self.window_count == 0
+                || slot >= self.window_start_slot.saturating_add(chain_state.rate_window_slots)
+            {
+                self.window_start_slot = slot;
+                self.window_count = 0;
+            }
+            require!(
+                self.window_count < chain_state.max_blocks_per_window,
+                NLPChainError::RateLimited
+            );
+            self.window_count += 1;
+        }
+        self.last_block_slot = slot;
+        Ok(())
+    }
+
+    // Enforce the writer's storage quota for `bytes` written at `now`
+    pub fn record_bytes(&mut self, chain_state: &ChainState, bytes: u64, now: i64) -> Result<()> {
+        if chain_state.quota_epoch_seconds > 0
+            && now >= self.quota_epoch_start.saturating_add(chain_state.quota_epoch_seconds)
+        {
+            self.quota_epoch_start = now;
+            self.epoch_bytes = 0;
+        }
+        let quota = if self.quota_override > 0 {
+            self.quota_override
+        } else {
+            chain_state.quota_bytes
+        };
+        let epoch_bytes = self.epoch_bytes.saturating_add(bytes);
+        require!(
+            quota == 0 || epoch_bytes <= quota,
+            NLPChainError::QuotaExceeded
+        );
+        self.epoch_bytes = epoch_bytes;
+        self.bytes_written = self.bytes_written.saturating_add(bytes);
+        Ok(())
+    }
+
+    // AuthorIndex page the next block will be recorded in
+    pub fn page(&self) -> u32 {
+        (self.block_count / AuthorIndex::PAGE_SIZE as u64) as u32
+    }
+}
+
+// One page of a writer's block indexes, in append order
+#[account]
+pub struct AuthorIndex {
+    pub chain: Pubkey,
+    pub author: Pubkey,
+    pub page: u32,
+    pub block_indexes: Vec<u64>,
+}
+
+impl AuthorIndex {
+    pub const PAGE_SIZE: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32 + // author
+        4 + // page
+        4 + 8 * Self::PAGE_SIZE; // block_indexes
+}
+
+// One page of the direct replies to a block, in reply order
+#[account]
+pub struct ThreadIndex {
+    pub chain: Pubkey,
+    pub parent_index: u64,
+    pub page: u32,
+    pub reply_indexes: Vec<u64>,
+}
+
+impl ThreadIndex {
+    pub const PAGE_SIZE: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // parent_index
+        4 + // page
+        4 + 8 * Self::PAGE_SIZE; // reply_indexes
+}
+
+pub const MAX_NAMESPACE_NAME_LEN: usize = 32;
+
+pub const MAX_CHAIN_NAME_LEN: usize = 64;
+pub const MAX_CHAIN_DESCRIPTION_LEN: usize = 256;
+pub const MAX_CHAIN_URI_LEN: usize = 200;
+
+// Human-readable labels for a chain; creation parameters live on ChainState
+#[account]
+pub struct ChainProfile {
+    pub chain: Pubkey,
+    pub name: String,
+    pub description: String,
+    pub content_uri: String, // e.g. where the corpus or its datasheet is published
+    pub updated_at: i64,
+}
+
+impl ChainProfile {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        4 + MAX_CHAIN_NAME_LEN + // name
+        4 + MAX_CHAIN_DESCRIPTION_LEN + // description
+        4 + MAX_CHAIN_URI_LEN + // content_uri
+        8; // updated_at
+}
+
+// Named collection of blocks within a chain (docs, chat, code, ...)
+#[account]
+pub struct Namespace {
+    pub chain: Pubkey,
+    pub id: u16,
+    pub name: String,
+    pub block_count: u64,
+}
+
+impl Namespace {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        2 + // id
+        4 + MAX_NAMESPACE_NAME_LEN + // name
+        8; // block_count
+
+    // NamespaceIndex page the next block will be recorded in
+    pub fn page(&self) -> u32 {
+        (self.block_count / NamespaceIndex::PAGE_SIZE as u64) as u32
+    }
+}
+
+// One page of a namespace's block indexes, in filing order
+#[account]
+pub struct NamespaceIndex {
+    pub chain: Pubkey,
+    pub namespace_id: u16,
+    pub page: u32,
+    pub block_indexes: Vec<u64>,
+}
+
+impl NamespaceIndex {
+    pub const PAGE_SIZE: usize = 64;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        2 + // namespace_id
+        4 + // page
+        4 + 8 * Self::PAGE_SIZE; // block_indexes
+}
+
+// Blocks appended during one `bucket_interval`-long period
+#[account]
+pub struct TimeBucket {
+    pub chain: Pubkey,
+    pub bucket_id: i64, // unix_timestamp / bucket_interval
+    pub first_index: u64,
+    pub last_index: u64,
+    pub count: u64,
+}
+
+impl TimeBucket {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // bucket_id
+        8 + // first_index
+        8 + // last_index
+        8; // count
+}
+
+// Slots between scheduling a validator rotation and it taking effect (~1 day)
+pub const VALIDATOR_ROTATION_DELAY: u64 = 216_000;
+
+#[account]
+pub struct ValidatorSet {
+    pub chain: Pubkey,
+    pub epoch: u64,
+    pub validators: Vec<Pubkey>,
+    pub activation_slot: u64,
+    pub retired_slot: u64, // u64::MAX while current
+}
+
+impl ValidatorSet {
+    pub const MAX_VALIDATORS: usize = 16;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // epoch
+        4 + 32 * Self::MAX_VALIDATORS + // validators
+        8 + // activation_slot
+        8; // retired_slot
+
+    pub fn is_active_at(&self, slot: u64) -> bool {
+        self.activation_slot <= slot && slot < self.retired_slot
+    }
+}
+
+// Embedding dimensions are split into this many contiguous buckets for
+// VectorStats min/max
+pub const VECTOR_STATS_BUCKETS: usize = 16;
+
+// Running statistics over the embeddings of blocks [start_index, next_index)
+#[account]
+pub struct VectorStats {
+    pub chain: Pubkey,
+    pub start_index: u64,
+    pub next_index: u64, // next block accumulate_stats folds in
+    pub count: u64, // blocks folded in; skipped indexes are not counted
+    pub norm_sum: u128, // sum of vector_norm (fixed point, VECTOR_NORM_SCALE)
+    pub min: [f64; VECTOR_STATS_BUCKETS], // smallest component per dimension bucket
+    pub max: [f64; VECTOR_STATS_BUCKETS], // largest component per dimension bucket
+}
+
+impl VectorStats {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // start_index
+        8 + // next_index
+        8 + // count
+        16 + // norm_sum
+        VECTOR_STATS_BUCKETS * 8 + // min
+        VECTOR_STATS_BUCKETS * 8; // max
+
+    pub fn record(&mut self, block: &Block) {
+        self.count += 1;
+        self.norm_sum += block.vector_norm as u128;
+        // Binary-only and sparse-only blocks have no dense components
+        let dim = block.vector.len();
+        for (d, value) in block.vector.iter().enumerate() {
+            let bucket = d * VECTOR_STATS_BUCKETS / dim;
+            self.min[bucket] = self.min[bucket].min(*value);
+            self.max[bucket] = self.max[bucket].max(*value);
+        }
+    }
+
+    pub fn mean_norm(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.norm_sum as f64 / self.count as f64 / VECTOR_NORM_SCALE
+    }
+}
+
+// Round-robin writer rotation for FEATURE_WRITER_EPOCHS
+#[account]
+pub struct WriterCommittee {
+    pub chain: Pubkey,
+    pub writers: Vec<Pubkey>,
+    pub epoch_slots: u64,
+    pub epoch: u64, // writers[epoch % writers.len()] holds the pen
+    pub epoch_start_slot: u64,
+}
+
+impl WriterCommittee {
+    pub const MAX_WRITERS: usize = 16;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        4 + 32 * Self::MAX_WRITERS + // writers
+        8 + // epoch_slots
+        8 + // epoch
+        8; // epoch_start_slot
+
+    // Epoch in effect at `slot`, counting epochs advance_epoch hasn't
+    // recorded yet
+    pub fn effective_epoch(&self, slot: u64) -> u64 {
+        self.epoch + slot.saturating_sub(self.epoch_start_slot) / self.epoch_slots.max(1)
+    }
+
+    pub fn current_writer(&self, slot: u64) -> Option<Pubkey> {
+        if self.writers.is_empty() {
+            return None;
+        }
+        Some(self.writers[(self.effective_epoch(slot) % self.writers.len() as u64) as usize])
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct HashRegistry {
+    pub chain: Pubkey,
+    pub data_hash: Hash,
+    pub block_index: u64,
+    pub compressed: bool, // block_index is a Merkle leaf, not a Block account
+}
+
+impl HashRegistry {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32 + // data_hash
+        8 + // block_index
+        1; // compressed
+}
+
+// Subscription of a target chain to a source chain's new blocks
+#[account]
+pub struct Syndication {
+    pub source_chain: Pubkey,
+    pub target_chain: Pubkey,
+    pub next_source_index: u64, // next source block to copy
+    pub copied_count: u64,
+    pub skipped_count: u64, // source blocks skip_syndicated_block stepped past
+}
+
+impl Syndication {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // source_chain
+        32 + // target_chain
+        8 + // next_source_index
+        8 + // copied_count
+        8; // skipped_count
+}
+
+// Attribution from a syndicated target block to the block it was copied from
+#[account]
+pub struct SyndicationEdge {
+    pub target_chain: Pubkey,
+    pub target_index: u64,
+    pub source_chain: Pubkey,
+    pub source_index: u64,
+    pub source_hash: Hash,
+}
+
+impl SyndicationEdge {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // target_chain
+        8 + // target_index
+        32 + // source_chain
+        8 + // source_index
+        32; // source_hash
+}
+
+// Grants moderation rights on a chain to `moderator`
+#[account]
+pub struct Moderator {
+    pub chain: Pubkey,
+    pub moderator: Pubkey,
+}
+
+impl Moderator {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32; // moderator
+}
+
+pub const MAX_TAG_LEN: usize = 32;
+
+// Signed keyword hints per block
+pub const MAX_KEYWORDS: usize = 8;
+pub const MAX_KEYWORD_LEN: usize = 32;
+
+// A single (tag, block) association
+#[account]
+pub struct TagEntry {
+    pub chain: Pubkey,
+    pub tag_hash: Hash, // hash of the normalized tag
+    pub block_index: u64,
+    pub tagged_by: Pubkey,
+}
+
+impl TagEntry {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32 + // tag_hash
+        8 + // block_index
+        32; // tagged_by
+}
+
+// Number of blocks carrying a tag
+#[account]
+pub struct TagCounter {
+    pub chain: Pubkey,
+    pub tag_hash: Hash,
+    pub tag: String,
+    pub count: u64,
+}
+
+impl TagCounter {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32 + // tag_hash
+        4 + MAX_TAG_LEN + // tag
+        8; // count
+}
+
+// Edge in the citation graph: `source_index` cites `target_index`
+#[account]
+pub struct Citation {
+    pub chain: Pubkey,
+    pub source_index: u64,
+    pub target_index: u64,
+    pub timestamp: i64,
+}
+
+impl Citation {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // source_index
+        8 + // target_index
+        8; // timestamp
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    DuplicateOf,
+    Contradicts,
+    Elaborates,
+}
+
+// Typed, scored edge in the knowledge graph between two blocks
+#[account]
+pub struct SemanticLink {
+    pub chain: Pubkey,
+    pub from_index: u64,
+    pub to_index: u64,
+    pub kind: LinkKind,
+    pub score_bps: u16, // similarity, 0-10000
+    pub creator: Pubkey,
+    pub timestamp: i64,
+}
+
+impl SemanticLink {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // from_index
+        8 + // to_index
+        1 + // kind
+        2 + // score_bps
+        32 + // creator
+        8; // timestamp
+}
+
+// A reader's access to an encrypted block: the content key wrapped to them
+#[account]
+pub struct AccessGrant {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub reader: Pubkey,
+    pub wrapped_key: Vec<u8>,
+    pub granted_at: i64,
+}
+
+impl AccessGrant {
+    pub const MAX_WRAPPED_KEY_LEN: usize = 128;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // reader
+        4 + Self::MAX_WRAPPED_KEY_LEN + // wrapped_key
+        8; // granted_at
+}
+
+// Full contents of a compressed block, logged via noop; its hash is the leaf
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct CompressedBlock {
+    pub chain: Pubkey,
+    pub leaf_index: u64,
+    pub authority: Pubkey,
+    pub index: u64,
+    pub timestamp: i64,
+    pub data_hash: Hash,
+    pub hash_scheme: u8,
+    pub previous_hash: Hash,
+    pub model_version: u32,
+    pub text: String,
+    pub vector: Vec<f64>,
+    pub metadata: Vec<MetadataEntry>,
+}
+
+pub const BLOOM_FILTER_BYTES: usize = 8192;
+pub const BLOOM_FILTER_BITS: u32 = (BLOOM_FILTER_BYTES * 8) as u32;
+pub const BLOOM_FILTER_HASHES: usize = 4;
+
+// Per-chain Bloom filter over registered data hashes. Zero-copy so add_block
+// doesn't deserialize 8 KB on every write.
+#[account(zero_copy)]
+pub struct BloomFilter {
+    pub chain: Pubkey,
+    pub item_count: u64,
+    pub bits: [u8; BLOOM_FILTER_BYTES],
+}
+
+impl BloomFilter {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // item_count
+        BLOOM_FILTER_BYTES; // bits
+
+    // data_hash is already uniform, so its 32-bit words serve as the k hashes
+    fn bit_positions(data_hash: &[u8; 32]) -> [u32; BLOOM_FILTER_HASHES] {
+        let mut positions = [0u32; BLOOM_FILTER_HASHES];
+        for (i, position) in positions.iter_mut().enumerate() {
+            let word = u32::from_le_bytes(data_hash[4 * i..4 * i + 4].try_into().unwrap());
+            *position = word % BLOOM_FILTER_BITS;
+        }
+        positions
+    }
+
+    pub fn insert(&mut self, data_hash: &[u8; 32]) {
+        for bit in Self::bit_positions(data_hash) {
+            self.bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+        self.item_count += 1;
+    }
+
+    pub fn probably_contains(&self, data_hash: &[u8; 32]) -> bool {
+        Self::bit_positions(data_hash)
+            .iter()
+            .all(|bit| self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+}
+
+// IVF centroid: coarse cluster center for retrieval
+#[account]
+pub struct Centroid {
+    pub chain: Pubkey,
+    pub centroid_id: u16,
+    pub norm: u64, // VECTOR_NORM_SCALE fixed point
+    pub member_count: u64,
+    pub vector: Vec<f64>,
+}
+
+impl Centroid {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        2 + // centroid_id
+        8 + // norm
+        8 + // member_count
+        4 + MAX_VECTOR_DIM * 8; // vector
+
+    // Cosine similarity to a block's vector; -inf when undefined
+    fn similarity(&self, block: &Block) -> f64 {
+        if self.norm == 0 || block.vector_norm == 0 || self.vector.len() != block.vector.len() {
+            return f64::NEG_INFINITY;
+        }
+        let dot: f64 = self.vector.iter().zip(&block.vector).map(|(a, b)| a * b).sum();
+        dot / ((self.norm as f64 / VECTOR_NORM_SCALE) * (block.vector_norm as f64 / VECTOR_NORM_SCALE))
+    }
+}
+
+pub const MAX_REASON_LEN: usize = 200;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationTask {
+    Label,
+    Summarize,
+    Translate,
+    FactCheck,
+    Other,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BountyStatus {
+    Open,
+    Approved, // `annotation` was accepted and can be claimed
+}
+
+// Tokens escrowed for an annotation of a block, held by the bounty-vault PDA
+#[account]
+pub struct Bounty {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub poster: Pubkey,
+    pub task_kind: AnnotationTask,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub status: BountyStatus,
+    pub annotation: Pubkey, // default until an annotation is approved
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Bounty {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // poster
+        1 + // task_kind
+        32 + // mint
+        8 + // amount
+        1 + // status
+        32 + // annotation
+        8 + // created_at
+        1; // bump
+}
+
+pub const MAX_ANNOTATION_URI_LEN: usize = 200;
+
+// An annotator's submission for a bounty
+#[account]
+pub struct Annotation {
+    pub bounty: Pubkey,
+    pub annotator: Pubkey,
+    pub content_hash: [u8; 32], // hash of the annotation at `uri`
+    pub uri: String,
+    pub submitted_at: i64,
+}
+
+impl Annotation {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // bounty
+        32 + // annotator
+        32 + // content_hash
+        4 + MAX_ANNOTATION_URI_LEN + // uri
+        8; // submitted_at
+}
+
+// Longest a pin can lock tokens for
+pub const MAX_PIN_DURATION: i64 = 365 * 24 * 60 * 60;
+
+// Tokens locked against a block until `unlock_at`, held by the pin-vault PDA
+#[account]
+pub struct Pin {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub owner: Pubkey,
+    pub amount: u64, // counted in the block's pin_weight until unpinned
+    pub unlock_at: i64,
+    pub bump: u8,
+}
+
+impl Pin {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // owner
+        8 + // amount
+        8 + // unlock_at
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagStatus {
+    Open,
+    Upheld,
+    Dismissed,
+}
+
+// A user's report against a block
+#[account]
+pub struct Flag {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub flagger: Pubkey,
+    pub reason: String,
+    pub timestamp: i64,
+    pub status: FlagStatus,
+    pub reviewer: Pubkey, // default until reviewed
+}
+
+impl Flag {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // flagger
+        4 + MAX_REASON_LEN + // reason
+        8 + // timestamp
+        1 + // status
+        32; // reviewer
+}
+
+// Audit record of a retraction
+#[account]
+pub struct Retraction {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub data_hash: Hash, // hash of the retracted content
+    pub moderator: Pubkey,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+impl Retraction {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // data_hash
+        32 + // moderator
+        4 + MAX_REASON_LEN + // reason
+        8; // timestamp
+}
+
+// Sealed chain head, used as the trust anchor for archives
+#[account]
+pub struct Checkpoint {
+    pub chain: Pubkey,
+    pub index: u64,
+    pub block_count: u64, // covers blocks [0, block_count)
+    pub last_hash: Hash,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+impl Checkpoint {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // index
+        8 + // block_count
+        32 + // last_hash
+        8 + // slot
+        8; // timestamp
+}
+
+// Compact summary of an archived block range
+#[account]
+pub struct Archive {
+    pub chain: Pubkey,
+    pub start_index: u64,
+    pub end_index: u64, // exclusive
+    pub count: u64,
+    pub merkle_root: Hash, // over the blocks' data hashes, in index order
+    pub first_previous_hash: Hash,
+    pub last_hash: Hash,
+    pub checkpoint_index: u64,
+}
+
+impl Archive {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // start_index
+        8 + // end_index
+        8 + // count
+        32 + // merkle_root
+        32 + // first_previous_hash
+        32 + // last_hash
+        8; // checkpoint_index
+}
+
+// Summary of blocks [start_index, end_index). Summaries over nested ranges
+// form a hierarchy, e.g. per-day summaries under a per-month one.
+#[account]
+pub struct SummaryBlock {
+    pub chain: Pubkey,
+    pub start_index: u64,
+    pub end_index: u64, // exclusive
+    pub range_hash: Hash, // see range_hash
+    pub first_previous_hash: Hash, // previous_hash of the first open block in the range
+    pub last_hash: Hash, // data_hash of the last open block in the range
+    pub authority: Pubkey,
+    pub timestamp: i64,
+    pub model_version: u32,
+    pub vector_norm: u64, // scaled by VECTOR_NORM_SCALE
+    pub text: String,
+    pub vector: Vec<f64>,
+}
+
+impl SummaryBlock {
+    pub const fn space(max_text_len: usize, vector_dim: usize) -> usize {
+        8 + // discriminator
+            32 + // chain
+            8 + // start_index
+            8 + // end_index
+            32 + // range_hash
+            32 + // first_previous_hash
+            32 + // last_hash
+            32 + // authority
+            8 + // timestamp
+            4 + // model_version
+            8 + // vector_norm
+            4 + max_text_len + // text
+            4 + vector_dim * 8 // vector
+    }
+}
+
+// Per-sentence embeddings of a block
+#[account]
+pub struct SubVectors {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub dim: u16,
+    pub sentence_offsets: Vec<u32>, // byte offset in the block text of each sentence
+    pub norms: Vec<u64>, // scaled by VECTOR_NORM_SCALE
+    pub vectors: Vec<f64>, // sentence_offsets.len() vectors of `dim` values, concatenated
+}
+
+impl SubVectors {
+    pub const fn space(max_sub_vectors: usize, vector_dim: usize) -> usize {
+        8 + // discriminator
+            32 + // chain
+            8 + // block_index
+            2 + // dim
+            4 + max_sub_vectors * 4 + // sentence_offsets
+            4 + max_sub_vectors * 8 + // norms
+            4 + max_sub_vectors * vector_dim * 8 // vectors
+    }
+
+    // Sub-vector `i` and its norm
+    pub fn get(&self, i: usize) -> Option<(&[f64], u64)> {
+        let dim = self.dim as usize;
+        let vector = self.vectors.get(i * dim..(i + 1) * dim)?;
+        Some((vector, *self.norms.get(i)?))
+    }
+}
+
+pub const MAX_SPARSE_NNZ: usize = 256;
+
+// Sparse embedding of a block: (index, value) pairs sorted by index
+#[account]
+pub struct SparseVector {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub indices: Vec<u32>,
+    pub values: Vec<f64>,
+}
+
+impl SparseVector {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        4 + MAX_SPARSE_NNZ * 4 + // indices
+        4 + MAX_SPARSE_NNZ * 8; // values
+
+    // Dot product by merging the two sorted index lists
+    pub fn dot(&self, other: &SparseVector) -> f64 {
+        let (mut i, mut j, mut sum) = (0, 0, 0.0);
+        while i < self.indices.len() && j < other.indices.len() {
+            match self.indices[i].cmp(&other.indices[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    sum += self.values[i] * other.values[j];
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        sum
+    }
+}
+
+// Time-limited subscription to a chain's encrypted content
+#[account]
+pub struct ReaderPass {
+    pub chain: Pubkey,
+    pub reader: Pubkey,
+    pub expires_at: i64,
+}
+
+impl ReaderPass {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32 + // reader
+        8; // expires_at
+}
+
+pub const STATS_DAYS: usize = 32;
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+// Running chain totals for dashboards. Zero-copy with a fixed layout so
+// clients can read fields at known offsets.
+#[account(zero_copy)]
+pub struct ChainStats {
+    pub chain: Pubkey,
+    pub total_bytes: u64, // text + vector + metadata of live blocks
+    pub live_blocks: u64,
+    pub distinct_authors: u64,
+    pub norm_sum: u64, // sum of live blocks' vector_norm
+    pub current_day: i64, // unix day of the newest daily_counts slot
+    pub daily_counts: [u32; STATS_DAYS], // blocks per day, indexed by day % STATS_DAYS
+}
+
+impl ChainStats {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // total_bytes
+        8 + // live_blocks
+        8 + // distinct_authors
+        8 + // norm_sum
+        8 + // current_day
+        4 * STATS_DAYS; // daily_counts
+
+    pub fn record_block(&mut self, bytes: u64, vector_norm: u64, new_author: bool, timestamp: i64) {
+        let day = timestamp / SECONDS_PER_DAY;
+        if day > self.current_day {
+            // Clear the slots of days that passed without blocks
+            let elapsed = (day - self.current_day).min(STATS_DAYS as i64);
+            for d in 0..elapsed {
+                self.daily_counts[((day - d) as usize) % STATS_DAYS] = 0;
+            }
+            self.current_day = day;
+        }
+        self.daily_counts[(day as usize) % STATS_DAYS] += 1;
+        self.total_bytes += bytes;
+        self.live_blocks += 1;
+        self.norm_sum += vector_norm;
+        if new_author {
+            self.distinct_authors += 1;
+        }
+    }
+
+    pub fn remove_block(&mut self, bytes: u64, vector_norm: u64) {
+        self.total_bytes = self.total_bytes.saturating_sub(bytes);
+        self.live_blocks = self.live_blocks.saturating_sub(1);
+        self.norm_sum = self.norm_sum.saturating_sub(vector_norm);
+    }
+
+    // Mean vector norm of live blocks, VECTOR_NORM_SCALE fixed point
+    pub fn average_norm(&self) -> u64 {
+        self.norm_sum.checked_div(self.live_blocks).unwrap_or(0)
+    }
+}
+
+// A fixed-size field inserted into an account layout. Migrating from an
+// older version shifts the bytes at `offset` right by `len` and zero-fills
+// the gap, which decodes as the field's default value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutChange {
+    pub version: u8, // first version with the field
+    pub offset: usize,
+    pub len: usize,
+}
+
+#[constant]
+pub const BLOCK_VERSION: u8 = 14;
+#[constant]
+pub const CHAIN_STATE_VERSION: u8 = 19;
+pub const CHAIN_STATE_VERSION_OFFSET: usize = 8;
+pub const CHAIN_STATE_AUTHORITY_OFFSET: usize = CHAIN_STATE_VERSION_OFFSET + 1;
+
+// Layout changes since version 1, in version order
+pub const BLOCK_LAYOUT_CHANGES: &[LayoutChange] = &[
+    LayoutChange {
+        version: 2,
+        offset: BLOCK_ETH_AUTHOR_OFFSET,
+        len: 20,
+    },
+    LayoutChange {
+        version: 3,
+        offset: BLOCK_SUPERSEDED_BY_OFFSET,
+        len: 16,
+    },
+    LayoutChange {
+        version: 4,
+        offset: BLOCK_LICENSE_OFFSET,
+        len: 1,
+    },
+    LayoutChange {
+        version: 5,
+        offset: BLOCK_BINARY_VECTOR_OFFSET,
+        len: BINARY_VECTOR_WORDS * 8,
+    },
+    LayoutChange {
+        version: 6,
+        offset: BLOCK_ATTESTATION_COUNT_OFFSET,
+        len: 2,
+    },
+    LayoutChange {
+        version: 7,
+        offset: BLOCK_RENT_PAYER_OFFSET,
+        len: 32,
+    },
+    LayoutChange {
+        version: 8,
+        offset: BLOCK_REPLY_TO_OFFSET,
+        len: 20,
+    },
+    LayoutChange {
+        version: 9,
+        offset: BLOCK_KEYWORD_HASHES_OFFSET,
+        len: MAX_KEYWORDS * 8,
+    },
+    LayoutChange {
+        version: 10,
+        offset: BLOCK_NAMESPACE_ID_OFFSET,
+        len: 2,
+    },
+    LayoutChange {
+        version: 11,
+        offset: BLOCK_PIN_WEIGHT_OFFSET,
+        len: 8,
+    },
+    LayoutChange {
+        version: 12,
+        offset: BLOCK_PENDING_OFFSET,
+        len: 2,
+    },
+    LayoutChange {
+        version: 13,
+        offset: BLOCK_HASH_SCHEME_OFFSET,
+        len: 1,
+    },
+    LayoutChange {
+        version: 14,
+        offset: BLOCK_STATS_BYTES_OFFSET,
+        len: 8,
+    },
+];
+// ChainState fields are appended after the variable-length model_id, so its
+// migrations only grow the account
+pub const CHAIN_STATE_LAYOUT_CHANGES: &[LayoutChange] = &[];
+
+pub mod wormhole {
+    use super::*;
+    declare_id!("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth");
+}
+pub const WORMHOLE_PROGRAM_ID: Pubkey = wormhole::ID;
+// Core bridge PostMessage instruction and finalized consistency level
+const WORMHOLE_POST_MESSAGE: u8 = 1;
+const WORMHOLE_FINALIZED: u8 = 1;
+// Offset of the message fee in the bridge config account
+const WORMHOLE_BRIDGE_FEE_OFFSET: usize = 16;
+
+pub mod bubblegum {
+    use super::*;
+    declare_id!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+}
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey = bubblegum::ID;
+// Anchor discriminator of Bubblegum's mint_v1
+const BUBBLEGUM_MINT_V1: [u8; 8] = [145, 98, 192, 118, 184, 147, 118, 104];
+// Offset of num_minted in Bubblegum's TreeConfig account
+const BUBBLEGUM_NUM_MINTED_OFFSET: usize = 80;
+pub const BLOCK_NFT_SYMBOL: &str = "SPAN";
+
+// Bubblegum's MetadataArgs, borsh-compatible for the fields span sets
+#[derive(AnchorSerialize)]
+struct BubblegumMetadata {
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+    edition_nonce: Option<u8>,
+    token_standard: Option<u8>, // TokenStandard::NonFungible = 0
+    collection: Option<(bool, Pubkey)>,
+    uses: Option<(u8, u64, u64)>,
+    token_program_version: u8, // TokenProgramVersion::Original = 0
+    creators: Vec<(Pubkey, bool, u8)>, // (address, verified, share)
+}
+
+// Wormhole payload published for every replicated block
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReplicationMessage {
+    pub index: u64,
+    pub data_hash: [u8; 32],
+    pub vector_hash: [u8; 32],
+}
+
+// ChainState.features flags
+#[constant]
+pub const FEATURE_ALLOWLIST_ONLY: u64 = 1 << 0; // only WriterPermit holders can add blocks
+#[constant]
+pub const FEATURE_IMMUTABLE_VECTORS: u64 = 1 << 1; // update_vector is disabled
+#[constant]
+pub const FEATURE_IMMUTABLE_METADATA: u64 = 1 << 2; // set_metadata_entry is disabled
+#[constant]
+pub const FEATURE_FEES: u64 = 1 << 3; // add_block charges the storage fee
+#[constant]
+pub const FEATURE_BINARY_ONLY: u64 = 1 << 4; // blocks store binary_vector but not the dense vector
+#[constant]
+pub const FEATURE_BLOCK_NFTS: u64 = 1 << 5; // mint_block_nft is enabled
+#[constant]
+pub const FEATURE_SPARSE_ONLY: u64 = 1 << 6; // blocks carry a SparseVector instead of a dense vector
+#[constant]
+pub const FEATURE_WRITER_EPOCHS: u64 = 1 << 7; // only the WriterCommittee's current writer can add blocks
+pub const FEATURE_ALL: u64 = FEATURE_ALLOWLIST_ONLY
+    | FEATURE_IMMUTABLE_VECTORS
+    | FEATURE_IMMUTABLE_METADATA
+    | FEATURE_FEES
+    | FEATURE_BINARY_ONLY
+    | FEATURE_BLOCK_NFTS
+    | FEATURE_SPARSE_ONLY
+    | FEATURE_WRITER_EPOCHS;
+
+// Allows `writer` to add blocks on an allowlist-only chain
+#[account]
+pub struct WriterPermit {
+    pub chain: Pubkey,
+    pub writer: Pubkey,
+}
+
+impl WriterPermit {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32; // writer
+}
+
+// Delay between guardians reaching the threshold and the recovery executing,
+// giving a live authority time to cancel
+#[constant]
+pub const RECOVERY_TIMELOCK: i64 = 7 * 86_400;
+
+// Keys allowed to recover the chain authority, M-of-N
+#[account]
+pub struct GuardianSet {
+    pub chain: Pubkey,
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+impl GuardianSet {
+    pub const MAX_GUARDIANS: usize = 10;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        4 + Self::MAX_GUARDIANS * 32 + // guardians
+        1; // threshold
+}
+
+// Pending authority replacement
+#[account]
+pub struct RecoveryProposal {
+    pub chain: Pubkey,
+    pub new_authority: Pubkey,
+    pub approvals: Vec<Pubkey>,
+    pub executable_at: i64, // 0 until the threshold is met
+    pub recovery_nonce: u64, // ChainState.recovery_nonce when proposed; stale proposals lapse
+}
+
+impl RecoveryProposal {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32 + // new_authority
+        4 + GuardianSet::MAX_GUARDIANS * 32 + // approvals
+        8 + // executable_at
+        8; // recovery_nonce
+
+    fn start_timelock_if_approved(&mut self, threshold: u8) -> Result<()> {
+        if self.executable_at == 0 && self.approvals.len() >= threshold as usize {
+            self.executable_at = Clock::get()?.unix_timestamp + RECOVERY_TIMELOCK;
+        }
+        Ok(())
+    }
+}
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+// Delay before a new treasury split applies
+#[constant]
+pub const TREASURY_TIMELOCK: i64 = 2 * 86_400;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TreasuryShare {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+impl TreasuryShare {
+    pub const LEN: usize = 32 + 2;
+
+    fn portion(&self, amount: u64) -> u64 {
+        (amount as u128 * self.bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+}
+
+// Chain treasury: collects storage fees and pass sales, paid out by share
+#[account]
+pub struct Treasury {
+    pub chain: Pubkey,
+    pub shares: Vec<TreasuryShare>, // bps sum to BPS_DENOMINATOR
+    pub pending_shares: Vec<TreasuryShare>,
+    pub pending_effective_at: i64, // 0 when nothing is pending
+}
+
+impl Treasury {
+    pub const MAX_SHARES: usize = 8;
+
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        4 + Self::MAX_SHARES * TreasuryShare::LEN + // shares
+        4 + Self::MAX_SHARES * TreasuryShare::LEN + // pending_shares
+        8; // pending_effective_at
+
+    fn activate_pending_shares(&mut self) -> Result<()> {
+        if self.pending_effective_at > 0 && Clock::get()?.unix_timestamp >= self.pending_effective_at {
+            self.shares = std::mem::take(&mut self.pending_shares);
+            self.pending_effective_at = 0;
+        }
+        Ok(())
+    }
+}
+
+// Grants attestation rights on a chain to `oracle`
+#[account]
+pub struct Oracle {
+    pub chain: Pubkey,
+    pub oracle: Pubkey,
+}
+
+impl Oracle {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32; // oracle
+}
+
+pub const MAX_CLAIM_LEN: usize = 32;
+
+// A key allowed to approve pending blocks on a curated chain
+#[account]
+pub struct Approver {
+    pub chain: Pubkey,
+    pub approver: Pubkey,
+}
+
+impl Approver {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        32; // approver
+}
+
+// One approver's sign-off on a pending block
+#[account]
+pub struct Approval {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub approver: Pubkey,
+    pub timestamp: i64,
+}
+
+impl Approval {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // approver
+        8; // timestamp
+}
+
+// An oracle's statement about a block
+#[account]
+pub struct Attestation {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub data_hash: Hash, // block content the oracle checked
+    pub oracle: Pubkey,
+    pub claim: String, // e.g. "toxicity-checked", "embedding-verified"
+    pub timestamp: i64,
+}
+
+impl Attestation {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // data_hash
+        32 + // oracle
+        4 + MAX_CLAIM_LEN + // claim
+        8; // timestamp
+}
+
+// Receipt for a block's compressed NFT; one per block
+#[account]
+pub struct BlockNft {
+    pub chain: Pubkey,
+    pub block_index: u64,
+    pub tree: Pubkey,
+    pub leaf_index: u64, // Bubblegum nonce of the minted leaf
+}
+
+impl BlockNft {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // chain
+        8 + // block_index
+        32 + // tree
+        8; // leaf_index
+}
+
+#[error_code]
+pub enum NLPChainError {
+    #[msg("Only the authority can update block data")]
+    UnauthorizedUpdate,
+    #[msg("Chain head has moved since the block was prepared")]
+    StaleChainHead,
+    #[msg("Validator set is empty, too large, or not the current set")]
+    InvalidValidatorSet,
+    #[msg("Invalid chain configuration")]
+    InvalidChainConfig,
+    #[msg("Tag must be 1-32 bytes of lowercase letters, digits, '-' or '_'")]
+    InvalidTag,
+    #[msg("A block cannot cite itself")]
+    InvalidCitation,
+    #[msg("Link must join two distinct blocks with a score of at most 10000 bps")]
+    InvalidLink,
+    #[msg("Embedding model or dimension does not match the chain")]
+    ModelMismatch,
+    #[msg("Missing or invalid ed25519 signature from the chain's embedder")]
+    InvalidEmbedderSignature,
+    #[msg("Block is not encrypted")]
+    NotEncrypted,
+    #[msg("Wrapped key must be 1-128 bytes")]
+    InvalidWrappedKey,
+    #[msg("Metadata exceeds entry count or size limits, or repeats a key")]
+    InvalidMetadata,
+    #[msg("Merkle tree is not the chain's compressed tree")]
+    CompressedTreeMismatch,
+    #[msg("Bloom filter is missing, or the chain already has blocks")]
+    BloomFilterUnavailable,
+    #[msg("MinHash signature must be empty or exactly 32 values")]
+    InvalidMinHash,
+    #[msg("Centroid accounts are missing, out of order, or from another chain")]
+    InvalidCentroid,
+    #[msg("Reason must be 1-200 bytes")]
+    InvalidReason,
+    #[msg("Flag has already been reviewed")]
+    FlagAlreadyReviewed,
+    #[msg("Block has already been retracted")]
+    AlreadyRetracted,
+    #[msg("Tip must be non-zero and pay in either lamports or a matching token pair")]
+    InvalidTip,
+    #[msg("Writer has exceeded the chain's rate limit")]
+    RateLimited,
+    #[msg("Chain charges a storage fee; the treasury account is required")]
+    TreasuryRequired,
+    #[msg("Block range does not match the chain or checkpoint")]
+    InvalidArchiveRange,
+    #[msg("Reader needs a valid, unexpired reader pass")]
+    ReaderPassRequired,
+    #[msg("Invalid reader pass purchase")]
+    InvalidReaderPass,
+    #[msg("Chain requires a recent, unspent proof of work from the writer")]
+    ProofOfWorkRequired,
+    #[msg("Chain stats account is missing or cannot be enabled")]
+    ChainStatsUnavailable,
+    #[msg("Account is not a migratable layout")]
+    InvalidAccountVersion,
+    #[msg("Account is already at the current layout version")]
+    AccountAlreadyCurrent,
+    #[msg("Chain replicates over Wormhole; the bridge accounts are required")]
+    WormholeAccountsRequired,
+    #[msg("Missing or invalid secp256k1 signature from the Ethereum author")]
+    InvalidSecp256k1Signature,
+    #[msg("This operation is disabled by the chain's feature flags")]
+    FeatureDisabled,
+    #[msg("Writer is not on the chain's allowlist")]
+    WriterNotAllowed,
+    #[msg("Blocks cannot be superseded in this order")]
+    InvalidSupersede,
+    #[msg("Guardian threshold must be between 1 and the number of guardians (max 10)")]
+    InvalidGuardianSet,
+    #[msg("Signer is not a guardian of this chain")]
+    NotAGuardian,
+    #[msg("Invalid recovery approval")]
+    InvalidRecovery,
+    #[msg("Recovery has not reached its threshold or timelock")]
+    RecoveryTimelocked,
+    #[msg("License is not allowed on this chain")]
+    LicenseNotAllowed,
+    #[msg("Treasury shares must name 1-8 recipients totalling 10000 bps")]
+    InvalidTreasuryShares,
+    #[msg("Text or metadata exceeds the chain's configured limits")]
+    ContentTooLarge,
+    #[msg("Claim must be 1-32 bytes")]
+    InvalidClaim,
+    #[msg("Block does not have the chain's required attestations yet")]
+    BlockNotFinalized,
+    #[msg("No checkpoint is due; only the chain authority may checkpoint now")]
+    CheckpointNotDue,
+    #[msg("Block has not expired, or is not covered by the checkpoint")]
+    BlockNotExpired,
+    #[msg("A block can reply once, to an earlier non-genesis block")]
+    InvalidReply,
+    #[msg("Keywords must number at most 8, each 1-32 bytes")]
+    InvalidKeyword,
+    #[msg("Keywords need a chain embedder to sign them")]
+    KeywordsRequireEmbedder,
+    #[msg("Sub-vectors must match the chain's dimension and limit, with increasing in-text offsets")]
+    InvalidSubVectors,
+    #[msg("Sparse vector must have 1-256 sorted, in-vocabulary indices with finite values")]
+    InvalidSparseVector,
+    #[msg("Namespace names are 1-32 bytes, and a block is filed under one namespace")]
+    InvalidNamespace,
+    #[msg("Bounty amount must be positive and annotation URIs at most 200 bytes")]
+    InvalidBounty,
+    #[msg("Bounty is not in the right state for this operation")]
+    BountyClosed,
+    #[msg("Pinning is not enabled on this chain")]
+    PinningDisabled,
+    #[msg("Pins need a positive amount of the chain's pin mint and a duration of at most a year")]
+    InvalidPin,
+    #[msg("Pin has not expired yet")]
+    PinLocked,
+    #[msg("Block is not awaiting approval")]
+    BlockNotPending,
+    #[msg("Chain name, description or content URI is too long")]
+    InvalidChainMetadata,
+    #[msg("Writer committees need 1 to 16 writers and a non-zero epoch length")]
+    InvalidWriterCommittee,
+    #[msg("Signer is not this epoch's writer")]
+    NotEpochWriter,
+    #[msg("Current writer epoch has not ended")]
+    EpochNotOver,
+    #[msg("Contributor has used up their storage quota for this epoch")]
+    QuotaExceeded,
+    #[msg("Too many blocks since the latest checkpoint; run create_checkpoint first")]
+    CheckpointRequired,
+    #[msg("Missing or invalid ed25519 signature from the validator")]
+    InvalidAttestationSignature,
+    #[msg("Source block can still be synced")]
+    BlockNotSkippable,
+    #[msg("The block's hash registry entry must be closed with it")]
+    HashRegistryRequired,
+    #[msg("Block is awaiting approval or hasn't been linked into the chain yet")]
+    BlockPending,
+    #[msg("The chain's pin mint is already set")]
+    PinMintAlreadySet,
+}
+
+// Helper to enforce metadata limits: entry count, key/value sizes, unique keys
+fn validate_metadata(entries: &[MetadataEntry]) -> Result<()> {
+    require!(
+        entries.len() <= MAX_METADATA_ENTRIES,
+        NLPChainError::InvalidMetadata
+    );
+    for (i, entry) in entries.iter().enumerate() {
+        require!(
+            !entry.key.is_empty() && entry.key.len() <= MAX_METADATA_KEY_LEN,
+            NLPChainError::InvalidMetadata
+        );
+        if let MetadataValue::Str(value) = &entry.value {
+            require!(
+                value.len() <= MAX_METADATA_STR_LEN,
+                NLPChainError::InvalidMetadata
+            );
+        }
+        require!(
+            entries[..i].iter().all(|other| other.key != entry.key),
+            NLPChainError::InvalidMetadata
+        );
+    }
+    Ok(())
+}
+
+// Helper to canonicalize a tag: trimmed, lowercased, restricted charset
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim().to_lowercase();
+    require!(
+        !tag.is_empty()
+            && tag.len() <= MAX_TAG_LEN
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        NLPChainError::InvalidTag
+    );
+    Ok(tag)
+}
+
+// Seed component for a tag. Invalid tags map to a hash no valid tag produces,
+// so the handler reports InvalidTag rather than a seeds mismatch.
+fn tag_seed(tag: &str) -> [u8; 32] {
+    match normalize_tag(tag) {
+        Ok(tag) => hash(tag.as_bytes()).to_bytes(),
+        Err(_) => [0; 32],
+    }
+}
+
+// Hash of a vector's little-endian f64 bytes, as signed by the embedder
+pub fn vector_hash(vector: &[f64]) -> Hash {
+    let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+    hash(&bytes)
+}
+
+// Message the embedder signs: data_hash || vector_hash, followed by
+// sha256(keyword_hashes as little-endian u64s) when the block has keywords
+pub fn embedder_message(data_hash: &Hash, vector: &[f64], keyword_hashes: &[u64; MAX_KEYWORDS]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(96);
+    message.extend_from_slice(data_hash.as_ref());
+    message.extend_from_slice(vector_hash(vector).as_ref());
+    if keyword_hashes.iter().any(|k| *k != 0) {
+        let bytes: Vec<u8> = keyword_hashes.iter().flat_map(|k| k.to_le_bytes()).collect();
+        message.extend_from_slice(hash(&bytes).as_ref());
+    }
+    message
+}
+
+pub const ATTESTATION_TAG: &[u8] = b"span:attestation:v1";
+
+// Message a validator signs to attest `data_hash` on `chain` at `slot`:
+// ATTESTATION_TAG || chain || slot (little-endian u64) || data_hash.
+// The chain key keeps a signature from being replayed on another chain.
+pub fn attestation_message(chain: &Pubkey, slot: u64, data_hash: &Hash) -> Vec<u8> {
+    let mut message = Vec::with_capacity(ATTESTATION_TAG.len() + 72);
+    message.extend_from_slice(ATTESTATION_TAG);
+    message.extend_from_slice(chain.as_ref());
+    message.extend_from_slice(&slot.to_le_bytes());
+    message.extend_from_slice(data_hash.as_ref());
+    message
+}
+
+pub const SUB_VECTORS_TAG: &[u8] = b"span:sub-vectors:v1";
+
+// Message the embedder signs for a block's sentence embeddings:
+// SUB_VECTORS_TAG || data_hash || sha256(sentence_offsets as little-endian
+// u32s) || vector_hash(vectors, concatenated). The tag keeps it distinct
+// from embedder_message.
+pub fn sub_vectors_message(data_hash: &Hash, sentence_offsets: &[u32], vectors: &[f64]) -> Vec<u8> {
+    let offsets: Vec<u8> = sentence_offsets.iter().flat_map(|o| o.to_le_bytes()).collect();
+    let mut message = Vec::with_capacity(SUB_VECTORS_TAG.len() + 96);
+    message.extend_from_slice(SUB_VECTORS_TAG);
+    message.extend_from_slice(data_hash.as_ref());
+    message.extend_from_slice(hash(&offsets).as_ref());
+    message.extend_from_slice(vector_hash(vectors).as_ref());
+    message
+}
+
+// Hash of a sparse vector: sha256(indices as little-endian u32s || values as
+// little-endian f64s)
+pub fn sparse_vector_hash(indices: &[u32], values: &[f64]) -> Hash {
+    let indices: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let values: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    hashv(&[&indices, &values])
+}
+
+pub const SPARSE_VECTOR_TAG: &[u8] = b"span:sparse-vector:v1";
+
+// Message the embedder signs for a block's sparse embedding:
+// SPARSE_VECTOR_TAG || data_hash || sparse_vector_hash
+pub fn sparse_vector_message(data_hash: &Hash, indices: &[u32], values: &[f64]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(SPARSE_VECTOR_TAG.len() + 64);
+    message.extend_from_slice(SPARSE_VECTOR_TAG);
+    message.extend_from_slice(data_hash.as_ref());
+    message.extend_from_slice(sparse_vector_hash(indices, values).as_ref());
+    message
+}
+
+// Keyword hash stored on blocks: the first 8 bytes of sha256 of the trimmed,
+// lowercased, normalized keyword, little-endian. Keywords may be n-grams.
+pub fn keyword_hash(keyword: &str) -> Result<u64> {
+    let keyword = normalize_text(keyword.trim()).to_lowercase();
+    require!(
+        !keyword.is_empty() && keyword.len() <= MAX_KEYWORD_LEN,
+        NLPChainError::InvalidKeyword
+    );
+    Ok(u64::from_le_bytes(hash(keyword.as_bytes()).to_bytes()[..8].try_into().unwrap()))
+}
+
+// Block keyword slots, in the given order; unused slots are zero
+pub fn keyword_hashes(keywords: &[String]) -> Result<[u64; MAX_KEYWORDS]> {
+    require!(keywords.len() <= MAX_KEYWORDS, NLPChainError::InvalidKeyword);
+    let mut hashes = [0u64; MAX_KEYWORDS];
+    for (slot, keyword) in hashes.iter_mut().zip(keywords) {
+        *slot = keyword_hash(keyword)?;
+    }
+    Ok(hashes)
+}
+
+// Helper to check that an earlier instruction in the transaction is an
+// ed25519 verification of `embedder_message` by `embedder`
+fn verify_embedder_signature(
+    instructions: Option<&UncheckedAccount>,
+    embedder: &Pubkey,
+    data_hash: &Hash,
+    vector: &[f64],
+    keyword_hashes: &[u64; MAX_KEYWORDS],
+) -> Result<()> {
+    verify_ed25519_message(
+        instructions,
+        embedder,
+        &embedder_message(data_hash, vector, keyword_hashes),
+        NLPChainError::InvalidEmbedderSignature,
+    )
+}
+
+// Same check for an arbitrary message and signer, failing with `error`
+fn verify_ed25519_message(
+    instructions: Option<&UncheckedAccount>,
+    signer: &Pubkey,
+    expected: &[u8],
+    error: NLPChainError,
+) -> Result<()> {
+    let instructions = instructions.ok_or(error)?;
+    let current = load_current_index_checked(instructions)?;
+    for index in 0..current {
+        let ix = load_instruction_at_checked(index as usize, instructions)?;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+        if let Some((pubkey, message)) = parse_ed25519_instruction(&ix.data) {
+            if pubkey == signer.as_ref() && message == expected {
+                return Ok(());
+            }
+        }
+    }
+    Err(error.into())
+}
+
+// Require a secp256k1 program instruction earlier in the transaction that
+// verifies `eth_author`'s signature over `data_hash`
+fn verify_secp256k1_author(
+    instructions: Option<&UncheckedAccount>,
+    eth_author: &[u8; 20],
+    data_hash: &Hash,
+) -> Result<()> {
+    let instructions = instructions.ok_or(NLPChainError::InvalidSecp256k1Signature)?;
+    let current = load_current_index_checked(instructions)?;
+    for index in 0..current {
+        let ix = load_instruction_at_checked(index as usize, instructions)?;
+        if ix.program_id != secp256k1_program::ID {
+            continue;
+        }
+        if let Some((address, message)) = parse_secp256k1_instruction(&ix.data, index as u8) {
+            if address == eth_author.as_ref() && message == data_hash.as_ref() {
+                return Ok(());
+            }
+        }
+    }
+    err!(NLPChainError::InvalidSecp256k1Signature)
+}
+
+// Extract (eth address, message) from a single-signature secp256k1 program
+// instruction at `own_index` whose offsets all point into its own data
+fn parse_secp256k1_instruction(data: &[u8], own_index: u8) -> Option<(&[u8], &[u8])> {
+    const OFFSETS_START: usize = 1;
+    const OFFSETS_LEN: usize = 11;
+
+    if data.len() < OFFSETS_START + OFFSETS_LEN || data[0] != 1 {
+        return None;
+    }
+    let offsets = &data[OFFSETS_START..OFFSETS_START + OFFSETS_LEN];
+    let u16_at = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]) as usize;
+    let (signature_ix, address_offset, address_ix) = (offsets[2], u16_at(3), offsets[5]);
+    let (message_offset, message_len, message_ix) = (u16_at(6), u16_at(8), offsets[10]);
+    if signature_ix != own_index || address_ix != own_index || message_ix != own_index {
+        return None;
+    }
+
+    let address = data.get(address_offset..address_offset + 20)?;
+    let message = data.get(message_offset..message_offset + message_len)?;
+    Some((address, message))
+}
+
+// Extract (pubkey, message) from a single-signature ed25519 program
+// instruction whose offsets all point into its own data
+fn parse_ed25519_instruction(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    const SELF: u16 = u16::MAX;
+
+    if data.len() < OFFSETS_START + OFFSETS_LEN || data[0] != 1 {
+        return None;
+    }
+    let field = |i: usize| {
+        let at = OFFSETS_START + 2 * i;
+        u16::from_le_bytes([data[at], data[at + 1]])
+    };
+    let (signature_ix, pubkey_offset, pubkey_ix) = (field(1), field(2) as usize, field(3));
+    let (message_offset, message_len, message_ix) = (field(4) as usize, field(5) as usize, field(6));
+    if signature_ix != SELF || pubkey_ix != SELF || message_ix != SELF {
+        return None;
+    }
+
+    let pubkey = data.get(pubkey_offset..pubkey_offset + 32)?;
+    let message = data.get(message_offset..message_offset + message_len)?;
+    Some((pubkey, message))
+}
+
+// Helper to add a newly registered data hash to the chain's Bloom filter
+fn insert_into_bloom(
+    chain_state: &ChainState,
+    bloom_filter: Option<&AccountLoader<BloomFilter>>,
+    data_hash: &Hash,
+) -> Result<()> {
+    if !chain_state.bloom_enabled {
+        return Ok(());
+    }
+    let bloom_filter = bloom_filter.ok_or(NLPChainError::BloomFilterUnavailable)?;
+    bloom_filter.load_mut()?.insert(&data_hash.to_bytes());
+    Ok(())
+}
+
+// Canonical form of block text: control characters other than newline and
+// tab are dropped, then the result is NFC-normalized, so visually identical
+// strings hash the same. Invalid UTF-8 never gets this far; borsh rejects it
+// when the instruction is deserialized.
+pub fn normalize_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .nfc()
+        .collect()
+}
+
+// data_hash under HASH_SCHEME_V1:
+//   sha256(len || BLOCK_HASH_DOMAIN || HASH_SCHEME_V1
+//          || len || text || count || minhash as little-endian u32s)
+// with lengths and the minhash count as little-endian u32s. `text` is
+// already normalized.
+pub fn block_data_hash(text: &str, minhash: &[u32]) -> Hash {
+    let signature: Vec<u8> = minhash.iter().flat_map(|v| v.to_le_bytes()).collect();
+    hashv(&[
+        &(BLOCK_HASH_DOMAIN.len() as u32).to_le_bytes(),
+        BLOCK_HASH_DOMAIN,
+        &[HASH_SCHEME_V1],
+        &(text.len() as u32).to_le_bytes(),
+        text.as_bytes(),
+        &(minhash.len() as u32).to_le_bytes(),
+        &signature,
+    ])
+}
+
+// Seed of a block's HashRegistry: the data hash of its normalized text with
+// no minhash, so the same text is a duplicate whatever minhash comes with it
+pub fn dedup_key(text: &str) -> Hash {
+    block_data_hash(&normalize_text(text), &[])
+}
+
+// data_hash of blocks with hash_scheme HASH_SCHEME_LEGACY:
+// sha256(text || minhash as little-endian u32s)
+pub fn legacy_block_data_hash(text: &str, minhash: &[u32]) -> Hash {
+    let signature: Vec<u8> = minhash.iter().flat_map(|v| v.to_le_bytes()).collect();
+    hashv(&[text.as_bytes(), &signature])
+}
+
+// Header of a chain derived from `parent` whose first `shared_count` blocks,
+// ending in `shared_hash`, are shared with the parent rather than copied.
+// fork_index is the last shared block.
+fn derive_chain_state(
+    child: &mut ChainState,
+    parent: &Account<ChainState>,
+    authority: Pubkey,
+    shared_count: u64,
+    shared_hash: Hash,
+) -> Result<()> {
+    child.version = CHAIN_STATE_VERSION;
+    child.authority = authority;
+    child.block_count = shared_count;
+    child.linked_count = shared_count;
+    child.last_hash = shared_hash;
+    child.parent_chain = parent.key();
+    child.fork_index = shared_count - 1;
+    child.fork_count = 0;
+    child.validator_epochs = 0;
+    child.bucket_interval = parent.bucket_interval;
+    child.model_id = parent.model_id.clone();
+    child.model_version = parent.model_version;
+    child.vector_dim = parent.vector_dim;
+    child.max_text_len = parent.max_text_len;
+    child.max_metadata_entries = parent.max_metadata_entries;
+    child.embedder = parent.embedder;
+    child.min_slot_gap = parent.min_slot_gap;
+    child.rate_window_slots = parent.rate_window_slots;
+    child.max_blocks_per_window = parent.max_blocks_per_window;
+    child.fee_per_byte = parent.fee_per_byte;
+    child.treasury = parent.treasury;
+    child.archived_count = shared_count;
+    child.archived_hash = shared_hash;
+    child.pow_max_age = parent.pow_max_age;
+    child.features = parent.features;
+    child.default_license = parent.default_license;
+    child.allowed_licenses = parent.allowed_licenses;
+    child.required_attestations = parent.required_attestations;
+    child.checkpoint_interval = parent.checkpoint_interval;
+    child.checkpoint_bounty = parent.checkpoint_bounty;
+    child.checkpointed_count = shared_count;
+    child.block_ttl = parent.block_ttl;
+    child.max_sub_vectors = parent.max_sub_vectors;
+    child.sparse_dim = parent.sparse_dim;
+    child.pin_mint = parent.pin_mint;
+    child.required_approvals = parent.required_approvals;
+    child.created_at = Clock::get()?.unix_timestamp;
+    child.genesis_config = parent.config();
+    child.quota_bytes = parent.quota_bytes;
+    child.quota_epoch_seconds = parent.quota_epoch_seconds;
+    child.max_unchecked_blocks = parent.max_unchecked_blocks;
+    child.source_checkpoint = None;
+    child.clone_count = 0;
+    Ok(())
+}
+
+// Shared body of add_block and add_block_secp. Kept out of line so its
+// frame is separate from the instruction dispatch and account validation.
+#[inline(never)]
+#[allow(clippy::too_many_arguments)]
+fn append_block(
+    ctx: Context<AddBlock>,
+    text: String,
+    minhash: Vec<u32>,
+    vector: Vec<f64>,
+    metadata: Vec<MetadataEntry>,
+    expected_index: u64,
+    expected_parent_hash: [u8; 32],
+    model: ModelRef,
+    encryption_pubkey: Option<Pubkey>,
+    license: Option<License>,
+    eth_author: Option<[u8; 20]>,
+    keywords: Vec<String>,
+) -> Result<()> {
+    let chain_state = &mut ctx.accounts.chain_state;
+    let block = &mut ctx.accounts.block;
+    let text = normalize_text(&text);
+    let keyword_hashes = keyword_hashes(&keywords)?;
+    chain_state.check_embedding(&model, &vector)?;
+    chain_state.check_content(&text, &metadata)?;
+    validate_metadata(&metadata)?;
+    // Blocks without an explicit license get the chain default
+    let license = license.unwrap_or(chain_state.default_license);
+    require!(chain_state.allows_license(license), NLPChainError::LicenseNotAllowed);
+    require!(
+        minhash.is_empty() || minhash.len() == MINHASH_SIZE,
+        NLPChainError::InvalidMinHash
+    );
+    let data_hash = block_data_hash(&text, &minhash);
+    if chain_state.embedder != Pubkey::default() {
+        verify_embedder_signature(
+            ctx.accounts.instructions.as_ref(),
+            &chain_state.embedder,
+            &data_hash,
+            &vector,
+            &keyword_hashes,
+        )?;
+    } else {
+        // Keywords are only trustworthy when the embedder vouches for them
+        require!(keywords.is_empty(), NLPChainError::KeywordsRequireEmbedder);
+    }
+    if let Some(eth_author) = &eth_author {
+        verify_secp256k1_author(ctx.accounts.instructions.as_ref(), eth_author, &data_hash)?;
+    }
+
+    // Reject writers that built on a stale view of the chain head
+    require!(
+        chain_state.block_count == expected_index
+            && chain_state.last_hash.to_bytes() == expected_parent_hash,
+        NLPChainError::StaleChainHead
+    );
+    chain_state.check_backpressure()?;
+
+    admit_writer(
+        chain_state,
+        &ctx.accounts.authority,
+        &ctx.accounts.payer,
+        WriterGates {
+            proof: ctx.accounts.proof.as_deref(),
+            minimal_program: ctx.accounts.minimal_program.as_ref(),
+            writer_permit: ctx.accounts.writer_permit.as_deref(),
+            writer_committee: ctx.accounts.writer_committee.as_deref(),
+            treasury: ctx.accounts.treasury.as_ref(),
+        },
+        &ctx.accounts.system_program,
+        chain_state.storage_fee(&text, &vector, &metadata)?,
+    )?;
+
+    // Update block data
+    block.version = BLOCK_VERSION;
+    block.authority = ctx.accounts.authority.key();
+    block.eth_author = eth_author.unwrap_or_default();
+    block.index = chain_state.block_count;
+    block.timestamp = Clock::get()?.unix_timestamp;
+    block.text = text;
+    block.vector_norm = fixed_point_norm(&vector);
+    block.binary_vector = binary_quantize(&vector);
+    block.keyword_hashes = keyword_hashes;
+    // Binary-only chains keep just the sign bits of the embedding
+    block.vector = if chain_state.has_feature(FEATURE_BINARY_ONLY) {
+        Vec::new()
+    } else {
+        vector
+    };
+    block.metadata = metadata;
+    block.model_version = model.model_version;
+    // When set, `text` is ciphertext readable via AccessGrant wrapped keys
+    block.encryption_pubkey = encryption_pubkey;
+    block.license = license;
+    block.rent_payer = ctx.accounts.payer.key();
+    block.minhash = minhash;
+    
+    // Store hashes
+    block.data_hash = data_hash;
+    block.hash_scheme = HASH_SCHEME_V1;
+    block.stats_bytes = stored_bytes(&block.text, &block.vector, &block.metadata)? as u64;
+
+    // Claim the data hash; a second submission of the same text fails at init
+    let registry = &mut ctx.accounts.hash_registry;
+    registry.chain = chain_state.key();
+    registry.data_hash = data_hash;
+    registry.block_index = block.index;
+    insert_into_bloom(chain_state, ctx.accounts.bloom_filter.as_ref(), &data_hash)?;
+
+    // Record the block in the writer's paged index
+    let contributor = &mut ctx.accounts.contributor;
+    let author_index = &mut ctx.accounts.author_index;
+    if author_index.block_indexes.is_empty() {
+        author_index.chain = chain_state.key();
+        author_index.author = block.authority;
+        author_index.page = contributor.page();
+    }
+    author_index.block_indexes.push(block.index);
+    contributor.record_block(chain_state, Clock::get()?.slot)?;
+    contributor.record_bytes(
+        chain_state,
+        stored_bytes(&block.text, &block.vector, &block.metadata)? as u64,
+        block.timestamp,
+    )?;
+    let new_author = contributor.block_count == 0;
+    contributor.chain = chain_state.key();
+    contributor.author = block.authority;
+    contributor.block_count += 1;
+
+    // Record the block in its time bucket
+    let time_bucket = &mut ctx.accounts.time_bucket;
+    if time_bucket.count == 0 {
+        time_bucket.chain = chain_state.key();
+        time_bucket.bucket_id = block.timestamp / chain_state.bucket_interval;
+        time_bucket.first_index = block.index;
+    }
+    time_bucket.last_index = block.index;
+    time_bucket.count += 1;
+
+    if chain_state.stats_enabled {
+        let stats = ctx
+            .accounts
+            .chain_stats
+            .as_ref()
+            .ok_or(NLPChainError::ChainStatsUnavailable)?;
+        stats.load_mut()?.record_block(
+            block.stats_bytes,
+            block.vector_norm,
+            new_author,
+            block.timestamp,
+        );
+    }
+    
+    // Mirrors on other chains verify the corpus from these messages
+    if chain_state.wormhole_enabled {
+        let message = ReplicationMessage {
+            index: block.index,
+            data_hash: data_hash.to_bytes(),
+            vector_hash: vector_hash(&block.vector).to_bytes(),
+        };
+        post_wormhole_message(
+            &chain_state.key(),
+            block.index,
+            [
+                ctx.accounts.wormhole_bridge.as_ref(),
+                ctx.accounts.wormhole_message.as_ref(),
+                ctx.accounts.wormhole_emitter.as_ref(),
+                ctx.accounts.wormhole_sequence.as_ref(),
+                ctx.accounts.wormhole_fee_collector.as_ref(),
+                ctx.accounts.wormhole_program.as_ref(),
+                ctx.accounts.clock.as_ref(),
+                ctx.accounts.rent.as_ref(),
+            ],
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            message.try_to_vec()?,
+        )?;
+    }
+
+    // Update chain state
+    link_new_block(chain_state, block);
+
+    Ok(())
+}
+
+// Give `block` the next index slot. On curated chains it waits for approval;
+// otherwise it joins the hash chain now, unless earlier blocks are still
+// waiting, in which case link_released_blocks links it after them.
+fn link_new_block(chain_state: &mut ChainState, block: &mut Block) {
+    block.pending = chain_state.required_approvals > 0;
+    if !block.pending && chain_state.linked_count == chain_state.block_count {
+        block.previous_hash = chain_state.last_hash;
+        chain_state.last_hash = block.data_hash;
+        chain_state.linked_count += 1;
+    } else {
+        block.previous_hash = Hash::default();
+    }
+    chain_state.block_count += 1;
+}
+
+// Optional accounts admit_writer checks a writer against
+struct WriterGates<'a, 'info> {
+    proof: Option<&'a Account<'info, minimal::ProofData>>,
+    minimal_program: Option<&'a Program<'info, minimal::program::Minimal>>,
+    writer_permit: Option<&'a Account<'info, WriterPermit>>,
+    writer_committee: Option<&'a Account<'info, WriterCommittee>>,
+    treasury: Option<&'a UncheckedAccount<'info>>,
+}
+
+// Checks every new block passes, whichever instruction writes it: proof of
+// work, the writer allowlist, committee epochs, and the storage fee (`fee`,
+// charged when the chain has FEATURE_FEES)
+fn admit_writer<'info>(
+    chain_state: &ChainState,
+    authority: &Signer<'info>,
+    payer: &Signer<'info>,
+    gates: WriterGates<'_, 'info>,
+    system_program: &Program<'info, System>,
+    fee: u64,
+) -> Result<()> {
+    // Permissionless chains make each block cost a fresh proof of work
+    if chain_state.pow_max_age > 0 {
+        let (Some(proof), Some(minimal_program)) = (gates.proof, gates.minimal_program) else {
+            return err!(NLPChainError::ProofOfWorkRequired);
+        };
+        require!(
+            proof.owner == authority.key()
+                && proof.timestamp + chain_state.pow_max_age >= Clock::get()?.unix_timestamp,
+            NLPChainError::ProofOfWorkRequired
+        );
+        minimal::cpi::consume_proof(CpiContext::new(
+            minimal_program.to_account_info(),
+            minimal::cpi::accounts::ConsumeProof {
+                proof: proof.to_account_info(),
+                owner: authority.to_account_info(),
+            },
+        ))?;
+    }
+
+    if chain_state.has_feature(FEATURE_ALLOWLIST_ONLY) {
+        require!(gates.writer_permit.is_some(), NLPChainError::WriterNotAllowed);
+    }
+
+    // Committee chains take blocks from one writer per epoch, in turn. The
+    // epoch follows the clock whether or not advance_epoch was cranked.
+    if chain_state.has_feature(FEATURE_WRITER_EPOCHS) {
+        let committee = gates.writer_committee.ok_or(NLPChainError::WriterNotAllowed)?;
+        require!(
+            committee.current_writer(Clock::get()?.slot) == Some(authority.key()),
+            NLPChainError::NotEpochWriter
+        );
+    }
+
+    // Larger blocks pay proportionally more into the chain treasury
+    if chain_state.has_feature(FEATURE_FEES) && fee > 0 {
+        let treasury = gates.treasury.ok_or(NLPChainError::TreasuryRequired)?;
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: treasury.to_account_info(),
+                },
+            ),
+            fee,
+        )?;
+    }
+    Ok(())
+}
+
+// Grow `account` by the bytes its pending layout changes insert (and to at
+// least `min_len`), then replay every layout change newer than its version
+#[allow(clippy::too_many_arguments)]
+fn migrate_layout<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    discriminator: &[u8],
+    version_offset: usize,
+    current_version: u8,
+    min_len: usize,
+    changes: &[LayoutChange],
+) -> Result<()> {
+    let version = {
+        let data = account.try_borrow_data()?;
+        require!(
+            data.len() > version_offset && data[..8] == *discriminator,
+            NLPChainError::InvalidAccountVersion
+        );
+        data[version_offset]
+    };
+    require!(version < current_version, NLPChainError::AccountAlreadyCurrent);
+
+    let inserted: usize = changes
+        .iter()
+        .filter(|change| change.version > version)
+        .map(|change| change.len)
+        .sum();
+    let current_len = (account.data_len() + inserted).max(min_len);
+    grow_account(account, payer, system_program, current_len)?;
+
+    // Bytes shifted off the end are the zero padding the new length added
+    let mut data = account.try_borrow_mut_data()?;
+    let len = data.len();
+    for change in changes.iter().filter(|change| change.version > version) {
+        data.copy_within(change.offset..len - change.len, change.offset + change.len);
+        data[change.offset..change.offset + change.len].fill(0);
+    }
+    data[version_offset] = current_version;
+    Ok(())
+}
+
+// Grow `account` to at least `len` bytes, topping its rent up from `payer`.
+// New bytes are zeroed.
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    len: usize,
+) -> Result<()> {
+    if account.data_len() >= len {
+        return Ok(());
+    }
+    let shortfall = Rent::get()?
+        .minimum_balance(len)
+        .saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    account.realloc(len, true)?;
+    Ok(())
+}
+
+// Pay the bridge fee and post `payload` from the chain's emitter. Accounts are
+// bridge, message, emitter, sequence, fee collector, the core bridge program
+// and the clock and rent sysvars.
+fn post_wormhole_message<'info>(
+    chain: &Pubkey,
+    index: u64,
+    accounts: [Option<&UncheckedAccount<'info>>; 8],
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    payload: Vec<u8>,
+) -> Result<()> {
+    let [Some(bridge), Some(message), Some(emitter), Some(sequence), Some(fee_collector), Some(wormhole), Some(clock), Some(rent)] =
+        accounts
+    else {
+        return err!(NLPChainError::WormholeAccountsRequired);
+    };
+
+    let fee = bridge
+        .try_borrow_data()?
+        .get(WORMHOLE_BRIDGE_FEE_OFFSET..WORMHOLE_BRIDGE_FEE_OFFSET + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(NLPChainError::WormholeAccountsRequired)?;
+    if fee > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: fee_collector.to_account_info(),
+                },
+            ),
+            fee,
+        )?;
+    }
+
+    let index_bytes = index.to_le_bytes();
+    let (_, message_bump) = find_wormhole_message_address(chain, index);
+    let (_, emitter_bump) = find_emitter_address(chain);
+
+    let mut data = vec![WORMHOLE_POST_MESSAGE];
+    // nonce, payload, consistency level
+    (index as u32, payload, WORMHOLE_FINALIZED).serialize(&mut data)?;
+    let instruction = Instruction {
+        program_id: WORMHOLE_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(bridge.key(), false),
+            AccountMeta::new(message.key(), true),
+            AccountMeta::new_readonly(emitter.key(), true),
+            AccountMeta::new(sequence.key(), false),
+            AccountMeta::new(payer.key(), true),
+            AccountMeta::new(fee_collector.key(), false),
+            AccountMeta::new_readonly(clock.key(), false),
+            AccountMeta::new_readonly(rent.key(), false),
+            AccountMeta::new_readonly(system_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &instruction,
+        &[
+            bridge.to_account_info(),
+            message.to_account_info(),
+            emitter.to_account_info(),
+            sequence.to_account_info(),
+            payer.to_account_info(),
+            fee_collector.to_account_info(),
+            clock.to_account_info(),
+            rent.to_account_info(),
+            system_program.to_account_info(),
+            wormhole.to_account_info(),
+        ],
+        &[
+            &[b"wormhole-message", chain.as_ref(), &index_bytes, &[message_bump]],
+            &[b"emitter", chain.as_ref(), &[emitter_bump]],
+        ],
+    )?;
+    Ok(())
+}
+
+// Require `data` to be a current-version Block account owned by this program
+fn check_block_data(info: &AccountInfo, data: &[u8]) -> Result<()> {
+    require_keys_eq!(*info.owner, crate::ID, NLPChainError::InvalidAccountVersion);
+    require!(
+        data.len() > BLOCK_ENCRYPTION_PUBKEY_OFFSET
+            && data[..8] == Block::DISCRIMINATOR
+            && data[BLOCK_VERSION_OFFSET] == BLOCK_VERSION,
+        NLPChainError::InvalidAccountVersion
+    );
+    Ok(())
+}
+
+// Read `count` little-endian u64s at `offset` of a current-version Block
+// account without deserializing the rest of it
+fn read_block_words(info: &AccountInfo, offset: usize, count: usize) -> Result<Vec<u64>> {
+    let data = info.try_borrow_data()?;
+    check_block_data(info, &data)?;
+    require!(data.len() >= offset + count * 8, NLPChainError::InvalidAccountVersion);
+    Ok(data[offset..offset + count * 8]
+        .chunks_exact(8)
+        .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+        .collect())
+}
+
+// Borsh length prefix at `at`
+fn read_len_at(data: &[u8], at: usize) -> Result<usize> {
+    data.get(at..at + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        .ok_or_else(|| error!(NLPChainError::InvalidAccountVersion))
+}
+
+// Serialized length of the Vec<MetadataEntry> at the start of `data`,
+// walked without deserializing it. Follows MetadataValue's variant order.
+fn metadata_serialized_len(data: &[u8]) -> Result<usize> {
+    let count = read_len_at(data, 0)?;
+    let mut at = 4;
+    for _ in 0..count {
+        at += 4 + read_len_at(data, at)?; // key
+        let tag = *data.get(at).ok_or(NLPChainError::InvalidAccountVersion)?;
+        at += 1 + match tag {
+            0 => 4 + read_len_at(data, at + 1)?, // Str
+            1 => 8, // U64
+            2 => 32, // Pubkey
+            3 => 1, // Bool
+            _ => return err!(NLPChainError::InvalidAccountVersion),
+        };
+    }
+    Ok(at)
+}
+
+// Overwrite the vector of a current-version Block account's data in place,
+// shifting the metadata and minhash behind it when the length changes. The
+// account must already have room for the new length.
+fn write_block_vector(data: &mut [u8], vector: &[f64]) -> Result<()> {
+    let text_offset = BLOCK_ENCRYPTION_PUBKEY_OFFSET
+        + if data[BLOCK_ENCRYPTION_PUBKEY_OFFSET] == 1 { 1 + 32 } else { 1 };
+    let vector_offset = text_offset + 4 + read_len_at(data, text_offset)?;
+    let old_end = vector_offset + 4 + read_len_at(data, vector_offset)? * 8;
+    let tail = data.get(old_end..).ok_or(NLPChainError::InvalidAccountVersion)?;
+    let metadata_len = metadata_serialized_len(tail)?;
+    let tail_len = metadata_len + 4 + read_len_at(tail, metadata_len)? * 4;
+    let new_end = vector_offset + 4 + vector.len() * 8;
+    require!(
+        old_end + tail_len <= data.len() && new_end + tail_len <= data.len(),
+        NLPChainError::InvalidAccountVersion
+    );
+
+    data.copy_within(old_end..old_end + tail_len, new_end);
+    if new_end < old_end {
+        data[new_end + tail_len..old_end + tail_len].fill(0);
+    }
+    data[vector_offset..vector_offset + 4].copy_from_slice(&(vector.len() as u32).to_le_bytes());
+    for (i, value) in vector.iter().enumerate() {
+        let offset = vector_offset + 4 + i * 8;
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    Ok(())
+}
+
+// A block's main vector, or sentence sub-vector `sub` of it, with its norm
+fn pick_vector<'a>(
+    block: &'a Block,
+    sub_vectors: Option<&'a SubVectors>,
+    sub: Option<u8>,
+) -> Result<(&'a [f64], u64)> {
+    match sub {
+        None => Ok((&block.vector, block.vector_norm)),
+        Some(i) => sub_vectors
+            .and_then(|s| s.get(i as usize))
+            .ok_or_else(|| NLPChainError::InvalidSubVectors.into()),
+    }
+}
+
+// Bytes of block content counted for storage fees and stats
+fn stored_bytes(text: &str, vector: &[f64], metadata: &[MetadataEntry]) -> Result<usize> {
+    Ok(text.len() + vector.len() * 8 + metadata.try_to_vec()?.len())
+}
+
+// Binary quantization: bit i is set when vector[i] > 0
+pub fn binary_quantize(vector: &[f64]) -> [u64; BINARY_VECTOR_WORDS] {
+    let mut words = [0u64; BINARY_VECTOR_WORDS];
+    for (i, value) in vector.iter().enumerate().take(MAX_VECTOR_DIM) {
+        if *value > 0.0 {
+            words[i / 64] |= 1 << (i % 64);
+        }
+    }
+    words
+}
+
+// Helper to compute a vector's L2 norm in VECTOR_NORM_SCALE fixed point
+fn fixed_point_norm(vector: &[f64]) -> u64 {
+    let norm = vector.iter().map(|v| v * v).sum::<f64>().sqrt();
+    (norm * VECTOR_NORM_SCALE).round() as u64
+}
+
+// Cumulative hash of a block range: h = sha256(h || data_hash) for each block
+// in index order, starting from the zero hash
+pub fn range_hash(data_hashes: &[Hash]) -> Hash {
+    data_hashes
+        .iter()
+        .fold(Hash::default(), |acc, data_hash| hashv(&[acc.as_ref(), data_hash.as_ref()]))
+}
+
+// Binary Merkle root over `leaves`; an odd node is paired with itself
+pub fn merkle_root(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return Hash::default();
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).unwrap_or(&pair[0]);
+                hashv(&[pair[0].as_ref(), right.as_ref()])
+            })
+            .collect();
+    }
+    level[0]
+}
+
+// PDA derivation for clients and CPI callers. These mirror the seeds in the
+// account constraints above; keep the two in sync.
+
+pub fn find_block_address(chain: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"block", chain.as_ref(), &index.to_le_bytes()], &ID)
+}
+
+// HashRegistry for a block's text
+pub fn find_hash_registry_address(chain: &Pubkey, text: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"dedup", chain.as_ref(), dedup_key(text).as_ref()], &ID)
+}
+
+pub fn find_contributor_address(chain: &Pubkey, author: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"contributor", chain.as_ref(), author.as_ref()], &ID)
+}
+
+pub fn find_author_index_address(chain: &Pubkey, author: &Pubkey, page: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"author-index", chain.as_ref(), author.as_ref(), &page.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_thread_index_address(chain: &Pubkey, parent_index: u64, page: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"thread", chain.as_ref(), &parent_index.to_le_bytes(), &page.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_namespace_address(chain: &Pubkey, namespace_id: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"namespace", chain.as_ref(), &namespace_id.to_le_bytes()], &ID)
+}
+
+pub fn find_namespace_index_address(chain: &Pubkey, namespace_id: u16, page: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"ns-index", chain.as_ref(), &namespace_id.to_le_bytes(), &page.to_le_bytes()],
+        &ID,
+    )
+}
+
+// `bucket` is unix_timestamp / ChainState::bucket_interval
+pub fn find_time_bucket_address(chain: &Pubkey, bucket: i64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"time-bucket", chain.as_ref(), &bucket.to_le_bytes()], &ID)
+}
+
+pub fn find_bloom_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bloom", chain.as_ref()], &ID)
+}
+
+pub fn find_chain_stats_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stats", chain.as_ref()], &ID)
+}
+
+pub fn find_centroid_address(chain: &Pubkey, centroid_id: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"centroid", chain.as_ref(), &centroid_id.to_le_bytes()], &ID)
+}
+
+pub fn find_validator_set_address(chain: &Pubkey, epoch: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"validator-set", chain.as_ref(), &epoch.to_le_bytes()], &ID)
+}
+
+pub fn find_moderator_address(chain: &Pubkey, moderator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"moderator", chain.as_ref(), moderator.as_ref()], &ID)
+}
+
+pub fn find_oracle_address(chain: &Pubkey, oracle: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"oracle", chain.as_ref(), oracle.as_ref()], &ID)
+}
+
+pub fn find_writer_permit_address(chain: &Pubkey, writer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"writer", chain.as_ref(), writer.as_ref()], &ID)
+}
+
+// `tag` is normalized before hashing, as tag_block does
+pub fn find_tag_address(chain: &Pubkey, tag: &str, block_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"tag", chain.as_ref(), &tag_seed(tag), &block_index.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_tag_counter_address(chain: &Pubkey, tag: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"tag-counter", chain.as_ref(), &tag_seed(tag)], &ID)
+}
+
+pub fn find_citation_address(chain: &Pubkey, source_index: u64, target_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"citation", chain.as_ref(), &source_index.to_le_bytes(), &target_index.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_link_address(chain: &Pubkey, from_index: u64, to_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"link", chain.as_ref(), &from_index.to_le_bytes(), &to_index.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_grant_address(chain: &Pubkey, block_index: u64, reader: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"grant", chain.as_ref(), &block_index.to_le_bytes(), reader.as_ref()],
+        &ID,
+    )
+}
+
+pub fn find_reader_pass_address(chain: &Pubkey, reader: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"reader-pass", chain.as_ref(), reader.as_ref()], &ID)
+}
+
+pub fn find_syndication_address(source_chain: &Pubkey, target_chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"syndication", source_chain.as_ref(), target_chain.as_ref()],
+        &ID,
+    )
+}
+
+// SyndicationEdge for block `target_index` of `target_chain`
+pub fn find_syndicated_address(target_chain: &Pubkey, target_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"syndicated", target_chain.as_ref(), &target_index.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_checkpoint_address(chain: &Pubkey, checkpoint_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"checkpoint", chain.as_ref(), &checkpoint_index.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_archive_address(chain: &Pubkey, start_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"archive", chain.as_ref(), &start_index.to_le_bytes()], &ID)
+}
+
+pub fn find_summary_address(chain: &Pubkey, start_index: u64, end_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"summary", chain.as_ref(), &start_index.to_le_bytes(), &end_index.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn find_sub_vectors_address(chain: &Pubkey, block_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sub-vectors", chain.as_ref(), &block_index.to_le_bytes()], &ID)
+}
+
+pub fn find_sparse_vector_address(chain: &Pubkey, block_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sparse", chain.as_ref(), &block_index.to_le_bytes()], &ID)
+}
+
+pub fn find_bounty_address(
+    chain: &Pubkey,
+    block_index: u64,
+    poster: &Pubkey,
+    task_kind: AnnotationTask,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"bounty", chain.as_ref(), &block_index.to_le_bytes(), poster.as_ref(), &[task_kind as u8]],
+        &ID,
+    )
+}
+
+pub fn find_bounty_vault_address(bounty: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bounty-vault", bounty.as_ref()], &ID)
+}
+
+pub fn find_annotation_address(bounty: &Pubkey, annotator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"annotation", bounty.as_ref(), annotator.as_ref()], &ID)
+}
+
+pub fn find_pin_address(chain: &Pubkey, block_index: u64, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"pin", chain.as_ref(), &block_index.to_le_bytes(), owner.as_ref()],
+        &ID,
+    )
+}
+
+pub fn find_pin_vault_address(pin: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pin-vault", pin.as_ref()], &ID)
+}
+
+pub fn find_approver_address(chain: &Pubkey, approver: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"approver", chain.as_ref(), approver.as_ref()], &ID)
+}
+
+pub fn find_approval_address(chain: &Pubkey, block_index: u64, approver: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"approval", chain.as_ref(), &block_index.to_le_bytes(), approver.as_ref()],
+        &ID,
+    )
+}
+
+pub fn find_chain_profile_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"chain-profile", chain.as_ref()], &ID)
+}
+
+pub fn find_writer_committee_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"committee", chain.as_ref()], &ID)
+}
+
+pub fn find_vector_stats_address(chain: &Pubkey, start_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vector-stats", chain.as_ref(), &start_index.to_le_bytes()], &ID)
+}
+
+pub fn find_flag_address(chain: &Pubkey, block_index: u64, flagger: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"flag", chain.as_ref(), &block_index.to_le_bytes(), flagger.as_ref()],
+        &ID,
+    )
+}
+
+pub fn find_retraction_address(chain: &Pubkey, block_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"retraction", chain.as_ref(), &block_index.to_le_bytes()], &ID)
+}
+
+pub fn find_attestation_address(chain: &Pubkey, block_index: u64, oracle: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"attestation", chain.as_ref(), &block_index.to_le_bytes(), oracle.as_ref()],
+        &ID,
+    )
+}
+
+pub fn find_block_nft_address(chain: &Pubkey, block_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"block-nft", chain.as_ref(), &block_index.to_le_bytes()], &ID)
+}
+
+pub fn find_treasury_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"treasury", chain.as_ref()], &ID)
+}
+
+pub fn find_guardian_set_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"guardians", chain.as_ref()], &ID)
+}
+
+pub fn find_recovery_address(chain: &Pubkey, new_authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"recovery", chain.as_ref(), new_authority.as_ref()], &ID)
+}
+
+// Signs for the chain's compressed block tree
+pub fn find_tree_authority_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"tree-authority", chain.as_ref()], &ID)
+}
+
+// Bubblegum tree delegate for block NFTs
+pub fn find_nft_authority_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"nft-authority", chain.as_ref()], &ID)
+}
+
+// Wormhole emitter for replicated blocks
+pub fn find_emitter_address(chain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"emitter", chain.as_ref()], &ID)
+}
+
+pub fn find_wormhole_message_address(chain: &Pubkey, block_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"wormhole-message", chain.as_ref(), &block_index.to_le_bytes()],
+        &ID,
+    )
+}
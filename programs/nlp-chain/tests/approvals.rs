@@ -0,0 +1,130 @@
+// Curated chains: blocks wait for the approval quorum and join the hash
+// chain in index order
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::solana_program::system_instruction::SystemError;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use nlp_chain::{accounts, instruction, Block, NLPChainError};
+use span_test::{ix, program_error, Ledger, ProgramError};
+
+type Outcome = std::result::Result<(), ProgramError>;
+
+fn set_required_approvals(ledger: &mut Ledger, chain: &Chain, required_approvals: u8) -> Outcome {
+    run(
+        ledger,
+        accounts::SetRequiredApprovals {
+            chain_state: chain.key,
+            authority: chain.authority,
+        },
+        instruction::SetRequiredApprovals { required_approvals },
+    )
+}
+
+fn add_approver(ledger: &mut Ledger, chain: &Chain) -> Pubkey {
+    let approver = wallet(ledger);
+    run(
+        ledger,
+        accounts::AddApprover {
+            chain_state: chain.key,
+            approver_record: nlp_chain::find_approver_address(&chain.key, &approver).0,
+            authority: chain.authority,
+            system_program: system_program::ID,
+        },
+        instruction::AddApprover { approver },
+    )
+    .unwrap();
+    approver
+}
+
+fn approve(ledger: &mut Ledger, chain: &Chain, approver: Pubkey, block_index: u64) -> Outcome {
+    run(
+        ledger,
+        accounts::ApproveBlock {
+            chain_state: chain.key,
+            block: nlp_chain::find_block_address(&chain.key, block_index).0,
+            approver_record: nlp_chain::find_approver_address(&chain.key, &approver).0,
+            approval: nlp_chain::find_approval_address(&chain.key, block_index, &approver).0,
+            approver,
+            system_program: system_program::ID,
+        },
+        instruction::ApproveBlock { _block_index: block_index },
+    )
+}
+
+fn link_released_blocks(ledger: &mut Ledger, chain: &Chain, indexes: std::ops::Range<u64>) -> Outcome {
+    let mut link = ix(
+        nlp_chain::ID,
+        accounts::LinkReleasedBlocks { chain_state: chain.key },
+        instruction::LinkReleasedBlocks {},
+    );
+    link.accounts.extend(
+        indexes.map(|index| AccountMeta::new(nlp_chain::find_block_address(&chain.key, index).0, false)),
+    );
+    ledger.process(&link)
+}
+
+fn block(ledger: &Ledger, chain: &Chain, index: u64) -> Block {
+    ledger.program_account(&nlp_chain::find_block_address(&chain.key, index).0)
+}
+
+#[test]
+fn approved_blocks_join_the_chain_in_index_order() {
+    let mut ledger = Ledger::new();
+    let chain = chain(&mut ledger);
+    let writer = wallet(&mut ledger);
+    let approvers = [add_approver(&mut ledger, &chain), add_approver(&mut ledger, &chain)];
+    set_required_approvals(&mut ledger, &chain, 2).unwrap();
+    let head = chain_state(&ledger, &chain).last_hash;
+    add_block(&mut ledger, &chain, writer, "first").unwrap();
+    add_block(&mut ledger, &chain, writer, "second").unwrap();
+    assert!(block(&ledger, &chain, 1).pending);
+
+    // Block 2 reaches the quorum first but must wait for block 1
+    for approver in approvers {
+        approve(&mut ledger, &chain, approver, 2).unwrap();
+    }
+    assert!(!block(&ledger, &chain, 2).pending);
+    assert_eq!(chain_state(&ledger, &chain).linked_count, 1);
+
+    // A second sign-off from the same approver finds its approval taken
+    approve(&mut ledger, &chain, approvers[0], 1).unwrap();
+    assert_eq!(
+        approve(&mut ledger, &chain, approvers[0], 1),
+        Err(program_error(SystemError::AccountAlreadyInUse as u32))
+    );
+    assert!(block(&ledger, &chain, 1).pending);
+    approve(&mut ledger, &chain, approvers[1], 1).unwrap();
+    assert_eq!(block(&ledger, &chain, 1).previous_hash, head);
+
+    link_released_blocks(&mut ledger, &chain, 2..3).unwrap();
+    let state = chain_state(&ledger, &chain);
+    assert_eq!(state.linked_count, 3);
+    assert_eq!(block(&ledger, &chain, 2).previous_hash, block(&ledger, &chain, 1).data_hash);
+    assert_eq!(state.last_hash, block(&ledger, &chain, 2).data_hash);
+}
+
+#[test]
+fn set_required_approvals_waits_for_pending_blocks() {
+    let mut ledger = Ledger::new();
+    let chain = chain(&mut ledger);
+    let writer = wallet(&mut ledger);
+    let approver = add_approver(&mut ledger, &chain);
+    set_required_approvals(&mut ledger, &chain, 1).unwrap();
+    add_block(&mut ledger, &chain, writer, "pending").unwrap();
+
+    assert_eq!(
+        set_required_approvals(&mut ledger, &chain, 0),
+        Err(program_error(NLPChainError::BlockPending))
+    );
+    approve(&mut ledger, &chain, approver, 1).unwrap();
+    set_required_approvals(&mut ledger, &chain, 0).unwrap();
+
+    add_block(&mut ledger, &chain, writer, "open").unwrap();
+    let state = chain_state(&ledger, &chain);
+    assert_eq!(state.linked_count, 3);
+    assert_eq!(state.last_hash, block(&ledger, &chain, 2).data_hash);
+}
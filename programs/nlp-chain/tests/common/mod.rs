@@ -0,0 +1,118 @@
+// Shared setup for the tests that run nlp-chain's instructions on a
+// span-test Ledger
+
+#![allow(dead_code)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use nlp_chain::{accounts, instruction, ChainConfig, ChainState, GenesisParams, ModelRef};
+use span_test::{ix, Ledger, ProgramError};
+
+pub const SOL: u64 = 1_000_000_000;
+pub const MODEL_ID: &str = "test-embedder";
+
+pub fn run(ledger: &mut Ledger, accounts: impl ToAccountMetas, args: impl InstructionData) -> std::result::Result<(), ProgramError> {
+    ledger.process(&ix(nlp_chain::ID, accounts, args))
+}
+
+pub fn wallet(ledger: &mut Ledger) -> Pubkey {
+    let key = Pubkey::new_unique();
+    ledger.fund(key, 10 * SOL);
+    key
+}
+
+pub struct Chain {
+    pub key: Pubkey,
+    pub authority: Pubkey,
+}
+
+// A chain small enough that its blocks fit the runtime's allocation limit
+pub fn chain(ledger: &mut Ledger) -> Chain {
+    let key = Pubkey::new_unique();
+    let authority = wallet(ledger);
+    run(
+        ledger,
+        accounts::Initialize {
+            chain_state: key,
+            genesis_block: nlp_chain::find_block_address(&key, 0).0,
+            authority,
+            system_program: system_program::ID,
+        },
+        instruction::Initialize {
+            genesis: GenesisParams {
+                title: "genesis".to_string(),
+                model_id: MODEL_ID.to_string(),
+                license: "CC-BY-4.0".to_string(),
+                external_root_hash: [0; 32],
+            },
+            config: ChainConfig {
+                bucket_interval: 86_400,
+                model_version: 1,
+                vector_dim: 4,
+                max_text_len: 128,
+                max_metadata_entries: 2,
+            },
+        },
+    )
+    .unwrap();
+    Chain { key, authority }
+}
+
+pub fn chain_state(ledger: &Ledger, chain: &Chain) -> ChainState {
+    ledger.program_account(&chain.key)
+}
+
+// Append `text` as `writer`'s block on a chain without optional gates
+pub fn add_block(ledger: &mut Ledger, chain: &Chain, writer: Pubkey, text: &str) -> std::result::Result<u64, ProgramError> {
+    let state = chain_state(ledger, chain);
+    let index = state.block_count;
+    let now = ledger.clock().unix_timestamp;
+    run(
+        ledger,
+        accounts::AddBlock {
+            block: nlp_chain::find_block_address(&chain.key, index).0,
+            hash_registry: nlp_chain::find_hash_registry_address(&chain.key, text).0,
+            contributor: nlp_chain::find_contributor_address(&chain.key, &writer).0,
+            author_index: nlp_chain::find_author_index_address(&chain.key, &writer, 0).0,
+            time_bucket: nlp_chain::find_time_bucket_address(&chain.key, now / state.bucket_interval).0,
+            chain_state: chain.key,
+            instructions: None,
+            bloom_filter: None,
+            treasury: None,
+            chain_stats: None,
+            wormhole_bridge: None,
+            wormhole_message: None,
+            wormhole_emitter: None,
+            wormhole_sequence: None,
+            wormhole_fee_collector: None,
+            wormhole_program: None,
+            clock: None,
+            rent: None,
+            writer_permit: None,
+            writer_committee: Some(nlp_chain::find_writer_committee_address(&chain.key).0)
+                .filter(|committee| ledger.exists(committee)),
+            proof: None,
+            minimal_program: None,
+            authority: writer,
+            payer: writer,
+            system_program: system_program::ID,
+        },
+        instruction::AddBlock {
+            text: text.to_string(),
+            minhash: Vec::new(),
+            vector: vec![0.5, -0.5, 0.5, -0.5],
+            metadata: Vec::new(),
+            expected_index: index,
+            expected_parent_hash: state.last_hash.to_bytes(),
+            model: ModelRef {
+                model_id: MODEL_ID.to_string(),
+                model_version: 1,
+            },
+            encryption_pubkey: None,
+            license: None,
+            keywords: Vec::new(),
+        },
+    )?;
+    Ok(index)
+}
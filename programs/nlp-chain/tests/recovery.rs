@@ -0,0 +1,160 @@
+// Guardian recovery: a proposal hands over the chain only after the
+// timelock, and lapses when the guardians change or another recovery runs
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use nlp_chain::{accounts, instruction, NLPChainError, RECOVERY_TIMELOCK};
+use span_test::{program_error, Ledger, ProgramError};
+
+type Outcome = std::result::Result<(), ProgramError>;
+
+fn set_guardians(ledger: &mut Ledger, chain: &Chain, guardians: &[Pubkey], threshold: u8) {
+    run(
+        ledger,
+        accounts::SetGuardians {
+            chain_state: chain.key,
+            guardian_set: nlp_chain::find_guardian_set_address(&chain.key).0,
+            authority: chain.authority,
+            system_program: system_program::ID,
+        },
+        instruction::SetGuardians {
+            guardians: guardians.to_vec(),
+            threshold,
+        },
+    )
+    .unwrap();
+}
+
+fn propose(ledger: &mut Ledger, chain: &Chain, guardian: Pubkey, new_authority: Pubkey) -> Outcome {
+    run(
+        ledger,
+        accounts::ProposeRecovery {
+            chain_state: chain.key,
+            guardian_set: nlp_chain::find_guardian_set_address(&chain.key).0,
+            proposal: nlp_chain::find_recovery_address(&chain.key, &new_authority).0,
+            guardian,
+            system_program: system_program::ID,
+        },
+        instruction::ProposeRecovery { new_authority },
+    )
+}
+
+fn approve(ledger: &mut Ledger, chain: &Chain, guardian: Pubkey, new_authority: Pubkey) -> Outcome {
+    run(
+        ledger,
+        accounts::ApproveRecovery {
+            chain_state: chain.key,
+            guardian_set: nlp_chain::find_guardian_set_address(&chain.key).0,
+            proposal: nlp_chain::find_recovery_address(&chain.key, &new_authority).0,
+            guardian,
+        },
+        instruction::ApproveRecovery {},
+    )
+}
+
+fn execute(ledger: &mut Ledger, chain: &Chain, new_authority: Pubkey) -> Outcome {
+    run(
+        ledger,
+        accounts::ExecuteRecovery {
+            chain_state: chain.key,
+            guardian_set: nlp_chain::find_guardian_set_address(&chain.key).0,
+            proposal: nlp_chain::find_recovery_address(&chain.key, &new_authority).0,
+            new_authority,
+        },
+        instruction::ExecuteRecovery {},
+    )
+}
+
+// A chain with three guardians, two of whom must agree
+fn guarded_chain(ledger: &mut Ledger) -> (Chain, [Pubkey; 3]) {
+    let chain = chain(ledger);
+    let guardians = [wallet(ledger), wallet(ledger), wallet(ledger)];
+    set_guardians(ledger, &chain, &guardians, 2);
+    (chain, guardians)
+}
+
+#[test]
+fn execute_recovery_hands_over_the_chain_after_the_timelock() {
+    let mut ledger = Ledger::new();
+    let (chain, [g1, g2, _]) = guarded_chain(&mut ledger);
+    let new_authority = Pubkey::new_unique();
+    propose(&mut ledger, &chain, g1, new_authority).unwrap();
+    approve(&mut ledger, &chain, g2, new_authority).unwrap();
+
+    assert_eq!(
+        execute(&mut ledger, &chain, new_authority),
+        Err(program_error(NLPChainError::RecoveryTimelocked))
+    );
+    ledger.advance(RECOVERY_TIMELOCK);
+    execute(&mut ledger, &chain, new_authority).unwrap();
+    assert_eq!(chain_state(&ledger, &chain).authority, new_authority);
+}
+
+#[test]
+fn execute_recovery_needs_the_threshold() {
+    let mut ledger = Ledger::new();
+    let (chain, [g1, _, _]) = guarded_chain(&mut ledger);
+    let new_authority = Pubkey::new_unique();
+    propose(&mut ledger, &chain, g1, new_authority).unwrap();
+    assert_eq!(
+        approve(&mut ledger, &chain, g1, new_authority),
+        Err(program_error(NLPChainError::InvalidRecovery))
+    );
+
+    ledger.advance(RECOVERY_TIMELOCK);
+    assert_eq!(
+        execute(&mut ledger, &chain, new_authority),
+        Err(program_error(NLPChainError::RecoveryTimelocked))
+    );
+}
+
+#[test]
+fn set_guardians_lapses_open_proposals() {
+    let mut ledger = Ledger::new();
+    let (chain, [g1, g2, g3]) = guarded_chain(&mut ledger);
+    let new_authority = Pubkey::new_unique();
+    propose(&mut ledger, &chain, g1, new_authority).unwrap();
+    approve(&mut ledger, &chain, g2, new_authority).unwrap();
+
+    // g2 is dropped; the approval it gave must not carry over
+    set_guardians(&mut ledger, &chain, &[g1, g3], 2);
+    ledger.advance(RECOVERY_TIMELOCK);
+    assert_eq!(
+        execute(&mut ledger, &chain, new_authority),
+        Err(program_error(NLPChainError::InvalidRecovery))
+    );
+    assert_eq!(
+        approve(&mut ledger, &chain, g3, new_authority),
+        Err(program_error(NLPChainError::InvalidRecovery))
+    );
+
+    // The current guardians can start over
+    propose(&mut ledger, &chain, g1, new_authority).unwrap();
+    approve(&mut ledger, &chain, g3, new_authority).unwrap();
+    ledger.advance(RECOVERY_TIMELOCK);
+    execute(&mut ledger, &chain, new_authority).unwrap();
+    assert_eq!(chain_state(&ledger, &chain).authority, new_authority);
+}
+
+#[test]
+fn execute_recovery_lapses_competing_proposals() {
+    let mut ledger = Ledger::new();
+    let (chain, [g1, g2, g3]) = guarded_chain(&mut ledger);
+    let first = Pubkey::new_unique();
+    let second = Pubkey::new_unique();
+    propose(&mut ledger, &chain, g1, first).unwrap();
+    approve(&mut ledger, &chain, g2, first).unwrap();
+    propose(&mut ledger, &chain, g2, second).unwrap();
+    approve(&mut ledger, &chain, g3, second).unwrap();
+
+    ledger.advance(RECOVERY_TIMELOCK);
+    execute(&mut ledger, &chain, first).unwrap();
+    assert_eq!(
+        execute(&mut ledger, &chain, second),
+        Err(program_error(NLPChainError::InvalidRecovery))
+    );
+    assert_eq!(chain_state(&ledger, &chain).authority, first);
+}
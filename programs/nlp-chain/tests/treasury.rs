@@ -0,0 +1,115 @@
+// Chain treasury: payouts follow the active split, and a new split only
+// applies once its timelock has passed
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use nlp_chain::{accounts, instruction, NLPChainError, TreasuryShare, TREASURY_TIMELOCK};
+use span_test::{ix, program_error, Ledger, ProgramError};
+
+fn init_treasury(ledger: &mut Ledger, chain: &Chain) -> Pubkey {
+    let treasury = nlp_chain::find_treasury_address(&chain.key).0;
+    run(
+        ledger,
+        accounts::InitTreasury {
+            chain_state: chain.key,
+            treasury,
+            authority: chain.authority,
+            system_program: system_program::ID,
+        },
+        instruction::InitTreasury {},
+    )
+    .unwrap();
+    treasury
+}
+
+fn propose_shares(ledger: &mut Ledger, chain: &Chain, shares: &[(Pubkey, u16)]) -> std::result::Result<(), ProgramError> {
+    run(
+        ledger,
+        accounts::ProposeTreasuryShares {
+            chain_state: chain.key,
+            treasury: nlp_chain::find_treasury_address(&chain.key).0,
+            authority: chain.authority,
+        },
+        instruction::ProposeTreasuryShares {
+            shares: shares
+                .iter()
+                .map(|&(recipient, bps)| TreasuryShare { recipient, bps })
+                .collect(),
+        },
+    )
+}
+
+fn withdraw(ledger: &mut Ledger, chain: &Chain, recipients: &[Pubkey]) -> std::result::Result<(), ProgramError> {
+    let mut withdraw = ix(
+        nlp_chain::ID,
+        accounts::Withdraw {
+            chain_state: chain.key,
+            treasury: nlp_chain::find_treasury_address(&chain.key).0,
+        },
+        instruction::Withdraw {},
+    );
+    withdraw
+        .accounts
+        .extend(recipients.iter().map(|recipient| AccountMeta::new(*recipient, false)));
+    ledger.process(&withdraw)
+}
+
+#[test]
+fn withdraw_splits_the_spare_lamports_by_share() {
+    let mut ledger = Ledger::new();
+    let chain = chain(&mut ledger);
+    let treasury = init_treasury(&mut ledger, &chain);
+    let (a, b) = (wallet(&mut ledger), wallet(&mut ledger));
+    propose_shares(&mut ledger, &chain, &[(a, 7_500), (b, 2_500)]).unwrap();
+    ledger.advance(TREASURY_TIMELOCK);
+
+    let reserve = ledger.lamports(&treasury);
+    ledger.fund(treasury, 4 * SOL);
+    withdraw(&mut ledger, &chain, &[a, b]).unwrap();
+    assert_eq!(ledger.lamports(&a), 13 * SOL);
+    assert_eq!(ledger.lamports(&b), 11 * SOL);
+    assert_eq!(ledger.lamports(&treasury), reserve);
+}
+
+#[test]
+fn new_shares_wait_for_the_timelock() {
+    let mut ledger = Ledger::new();
+    let chain = chain(&mut ledger);
+    let treasury = init_treasury(&mut ledger, &chain);
+    let (a, b) = (wallet(&mut ledger), wallet(&mut ledger));
+    propose_shares(&mut ledger, &chain, &[(a, 10_000)]).unwrap();
+    ledger.advance(TREASURY_TIMELOCK);
+    ledger.fund(treasury, 2 * SOL);
+    withdraw(&mut ledger, &chain, &[a]).unwrap();
+
+    // The new split is pending, so the old one still pays out
+    propose_shares(&mut ledger, &chain, &[(b, 10_000)]).unwrap();
+    ledger.fund(treasury, 2 * SOL);
+    assert_eq!(
+        withdraw(&mut ledger, &chain, &[b]),
+        Err(program_error(NLPChainError::InvalidTreasuryShares))
+    );
+    withdraw(&mut ledger, &chain, &[a]).unwrap();
+    assert_eq!(ledger.lamports(&a), 14 * SOL);
+
+    ledger.advance(TREASURY_TIMELOCK);
+    ledger.fund(treasury, 2 * SOL);
+    withdraw(&mut ledger, &chain, &[b]).unwrap();
+    assert_eq!(ledger.lamports(&b), 12 * SOL);
+}
+
+#[test]
+fn propose_treasury_shares_must_cover_every_basis_point() {
+    let mut ledger = Ledger::new();
+    let chain = chain(&mut ledger);
+    init_treasury(&mut ledger, &chain);
+    let a = wallet(&mut ledger);
+    assert_eq!(
+        propose_shares(&mut ledger, &chain, &[(a, 9_999)]),
+        Err(program_error(NLPChainError::InvalidTreasuryShares))
+    );
+}
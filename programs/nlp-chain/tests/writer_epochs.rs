@@ -0,0 +1,96 @@
+// Committee chains take blocks from one writer per epoch, in turn, by the
+// clock whether or not advance_epoch is cranked
+
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use common::*;
+use nlp_chain::{accounts, instruction, NLPChainError, WriterCommittee, FEATURE_WRITER_EPOCHS};
+use span_test::{program_error, Ledger, ProgramError};
+
+const EPOCH_SLOTS: u64 = 100;
+
+// A committee chain with two writers; the first holds the pen
+fn committee_chain(ledger: &mut Ledger) -> (Chain, [Pubkey; 2]) {
+    let chain = chain(ledger);
+    let writers = [wallet(ledger), wallet(ledger)];
+    run(
+        ledger,
+        accounts::SetWriterCommittee {
+            chain_state: chain.key,
+            writer_committee: nlp_chain::find_writer_committee_address(&chain.key).0,
+            authority: chain.authority,
+            system_program: system_program::ID,
+        },
+        instruction::SetWriterCommittee {
+            writers: writers.to_vec(),
+            epoch_slots: EPOCH_SLOTS,
+        },
+    )
+    .unwrap();
+    run(
+        ledger,
+        accounts::SetFeatures {
+            chain_state: chain.key,
+            authority: chain.authority,
+        },
+        instruction::SetFeatures {
+            features: FEATURE_WRITER_EPOCHS,
+        },
+    )
+    .unwrap();
+    (chain, writers)
+}
+
+fn advance_epoch(ledger: &mut Ledger, chain: &Chain) -> std::result::Result<(), ProgramError> {
+    run(
+        ledger,
+        accounts::AdvanceEpoch {
+            chain_state: chain.key,
+            writer_committee: nlp_chain::find_writer_committee_address(&chain.key).0,
+        },
+        instruction::AdvanceEpoch {},
+    )
+}
+
+fn committee(ledger: &Ledger, chain: &Chain) -> WriterCommittee {
+    ledger.program_account(&nlp_chain::find_writer_committee_address(&chain.key).0)
+}
+
+#[test]
+fn only_the_epoch_writer_can_add_blocks() {
+    let mut ledger = Ledger::new();
+    let (chain, [first, second]) = committee_chain(&mut ledger);
+    add_block(&mut ledger, &chain, first, "first epoch").unwrap();
+    assert_eq!(
+        add_block(&mut ledger, &chain, second, "out of turn"),
+        Err(program_error(NLPChainError::NotEpochWriter))
+    );
+
+    // The pen passes with the clock, before anyone cranks advance_epoch
+    ledger.advance_slots(EPOCH_SLOTS);
+    add_block(&mut ledger, &chain, second, "second epoch").unwrap();
+    assert_eq!(
+        add_block(&mut ledger, &chain, first, "stale writer"),
+        Err(program_error(NLPChainError::NotEpochWriter))
+    );
+}
+
+#[test]
+fn advance_epoch_records_the_elapsed_epochs() {
+    let mut ledger = Ledger::new();
+    let (chain, [first, _]) = committee_chain(&mut ledger);
+    let start = committee(&ledger, &chain).epoch_start_slot;
+    assert_eq!(
+        advance_epoch(&mut ledger, &chain),
+        Err(program_error(NLPChainError::EpochNotOver))
+    );
+
+    ledger.advance_slots(2 * EPOCH_SLOTS + 10);
+    advance_epoch(&mut ledger, &chain).unwrap();
+    let committee = committee(&ledger, &chain);
+    assert_eq!(committee.epoch, 2);
+    assert_eq!(committee.epoch_start_slot, start + 2 * EPOCH_SLOTS);
+    add_block(&mut ledger, &chain, first, "third epoch").unwrap();
+}
@@ -0,0 +1,364 @@
+//! span-demo: runnable end-to-end reference flow on a local validator.
+//!
+//! Starts `solana-test-validator`, deploys `minimal` and `nlp_chain` from
+//! `target/deploy`, creates a user profile and a chain, mines and submits a
+//! proof, appends sample blocks embedded with the repo's embedding service,
+//! and prints explorer links for every transaction.
+//!
+//! Run from the repository root after `anchor build`:
+//!
+//!     cargo run -p span-demo
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::{
+    read_keypair_file, write_keypair_file, Keypair, Signature, Signer,
+};
+use anchor_client::solana_sdk::{system_program, sysvar};
+use anchor_client::{Client, Cluster, Program};
+use anyhow::{anyhow, bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+const RPC_URL: &str = "http://127.0.0.1:8899";
+const LEDGER_DIR: &str = "target/demo-ledger";
+const PAYER_PATH: &str = "target/demo-payer.json";
+
+// Leading zero bits the demo configures for minimal::submit_proof and
+// minimal::verify_chain
+const PROOF_DIFFICULTY: u32 = 24;
+const CHAIN_DIFFICULTY: u32 = 16;
+
+// Embeddings are truncated so the vector plus text fits in a single
+// transaction (1232 bytes); 384 f64 values alone would be 3 KB.
+const DEMO_VECTOR_DIMS: usize = 64;
+
+const SAMPLE_TEXTS: [&str; 3] = [
+    "Ethereum's latest upgrade has improved transaction speeds and reduced gas fees.",
+    "The DeFi ecosystem continues to grow with new lending and risk management protocols.",
+    "NFT marketplaces are evolving beyond simple art trading into immersive experiences.",
+];
+
+// Kills the validator when the demo exits, successfully or not
+struct Validator(Child);
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn main() -> Result<()> {
+    let _validator = start_validator()?;
+    let rpc = RpcClient::new_with_commitment(RPC_URL, CommitmentConfig::confirmed());
+    wait_for_validator(&rpc)?;
+
+    let payer = Keypair::new();
+    write_keypair_file(&payer, PAYER_PATH).map_err(|e| anyhow!("{e}"))?;
+    airdrop(&rpc, &payer.pubkey(), 100_000_000_000)?;
+
+    let minimal_id = deploy(&rpc, "minimal")?;
+    let nlp_chain_id = deploy(&rpc, "nlp_chain")?;
+
+    let payer = Rc::new(payer);
+    let client = Client::new_with_options(
+        Cluster::Localnet,
+        payer.clone(),
+        CommitmentConfig::confirmed(),
+    );
+    let minimal = client.program(minimal_id)?;
+    let nlp_chain = client.program(nlp_chain_id)?;
+
+    // User profile
+    let (user_profile, _) = minimal::find_user_profile_address(&payer.pubkey());
+    let sig = minimal
+        .request()
+        .accounts(minimal::accounts::InitializeUser {
+            user_profile,
+            owner: payer.pubkey(),
+            system_program: system_program::ID,
+        })
+        .args(minimal::instruction::InitializeUser {})
+        .send()?;
+    report("initialize_user", &sig);
+
+    // Program config
+    let (config, _) = minimal::find_config_address();
+    let sig = minimal
+        .request()
+        .accounts(minimal::accounts::InitializeConfig {
+            config,
+            admin: payer.pubkey(),
+            system_program: system_program::ID,
+        })
+        .args(minimal::instruction::InitializeConfig {
+            proof_target: minimal::target_from_leading_zero_bits(PROOF_DIFFICULTY),
+            chain_target: minimal::target_from_leading_zero_bits(CHAIN_DIFFICULTY),
+        })
+        .send()?;
+    report("initialize_config", &sig);
+
+    // Challenge salt the proof is mined over
+    let (challenge, _) = minimal::find_challenge_address();
+    let sig = minimal
+        .request()
+        .accounts(minimal::accounts::RefreshChallenge {
+            challenge,
+            slot_hashes: sysvar::slot_hashes::ID,
+            payer: payer.pubkey(),
+            system_program: system_program::ID,
+        })
+        .args(minimal::instruction::RefreshChallenge {})
+        .send()?;
+    report("refresh_challenge", &sig);
+    let salt = minimal.account::<minimal::Challenge>(challenge)?.salt;
+
+    // Proof of work
+    let data_hash: [u8; 32] = Sha256::digest(b"span-demo").into();
+    let nonce = mine_proof(&salt, &data_hash, &payer.pubkey(), PROOF_DIFFICULTY);
+    let sig = submit_proof(&minimal, &payer.pubkey(), data_hash, nonce)?;
+    report("submit_proof", &sig);
+
+    // Chain
+    let chain_state = Keypair::new();
+    let (genesis_block, _) = nlp_chain::find_block_address(&chain_state.pubkey(), 0);
+    let sig = nlp_chain
+        .request()
+        .accounts(nlp_chain::accounts::Initialize {
+            chain_state: chain_state.pubkey(),
+            genesis_block,
+            authority: payer.pubkey(),
+            system_program: system_program::ID,
+        })
+        .args(nlp_chain::instruction::Initialize {
+            genesis: nlp_chain::GenesisParams {
+                title: "span-demo sample corpus".to_string(),
+                model_id: "all-MiniLM-L6-v2".to_string(),
+                license: "CC0-1.0".to_string(),
+                external_root_hash: [0; 32],
+            },
+            config: nlp_chain::ChainConfig {
+                vector_dim: DEMO_VECTOR_DIMS as u16,
+                ..Default::default()
+            },
+        })
+        .signer(&chain_state)
+        .send()?;
+    report("initialize", &sig);
+
+    // Blocks
+    let embeddings = embed(&SAMPLE_TEXTS)?;
+    for (text, vector) in SAMPLE_TEXTS.iter().zip(embeddings) {
+        let head: nlp_chain::ChainState = nlp_chain.account(chain_state.pubkey())?;
+        let (block, _) = nlp_chain::find_block_address(&chain_state.pubkey(), head.block_count);
+        let (hash_registry, _) = nlp_chain::find_hash_registry_address(&chain_state.pubkey(), text);
+        let (contributor, _) =
+            nlp_chain::find_contributor_address(&chain_state.pubkey(), &payer.pubkey());
+        let page = nlp_chain
+            .account::<nlp_chain::Contributor>(contributor)
+            .map(|c| c.page())
+            .unwrap_or(0);
+        let (author_index, _) =
+            nlp_chain::find_author_index_address(&chain_state.pubkey(), &payer.pubkey(), page);
+        let now = rpc.get_block_time(rpc.get_slot()?)?;
+        let (time_bucket, _) =
+            nlp_chain::find_time_bucket_address(&chain_state.pubkey(), now / head.bucket_interval);
+        let sig = nlp_chain
+            .request()
+            .accounts(nlp_chain::accounts::AddBlock {
+                block,
+                hash_registry,
+                contributor,
+                author_index,
+                time_bucket,
+                instructions: None,
+                bloom_filter: None,
+                treasury: None,
+                chain_stats: None,
+                writer_permit: None,
+                writer_committee: None,
+                wormhole_bridge: None,
+                wormhole_message: None,
+                wormhole_emitter: None,
+                wormhole_sequence: None,
+                wormhole_fee_collector: None,
+                wormhole_program: None,
+                clock: None,
+                rent: None,
+                proof: None,
+                minimal_program: None,
+                chain_state: chain_state.pubkey(),
+                authority: payer.pubkey(),
+                payer: payer.pubkey(),
+                system_program: system_program::ID,
+            })
+            .args(nlp_chain::instruction::AddBlock {
+                text: text.to_string(),
+                minhash: Vec::new(),
+                vector,
+                metadata: vec![nlp_chain::MetadataEntry::new(
+                    "source",
+                    nlp_chain::MetadataValue::Str("span-demo".to_string()),
+                )],
+                expected_index: head.block_count,
+                expected_parent_hash: head.last_hash.to_bytes(),
+                model: nlp_chain::ModelRef {
+                    model_id: head.model_id.clone(),
+                    model_version: head.model_version,
+                },
+                encryption_pubkey: None,
+                license: None,
+                keywords: vec![],
+            })
+            .send()?;
+        report(&format!("add_block #{}", head.block_count), &sig);
+    }
+
+    println!("\nchain state: {}", explorer_account(&chain_state.pubkey()));
+    Ok(())
+}
+
+fn start_validator() -> Result<Validator> {
+    let child = Command::new("solana-test-validator")
+        .args(["--reset", "--quiet", "--ledger", LEDGER_DIR])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to start solana-test-validator (is the Solana CLI installed?)")?;
+    Ok(Validator(child))
+}
+
+fn wait_for_validator(rpc: &RpcClient) -> Result<()> {
+    for _ in 0..60 {
+        if rpc.get_health().is_ok() {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    bail!("validator did not become healthy at {RPC_URL}")
+}
+
+fn airdrop(rpc: &RpcClient, to: &Pubkey, lamports: u64) -> Result<()> {
+    let sig = rpc.request_airdrop(to, lamports)?;
+    while !rpc.confirm_transaction(&sig)? {
+        thread::sleep(Duration::from_millis(250));
+    }
+    Ok(())
+}
+
+// Deploys target/deploy/<name>.so at the address of its generated keypair
+fn deploy(rpc: &RpcClient, name: &str) -> Result<Pubkey> {
+    let so = format!("target/deploy/{name}.so");
+    let keypair = format!("target/deploy/{name}-keypair.json");
+    if !Path::new(&so).exists() {
+        bail!("{so} not found; run `anchor build` first");
+    }
+    let program_id = read_keypair_file(&keypair)
+        .map_err(|e| anyhow!("{keypair}: {e}"))?
+        .pubkey();
+
+    let status = Command::new("solana")
+        .args(["program", "deploy", &so])
+        .args(["--program-id", &keypair])
+        .args(["--keypair", PAYER_PATH])
+        .args(["--url", RPC_URL])
+        .stdout(Stdio::null())
+        .status()?;
+    if !status.success() {
+        bail!("deploying {name} failed");
+    }
+    if rpc.get_account(&program_id).is_err() {
+        bail!("{name} not found at {program_id} after deploy");
+    }
+    println!("deployed {name}: {}", explorer_account(&program_id));
+    Ok(program_id)
+}
+
+// Searches nonces until the proof's work hash has `difficulty` leading zero bits
+fn mine_proof(salt: &[u8; 32], data_hash: &[u8; 32], owner: &Pubkey, difficulty: u32) -> u64 {
+    let target = minimal::target_from_leading_zero_bits(difficulty);
+    (0u64..)
+        .find(|nonce| {
+            minimal::meets_target(&minimal::proof_work_hash(salt, data_hash, *nonce, owner), &target)
+        })
+        .expect("nonce space exhausted")
+}
+
+// The proof PDA is seeded by the owner's proof count
+fn submit_proof(
+    minimal: &Program<Rc<Keypair>>,
+    owner: &Pubkey,
+    data_hash: [u8; 32],
+    nonce: u64,
+) -> Result<Signature> {
+    let (user_profile, _) = minimal::find_user_profile_address(owner);
+    let count = minimal.account::<minimal::UserProfile>(user_profile)?.proof_count;
+    let (proof, _) = minimal::find_proof_address(owner, count);
+    Ok(minimal
+        .request()
+        .accounts(minimal::accounts::SubmitProof {
+            proof,
+            user_profile,
+            config: minimal::find_config_address().0,
+            challenge: minimal::find_challenge_address().0,
+            reward_mint: None,
+            reward_account: None,
+            token_program: None,
+            leaderboard: None,
+            stake: None,
+            owner: *owner,
+            system_program: system_program::ID,
+        })
+        .args(minimal::instruction::SubmitProof { data_hash, nonce })
+        .send()?)
+}
+
+// Embeds texts with models/embedding_service.py so the demo stores real vectors
+fn embed(texts: &[&str]) -> Result<Vec<Vec<f64>>> {
+    let script = "import json, sys\n\
+        from models.embedding_service import EmbeddingService\n\
+        texts = json.load(sys.stdin)\n\
+        print(json.dumps(EmbeddingService().generate_embeddings(texts)))";
+    let mut child = Command::new("python3")
+        .args(["-c", script])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .context("failed to start python3 for embeddings")?;
+    serde_json::to_writer(child.stdin.take().unwrap(), texts)?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("embedding service failed");
+    }
+    let vectors: Vec<Vec<f64>> = serde_json::from_slice(&output.stdout)?;
+    Ok(vectors
+        .into_iter()
+        .map(|v| v.into_iter().take(DEMO_VECTOR_DIMS).collect())
+        .collect())
+}
+
+fn report(step: &str, sig: &Signature) {
+    println!(
+        "{step}: https://explorer.solana.com/tx/{sig}?cluster=custom&customUrl={}",
+        urlencoded(RPC_URL)
+    );
+}
+
+fn explorer_account(address: &Pubkey) -> String {
+    format!(
+        "https://explorer.solana.com/address/{address}?cluster=custom&customUrl={}",
+        urlencoded(RPC_URL)
+    )
+}
+
+fn urlencoded(url: &str) -> String {
+    url.replace(':', "%3A").replace('/', "%2F")
+}
@@ -0,0 +1,487 @@
+//! span-test: runs span's programs in-process for behavioural tests.
+//!
+//! A `Ledger` holds accounts in memory and executes instructions through
+//! each program's Anchor `entry`, with the accounts laid out exactly as the
+//! BPF loader serializes them, so account validation, `init`, `close` and
+//! realloc behave as they do on chain. Syscall stubs supply the clock, rent
+//! and return data, and run CPIs into the system program, SPL Token,
+//! `minimal` and `nlp_chain` in the same process.
+//!
+//! Instructions are atomic: a failing one leaves the ledger untouched.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::{
+    self, ProgramResult, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER,
+};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use anchor_lang::solana_program::program_utils::limited_deserialize;
+use anchor_lang::solana_program::system_instruction::{SystemError, SystemInstruction};
+use anchor_lang::solana_program::{bpf_loader_upgradeable, system_program};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+
+pub use anchor_lang::solana_program::program_error::ProgramError;
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    // Programs on the call stack, innermost last
+    static PROGRAMS: RefCell<Vec<Pubkey>> = const { RefCell::new(Vec::new()) };
+}
+
+// Programs a Ledger can execute
+const PROGRAMS_LOADED: [Pubkey; 4] = [system_program::ID, spl_token::ID, minimal::ID, nlp_chain::ID];
+
+// Largest instruction the system program decodes
+const PACKET_DATA_SIZE: u64 = 1232;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StoredAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+#[derive(Default)]
+pub struct Ledger {
+    accounts: HashMap<Pubkey, StoredAccount>,
+    clock: Clock,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        let mut ledger = Self::default();
+        for program in PROGRAMS_LOADED {
+            ledger.accounts.insert(
+                program,
+                StoredAccount {
+                    lamports: 1,
+                    data: Vec::new(),
+                    owner: bpf_loader_upgradeable::ID,
+                    executable: true,
+                },
+            );
+        }
+        ledger.clock.unix_timestamp = 1_700_000_000;
+        ledger.clock.slot = 1;
+        ledger
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    // Move the clock forward by `seconds` and one slot per 400ms
+    pub fn advance(&mut self, seconds: i64) {
+        self.clock.unix_timestamp += seconds;
+        self.clock.slot += (seconds.max(0) as u64 * 5).div_ceil(2);
+    }
+
+    pub fn advance_slots(&mut self, slots: u64) {
+        self.clock.slot += slots;
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: StoredAccount) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn get_account(&self, key: &Pubkey) -> Option<&StoredAccount> {
+        self.accounts.get(key)
+    }
+
+    pub fn exists(&self, key: &Pubkey) -> bool {
+        self.accounts.contains_key(key)
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    // A system account holding `lamports`, e.g. a fee payer
+    pub fn fund(&mut self, key: Pubkey, lamports: u64) {
+        let account = self.accounts.entry(key).or_insert_with(|| StoredAccount {
+            owner: system_program::ID,
+            ..Default::default()
+        });
+        account.lamports += lamports;
+    }
+
+    // Store an Anchor account in `space` bytes, rent exempt
+    pub fn set_program_account<T: AccountSerialize + Owner>(&mut self, key: Pubkey, account: &T, space: usize) {
+        let mut data = Vec::with_capacity(space);
+        account.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= space, "account does not fit in {space} bytes");
+        data.resize(space, 0);
+        self.accounts.insert(
+            key,
+            StoredAccount {
+                lamports: Rent::default().minimum_balance(space),
+                data,
+                owner: T::owner(),
+                executable: false,
+            },
+        );
+    }
+
+    pub fn program_account<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        let account = self.accounts.get(key).unwrap_or_else(|| panic!("no account at {key}"));
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    pub fn create_mint(&mut self, mint: Pubkey, authority: Pubkey, decimals: u8) {
+        let state = spl_token::state::Mint {
+            mint_authority: COption::Some(authority),
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        self.set_token_state(mint, state);
+    }
+
+    // A token account of `mint` owned by `owner`, holding `amount`
+    pub fn create_token_account(&mut self, key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+        let state = spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        self.set_token_state(key, state);
+    }
+
+    pub fn token_account(&self, key: &Pubkey) -> spl_token::state::Account {
+        let account = self.accounts.get(key).unwrap_or_else(|| panic!("no token account at {key}"));
+        spl_token::state::Account::unpack(&account.data).unwrap()
+    }
+
+    pub fn token_balance(&self, key: &Pubkey) -> u64 {
+        self.token_account(key).amount
+    }
+
+    fn set_token_state<T: Pack>(&mut self, key: Pubkey, state: T) {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        self.accounts.insert(
+            key,
+            StoredAccount {
+                lamports: Rent::default().minimum_balance(T::LEN),
+                data,
+                owner: spl_token::ID,
+                executable: false,
+            },
+        );
+    }
+
+    // Return data left by the last instruction
+    pub fn return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|data| data.borrow().clone())
+    }
+
+    // Run one top-level instruction. Accounts flagged as signers in its
+    // metas are taken to have signed.
+    pub fn process(&mut self, instruction: &Instruction) -> ProgramResult {
+        install_stubs();
+        CLOCK.with(|clock| *clock.borrow_mut() = self.clock.clone());
+        RETURN_DATA.with(|data| *data.borrow_mut() = None);
+
+        let mut input = LoaderInput::new(instruction, &self.accounts);
+        let result = {
+            let (program_id, accounts, data) = unsafe { entrypoint::deserialize(input.as_mut_ptr()) };
+            execute(program_id, accounts, data)
+        };
+        if result.is_ok() {
+            for (key, account) in input.accounts() {
+                // Accounts left without lamports are purged, as at the end of a transaction
+                if account.lamports == 0 {
+                    self.accounts.remove(&key);
+                } else {
+                    self.accounts.insert(key, account);
+                }
+            }
+        }
+        result
+    }
+}
+
+// Build an instruction from Anchor's generated accounts and args structs
+pub fn ix(program_id: Pubkey, accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+// The error an Anchor program returns for `code`
+pub fn program_error(code: impl Into<u32>) -> ProgramError {
+    ProgramError::Custom(code.into())
+}
+
+// An instruction's accounts serialized in the BPF loader's aligned input
+// format, followed by its data and program id
+struct LoaderInput {
+    buffer: Vec<u64>, // u64 words keep the input 8-byte aligned
+    accounts: Vec<(Pubkey, usize, bool)>, // key, offset of its header, executable
+}
+
+impl LoaderInput {
+    fn new(instruction: &Instruction, store: &HashMap<Pubkey, StoredAccount>) -> Self {
+        // Deduplicate the metas, merging their privileges
+        let mut metas: Vec<(Pubkey, bool, bool)> = Vec::new();
+        let mut positions = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            match metas.iter().position(|(key, _, _)| *key == meta.pubkey) {
+                Some(position) => {
+                    metas[position].1 |= meta.is_signer;
+                    metas[position].2 |= meta.is_writable;
+                    positions.push(position);
+                }
+                None => {
+                    positions.push(metas.len());
+                    metas.push((meta.pubkey, meta.is_signer, meta.is_writable));
+                }
+            }
+        }
+
+        let mut bytes = Vec::new();
+        let mut accounts = Vec::new();
+        let mut serialized = vec![false; metas.len()];
+        bytes.extend_from_slice(&(positions.len() as u64).to_le_bytes());
+        for (index, position) in positions.into_iter().enumerate() {
+            if serialized[position] {
+                let first = positions_first(&instruction.accounts, index);
+                bytes.push(first as u8);
+                bytes.extend_from_slice(&[0; 7]);
+                continue;
+            }
+            serialized[position] = true;
+            let (key, is_signer, is_writable) = metas[position];
+            let account = store.get(&key).cloned().unwrap_or_else(|| StoredAccount {
+                owner: system_program::ID,
+                ..Default::default()
+            });
+            accounts.push((key, bytes.len(), account.executable));
+            bytes.push(NON_DUP_MARKER);
+            bytes.push(is_signer as u8);
+            bytes.push(is_writable as u8);
+            bytes.push(account.executable as u8);
+            bytes.extend_from_slice(&[0; 4]); // original data length, filled in by deserialize
+            bytes.extend_from_slice(key.as_ref());
+            bytes.extend_from_slice(account.owner.as_ref());
+            bytes.extend_from_slice(&account.lamports.to_le_bytes());
+            bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&account.data);
+            bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            bytes.resize(bytes.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+            bytes.extend_from_slice(&0u64.to_le_bytes()); // rent epoch
+        }
+        bytes.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&instruction.data);
+        bytes.extend_from_slice(instruction.program_id.as_ref());
+
+        let mut buffer = vec![0u64; bytes.len().div_ceil(8)];
+        for (word, chunk) in buffer.iter_mut().zip(bytes.chunks(8)) {
+            let mut le = [0; 8];
+            le[..chunk.len()].copy_from_slice(chunk);
+            *word = u64::from_le_bytes(le);
+        }
+        Self { buffer, accounts }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.buffer.as_mut_ptr() as *mut u8
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.buffer.len() * 8) }
+    }
+
+    // Each account's state as the program left it
+    fn accounts(&self) -> Vec<(Pubkey, StoredAccount)> {
+        let bytes = self.bytes();
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        self.accounts
+            .iter()
+            .map(|&(key, header, executable)| {
+                let owner = header + 8 + 32;
+                let lamports = owner + 32;
+                let data_len = lamports + 8;
+                let data = data_len + 8;
+                let len = u64_at(data_len) as usize;
+                let account = StoredAccount {
+                    lamports: u64_at(lamports),
+                    data: bytes[data..data + len].to_vec(),
+                    owner: Pubkey::try_from(&bytes[owner..owner + 32]).unwrap(),
+                    executable,
+                };
+                (key, account)
+            })
+            .collect()
+    }
+}
+
+// Index of the first meta naming the same account as meta `index`
+fn positions_first(metas: &[AccountMeta], index: usize) -> usize {
+    metas.iter().position(|meta| meta.pubkey == metas[index].pubkey).unwrap()
+}
+
+fn install_stubs() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(Stubs));
+    });
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = CLOCK.with(|clock| clock.borrow().clone());
+        unsafe { std::ptr::write(var_addr as *mut Clock, clock) };
+        entrypoint::SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { std::ptr::write(var_addr as *mut Rent, Rent::default()) };
+        entrypoint::SUCCESS
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|data| data.borrow().clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let program = current_program();
+        RETURN_DATA.with(|return_data| {
+            *return_data.borrow_mut() = (!data.is_empty()).then(|| (program, data.to_vec()));
+        });
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let caller = current_program();
+        let pda_signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut accounts = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            // The callee gets no privilege the caller doesn't hold
+            let is_signer = meta.is_signer && (info.is_signer || pda_signers.contains(info.key));
+            if meta.is_signer && !is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            let mut info = info.clone();
+            info.is_signer = is_signer;
+            info.is_writable = meta.is_writable;
+            accounts.push(info);
+        }
+        execute(&instruction.program_id, accounts, &instruction.data)
+    }
+}
+
+fn current_program() -> Pubkey {
+    PROGRAMS.with(|programs| *programs.borrow().last().expect("no program is executing"))
+}
+
+fn execute<'a>(program_id: &Pubkey, accounts: Vec<AccountInfo<'a>>, data: &[u8]) -> ProgramResult {
+    // Anchor's entry wants the account slice to live as long as the infos
+    let accounts: &'a [AccountInfo<'a>] = Vec::leak(accounts);
+    PROGRAMS.with(|programs| programs.borrow_mut().push(*program_id));
+    let result = if *program_id == system_program::ID {
+        process_system_instruction(accounts, data)
+    } else if *program_id == spl_token::ID {
+        spl_token::processor::Processor::process(program_id, accounts, data)
+    } else if *program_id == minimal::ID {
+        minimal::entry(program_id, accounts, data)
+    } else if *program_id == nlp_chain::ID {
+        nlp_chain::entry(program_id, accounts, data)
+    } else {
+        Err(ProgramError::IncorrectProgramId)
+    };
+    PROGRAMS.with(|programs| programs.borrow_mut().pop());
+    result
+}
+
+// The parts of the system program Anchor's account constraints use
+fn process_system_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let instruction: SystemInstruction =
+        limited_deserialize(data, PACKET_DATA_SIZE).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let signed = |info: &AccountInfo| {
+        if info.is_signer {
+            Ok(())
+        } else {
+            Err(ProgramError::MissingRequiredSignature)
+        }
+    };
+    match instruction {
+        SystemInstruction::CreateAccount { lamports, space, owner } => {
+            let (from, to) = (&accounts[0], &accounts[1]);
+            signed(from)?;
+            signed(to)?;
+            if to.lamports() > 0 || !to.data_is_empty() || *to.owner != system_program::ID {
+                return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+            }
+            move_lamports(from, to, lamports)?;
+            to.realloc(space as usize, true)?;
+            to.assign(&owner);
+        }
+        SystemInstruction::Transfer { lamports } => {
+            signed(&accounts[0])?;
+            move_lamports(&accounts[0], &accounts[1], lamports)?;
+        }
+        SystemInstruction::Allocate { space } => {
+            signed(&accounts[0])?;
+            if !accounts[0].data_is_empty() || *accounts[0].owner != system_program::ID {
+                return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+            }
+            accounts[0].realloc(space as usize, true)?;
+        }
+        SystemInstruction::Assign { owner } => {
+            signed(&accounts[0])?;
+            // Only the owning program may hand an account on
+            if *accounts[0].owner != owner && *accounts[0].owner != system_program::ID {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            accounts[0].assign(&owner);
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+    Ok(())
+}
+
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    let mut from_lamports = from.try_borrow_mut_lamports()?;
+    if **from_lamports < lamports {
+        return Err(ProgramError::Custom(SystemError::ResultWithNegativeLamports as u32));
+    }
+    **from_lamports -= lamports;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
@@ -0,0 +1,429 @@
+//! span-vectors: emits golden test vectors for span's hashing, PDA and
+//! serialization rules.
+//!
+//! Every vector is computed by calling into the `nlp_chain` and `minimal`
+//! crates themselves, so a client implementation that reproduces the
+//! fixtures agrees with the on-chain programs byte for byte.
+//!
+//!     cargo run -p span-vectors -- [output-dir]   # default: fixtures/vectors
+
+use std::fs;
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::{AccountSerialize, AnchorSerialize};
+use anyhow::Result;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+const DEFAULT_OUT_DIR: &str = "fixtures/vectors";
+
+fn main() -> Result<()> {
+    let out_dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_OUT_DIR));
+    fs::create_dir_all(&out_dir)?;
+
+    for (rule, cases) in vectors()? {
+        let path = out_dir.join(format!("{rule}.json"));
+        fs::write(&path, render(rule, &cases)?)?;
+        println!("wrote {} ({} cases)", path.display(), cases.len());
+    }
+    Ok(())
+}
+
+// Every fixture file, by rule name
+fn vectors() -> Result<Vec<(&'static str, Vec<Value>)>> {
+    Ok(vec![
+        ("text_normalization", text_normalization()),
+        ("block_data_hash", block_data_hash()),
+        ("genesis_hash", genesis_hash()),
+        ("archive_merkle_root", archive_merkle_root()),
+        ("summary_range_hash", summary_range_hash()),
+        ("tag_normalization", tag_normalization()),
+        ("keyword_hash", keyword_hash()),
+        ("sparse_vector_hash", sparse_vector_hash()),
+        ("proof_difficulty", proof_difficulty()),
+        ("nlp_chain_pdas", nlp_chain_pdas()),
+        ("minimal_pdas", minimal_pdas()),
+        ("accounts", accounts()?),
+    ])
+}
+
+fn render(rule: &str, cases: &[Value]) -> Result<String> {
+    let body = json!({ "rule": rule, "cases": cases });
+    Ok(serde_json::to_string_pretty(&body)? + "\n")
+}
+
+// Fixed, human-recognizable keys: [n; 32]
+fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Text is stripped of control characters (except \n and \t) and NFC-normalized
+// before hashing
+fn text_normalization() -> Vec<Value> {
+    ["hello", "he\u{301}llo", "h\u{e9}llo", "tab\there", "crlf\r\nline", "nul\u{0}byte", "\u{85}next"]
+        .iter()
+        .map(|text| {
+            let normalized = nlp_chain::normalize_text(text);
+            json!({
+                "text_hex": hex(text.as_bytes()),
+                "normalized": normalized,
+                "normalized_hex": hex(normalized.as_bytes()),
+                "data_hash": hex(nlp_chain::block_data_hash(&normalized, &[]).as_ref()),
+            })
+        })
+        .collect()
+}
+
+// data_hash = sha256(u32 len || "span:block:v1" || 0x01 || u32 len || text
+// || u32 count || minhash as little-endian u32s)
+fn block_data_hash() -> Vec<Value> {
+    let signature: Vec<u32> = (0..nlp_chain::MINHASH_SIZE as u32).map(|i| i * 7919).collect();
+    let cases: [(&str, &[u32]); 6] = [
+        ("", &[]),
+        ("hello", &[]),
+        ("Hello", &[]),
+        ("héllo wörld", &[]),
+        ("line one\nline two", &[]),
+        ("hello", &signature),
+    ];
+    cases
+        .iter()
+        .map(|(text, minhash)| {
+            json!({
+                "text": text,
+                "text_hex": hex(text.as_bytes()),
+                "minhash": minhash,
+                "data_hash": hex(nlp_chain::block_data_hash(text, minhash).as_ref()),
+            })
+        })
+        .collect()
+}
+
+// Genesis block hash = sha256(borsh(GenesisParams))
+fn genesis_hash() -> Vec<Value> {
+    let cases = [
+        nlp_chain::GenesisParams {
+            title: "span test corpus".to_string(),
+            model_id: "all-MiniLM-L6-v2".to_string(),
+            license: "CC0-1.0".to_string(),
+            external_root_hash: [0; 32],
+        },
+        nlp_chain::GenesisParams {
+            title: String::new(),
+            model_id: "text-embedding-3-small".to_string(),
+            license: "CC-BY-4.0".to_string(),
+            external_root_hash: [0xab; 32],
+        },
+    ];
+    cases
+        .iter()
+        .map(|genesis| {
+            let preimage = genesis.try_to_vec().unwrap();
+            json!({
+                "title": genesis.title,
+                "model_id": genesis.model_id,
+                "license": genesis.license,
+                "external_root_hash": hex(&genesis.external_root_hash),
+                "preimage": hex(&preimage),
+                "genesis_hash": hex(hash(&preimage).as_ref()),
+            })
+        })
+        .collect()
+}
+
+// Archive root: pairwise sha256 up the tree, odd nodes paired with themselves
+fn archive_merkle_root() -> Vec<Value> {
+    [0usize, 1, 2, 3, 5]
+        .iter()
+        .map(|&count| {
+            let leaves: Vec<_> = (0..count).map(|i| hash(format!("block {i}").as_bytes())).collect();
+            json!({
+                "leaves": leaves.iter().map(|l| hex(l.as_ref())).collect::<Vec<_>>(),
+                "merkle_root": hex(nlp_chain::merkle_root(&leaves).as_ref()),
+            })
+        })
+        .collect()
+}
+
+// Summary range hash: h = sha256(h || data_hash) per block, from the zero hash
+fn summary_range_hash() -> Vec<Value> {
+    [1usize, 2, 5]
+        .iter()
+        .map(|&count| {
+            let data_hashes: Vec<_> = (0..count).map(|i| hash(format!("block {i}").as_bytes())).collect();
+            json!({
+                "data_hashes": data_hashes.iter().map(|h| hex(h.as_ref())).collect::<Vec<_>>(),
+                "range_hash": hex(nlp_chain::range_hash(&data_hashes).as_ref()),
+            })
+        })
+        .collect()
+}
+
+// Tags are trimmed and lowercased before hashing; invalid tags are rejected
+fn tag_normalization() -> Vec<Value> {
+    ["rust", "  Rust ", "machine-learning", "snake_case", "", "has space", "ünï"]
+        .iter()
+        .map(|tag| match nlp_chain::normalize_tag(tag) {
+            Ok(normalized) => json!({
+                "tag": tag,
+                "valid": true,
+                "normalized": normalized,
+                "tag_hash": hex(hash(normalized.as_bytes()).as_ref()),
+            }),
+            Err(_) => json!({ "tag": tag, "valid": false }),
+        })
+        .collect()
+}
+
+// Keywords are trimmed, normalized and lowercased; the hash is the first 8
+// bytes of sha256, little-endian
+fn keyword_hash() -> Vec<Value> {
+    ["rust", "  Rust ", "machine learning", "", "a keyword that is far too long to store"]
+        .iter()
+        .map(|keyword| match nlp_chain::keyword_hash(keyword) {
+            Ok(keyword_hash) => json!({
+                "keyword": keyword,
+                "valid": true,
+                "keyword_hash": keyword_hash.to_string(),
+            }),
+            Err(_) => json!({ "keyword": keyword, "valid": false }),
+        })
+        .collect()
+}
+
+// Sparse vector hash = sha256(indices as LE u32s || values as LE f64s)
+fn sparse_vector_hash() -> Vec<Value> {
+    let cases: [(&[u32], &[f64]); 2] = [(&[7], &[0.5]), (&[3, 1017, 20_000], &[1.25, 0.5, 0.125])];
+    cases
+        .iter()
+        .map(|(indices, values)| {
+            json!({
+                "indices": indices,
+                "values": values,
+                "sparse_vector_hash": hex(nlp_chain::sparse_vector_hash(indices, values).as_ref()),
+            })
+        })
+        .collect()
+}
+
+// Big-endian 256-bit target comparison as checked by minimal::submit_proof,
+// over work hash =
+// sha256(challenge salt || data_hash || nonce as little-endian u64 || owner).
+// Targets are 2^(256 - difficulty) - 1.
+fn proof_difficulty() -> Vec<Value> {
+    let salt: [u8; 32] = Sha256::digest(b"span-vectors-salt").into();
+    let data_hash: [u8; 32] = Sha256::digest(b"span-vectors").into();
+    let owner = key(3);
+    let mut cases = Vec::new();
+    for difficulty in [1u32, 4, 8, 12] {
+        let target = minimal::target_from_leading_zero_bits(difficulty);
+        let nonce = (0u64..)
+            .find(|nonce| {
+                let digest = minimal::proof_work_hash(&salt, &data_hash, *nonce, &owner);
+                minimal::meets_target(&digest, &target)
+            })
+            .unwrap();
+        let digest = minimal::proof_work_hash(&salt, &data_hash, nonce, &owner);
+        cases.push(json!({
+            "salt": hex(&salt),
+            "data_hash": hex(&data_hash),
+            "nonce": nonce,
+            "owner": owner.to_string(),
+            "hash": hex(&digest),
+            "target": hex(&target),
+            "work": minimal::target_work(&target).to_string(),
+            "valid": true,
+        }));
+        let mut failing = digest;
+        let bit = difficulty as usize - 1;
+        failing[bit / 8] |= 0x80 >> (bit % 8);
+        cases.push(json!({
+            "hash": hex(&failing),
+            "target": hex(&target),
+            "valid": minimal::meets_target(&failing, &target),
+        }));
+    }
+    cases
+}
+
+fn pda(name: &str, seeds: &[&[u8]], program_id: &Pubkey) -> Value {
+    let (address, bump) = Pubkey::find_program_address(seeds, program_id);
+    json!({
+        "name": name,
+        "seeds": seeds.iter().map(|s| hex(s)).collect::<Vec<_>>(),
+        "program_id": program_id.to_string(),
+        "address": address.to_string(),
+        "bump": bump,
+    })
+}
+
+fn nlp_chain_pdas() -> Vec<Value> {
+    let id = nlp_chain::ID;
+    let (chain, target, author) = (key(1), key(2), key(3));
+    let tag_hash = hash(b"rust");
+    vec![
+        pda("block", &[b"block", chain.as_ref(), &0u64.to_le_bytes()], &id),
+        pda("block", &[b"block", chain.as_ref(), &7u64.to_le_bytes()], &id),
+        pda("dedup", &[b"dedup", chain.as_ref(), nlp_chain::dedup_key("hello").as_ref()], &id),
+        pda("contributor", &[b"contributor", chain.as_ref(), author.as_ref()], &id),
+        pda(
+            "author-index",
+            &[b"author-index", chain.as_ref(), author.as_ref(), &0u32.to_le_bytes()],
+            &id,
+        ),
+        pda("time-bucket", &[b"time-bucket", chain.as_ref(), &19_700i64.to_le_bytes()], &id),
+        pda("validator-set", &[b"validator-set", chain.as_ref(), &0u64.to_le_bytes()], &id),
+        pda("moderator", &[b"moderator", chain.as_ref(), author.as_ref()], &id),
+        pda(
+            "tag",
+            &[b"tag", chain.as_ref(), tag_hash.as_ref(), &7u64.to_le_bytes()],
+            &id,
+        ),
+        pda("tag-counter", &[b"tag-counter", chain.as_ref(), tag_hash.as_ref()], &id),
+        pda(
+            "citation",
+            &[b"citation", chain.as_ref(), &7u64.to_le_bytes(), &3u64.to_le_bytes()],
+            &id,
+        ),
+        pda("syndication", &[b"syndication", chain.as_ref(), target.as_ref()], &id),
+        pda("syndicated", &[b"syndicated", target.as_ref(), &4u64.to_le_bytes()], &id),
+        pda("checkpoint", &[b"checkpoint", chain.as_ref(), &0u64.to_le_bytes()], &id),
+        pda("archive", &[b"archive", chain.as_ref(), &0u64.to_le_bytes()], &id),
+        pda(
+            "summary",
+            &[b"summary", chain.as_ref(), &0u64.to_le_bytes(), &8u64.to_le_bytes()],
+            &id,
+        ),
+        pda("oracle", &[b"oracle", chain.as_ref(), author.as_ref()], &id),
+        pda("block-nft", &[b"block-nft", chain.as_ref(), &7u64.to_le_bytes()], &id),
+        pda("nft-authority", &[b"nft-authority", chain.as_ref()], &id),
+        pda(
+            "attestation",
+            &[b"attestation", chain.as_ref(), &7u64.to_le_bytes(), author.as_ref()],
+            &id,
+        ),
+    ]
+}
+
+fn minimal_pdas() -> Vec<Value> {
+    let id = minimal::ID;
+    let owner = key(3);
+    vec![
+        pda("user-profile", &[b"user-profile", owner.as_ref()], &id),
+        pda("proof", &[b"proof", owner.as_ref(), &0u64.to_le_bytes()], &id),
+        pda("proof-batch", &[b"proof-batch", owner.as_ref(), &[7; 32]], &id),
+        pda("listing", &[b"listing", key(4).as_ref()], &id),
+        pda("fee-tokens", &[b"fee-tokens"], &id),
+        pda("treasury", &[b"treasury"], &id),
+        pda("config", &[b"config"], &id),
+        pda("reward-mint", &[b"reward-mint"], &id),
+        pda("leaderboard", &[b"leaderboard"], &id),
+        pda("challenge", &[b"challenge"], &id),
+        pda("username", &[b"username", &minimal::username_seed("satoshi")], &id),
+    ]
+}
+
+fn account<T: AccountSerialize>(name: &str, account: &T) -> Result<Value> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data)?;
+    Ok(json!({ "name": name, "len": data.len(), "data": hex(&data) }))
+}
+
+// Full account serialization (discriminator + borsh body)
+fn accounts() -> Result<Vec<Value>> {
+    let data_hash = hash(b"hello");
+    Ok(vec![
+        account(
+            "ChainState",
+            &nlp_chain::ChainState {
+                version: nlp_chain::CHAIN_STATE_VERSION,
+                authority: key(3),
+                block_count: 2,
+                last_hash: data_hash,
+                bucket_interval: 86_400,
+                linked_count: 2,
+                ..Default::default()
+            },
+        )?,
+        account(
+            "Block",
+            &nlp_chain::Block {
+                version: nlp_chain::BLOCK_VERSION,
+                authority: key(3),
+                index: 1,
+                timestamp: 1_700_000_000,
+                text: "hello".to_string(),
+                vector: vec![0.5, -0.25, 1.0],
+                metadata: vec![
+                    nlp_chain::MetadataEntry::new(
+                        "source",
+                        nlp_chain::MetadataValue::Str("vectors".to_string()),
+                    ),
+                    nlp_chain::MetadataEntry::new("page", nlp_chain::MetadataValue::U64(3)),
+                ],
+                data_hash,
+                ..Default::default()
+            },
+        )?,
+        account(
+            "HashRegistry",
+            &nlp_chain::HashRegistry {
+                chain: key(1),
+                data_hash,
+                block_index: 1,
+                compressed: false,
+            },
+        )?,
+        account(
+            "UserProfile",
+            &minimal::UserProfile {
+                owner: key(3),
+                active: true,
+                created_at: 1_700_000_000,
+                ..Default::default()
+            },
+        )?,
+        account(
+            "ProofData",
+            &minimal::ProofData {
+                owner: key(3),
+                data_hash: [0; 32],
+                nonce: 42,
+                timestamp: 1_700_000_000,
+                verified: true,
+                consumed: false,
+                previous_proof: key(5),
+                height: 1,
+                expires_at: 1_700_086_400,
+                revoked: false,
+            },
+        )?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The checked-in fixtures must match what the programs compute today;
+    // regenerate them with `cargo run -p span-vectors` after a rule changes
+    #[test]
+    fn fixtures_are_current() -> Result<()> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..").join(DEFAULT_OUT_DIR);
+        for (rule, cases) in vectors()? {
+            let path = dir.join(format!("{rule}.json"));
+            let committed = fs::read_to_string(&path)?;
+            assert_eq!(committed, render(rule, &cases)?, "{} is stale", path.display());
+        }
+        Ok(())
+    }
+}